@@ -0,0 +1,2 @@
+-- Drop Users Table
+DROP TABLE IF EXISTS users;
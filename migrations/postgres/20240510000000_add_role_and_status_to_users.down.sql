@@ -0,0 +1,7 @@
+-- Remove role, account status and forced password reset from users
+DROP INDEX IF EXISTS idx_users_status;
+DROP INDEX IF EXISTS idx_users_role;
+
+ALTER TABLE users DROP COLUMN password_reset_required;
+ALTER TABLE users DROP COLUMN status;
+ALTER TABLE users DROP COLUMN role;
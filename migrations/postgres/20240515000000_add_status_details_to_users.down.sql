@@ -0,0 +1,3 @@
+-- Forget why and when account statuses changed
+ALTER TABLE users DROP COLUMN status_changed_at;
+ALTER TABLE users DROP COLUMN status_reason;
@@ -0,0 +1,3 @@
+-- Record why and when an account's status last changed
+ALTER TABLE users ADD COLUMN status_reason TEXT;
+ALTER TABLE users ADD COLUMN status_changed_at TIMESTAMPTZ;
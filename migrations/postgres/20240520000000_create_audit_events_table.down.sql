@@ -0,0 +1,5 @@
+-- Drop Audit Events Table
+DROP INDEX IF EXISTS idx_audit_events_event_type;
+DROP INDEX IF EXISTS idx_audit_events_actor_id;
+DROP INDEX IF EXISTS idx_audit_events_user_id;
+DROP TABLE IF EXISTS audit_events;
@@ -0,0 +1,20 @@
+-- Match emails and usernames regardless of case or surrounding whitespace
+-- Accounts that would collide must be merged or renamed by hand first
+DO $$
+DECLARE
+    conflicts TEXT;
+BEGIN
+    SELECT string_agg(value, ', ') INTO conflicts FROM (
+        SELECT lower(trim(email)) AS value FROM users GROUP BY 1 HAVING count(*) > 1
+        UNION ALL
+        SELECT lower(trim(username)) FROM users GROUP BY 1 HAVING count(*) > 1
+    ) duplicates;
+    IF conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'users differ only by case or whitespace in their email or username (%); resolve them before migrating', conflicts;
+    END IF;
+END $$;
+
+UPDATE users SET email = trim(email), username = trim(username);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_lower ON users (lower(email));
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_lower ON users (lower(username));
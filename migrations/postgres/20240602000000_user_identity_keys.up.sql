@@ -0,0 +1,9 @@
+-- Keys emails and usernames are matched on: NFKC-normalised, trimmed and
+-- lowercased with Unicode rules. SQL's case folding differs by backend (SQLite
+-- only folds ASCII), so the application computes them, filling them in for
+-- existing users right after migrating.
+ALTER TABLE users ADD COLUMN email_key TEXT;
+ALTER TABLE users ADD COLUMN username_key TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_key ON users (email_key);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_key ON users (username_key);
@@ -0,0 +1,17 @@
+-- Create Audit Events Table
+-- No foreign keys: audit history must outlive the users it refers to
+CREATE TABLE IF NOT EXISTS audit_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    actor_id INTEGER,
+    user_id INTEGER,
+    ip TEXT,
+    user_agent TEXT,
+    metadata TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_events_user_id ON audit_events (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_events_actor_id ON audit_events (actor_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_events_event_type ON audit_events (event_type, created_at);
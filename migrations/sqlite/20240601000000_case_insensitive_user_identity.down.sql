@@ -0,0 +1,3 @@
+-- Match emails and usernames exactly again; trimmed values stay trimmed
+DROP INDEX IF EXISTS idx_users_username_nocase;
+DROP INDEX IF EXISTS idx_users_email_nocase;
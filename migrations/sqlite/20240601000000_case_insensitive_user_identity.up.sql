@@ -0,0 +1,20 @@
+-- Match emails and usernames regardless of case or surrounding whitespace
+-- Accounts that would collide must be merged or renamed by hand first; list them with:
+--   SELECT lower(trim(email)) FROM users GROUP BY 1 HAVING count(*) > 1;
+--   SELECT lower(trim(username)) FROM users GROUP BY 1 HAVING count(*) > 1;
+CREATE TEMP TABLE user_identity_conflicts (value TEXT);
+CREATE TEMP TRIGGER refuse_user_identity_conflicts BEFORE INSERT ON user_identity_conflicts
+BEGIN
+    SELECT RAISE(ABORT, 'users differ only by case or whitespace in their email or username; resolve them before migrating');
+END;
+INSERT INTO user_identity_conflicts
+    SELECT lower(trim(email)) FROM users GROUP BY lower(trim(email)) HAVING count(*) > 1
+    UNION ALL
+    SELECT lower(trim(username)) FROM users GROUP BY lower(trim(username)) HAVING count(*) > 1;
+DROP TRIGGER refuse_user_identity_conflicts;
+DROP TABLE user_identity_conflicts;
+
+UPDATE users SET email = trim(email), username = trim(username);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_nocase ON users (email COLLATE NOCASE);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_nocase ON users (username COLLATE NOCASE);
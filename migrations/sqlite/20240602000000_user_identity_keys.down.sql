@@ -0,0 +1,5 @@
+-- Match emails and usernames by the case-insensitive indexes alone again
+DROP INDEX IF EXISTS idx_users_username_key;
+DROP INDEX IF EXISTS idx_users_email_key;
+ALTER TABLE users DROP COLUMN username_key;
+ALTER TABLE users DROP COLUMN email_key;
@@ -0,0 +1,358 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, error};
+
+use crate::AppState;
+use crate::api::context::RequestContext;
+use crate::middleware::auth::{AuthMethod, CurrentUser};
+use crate::models::audit_event::{AuditEventType, NewAuditEvent};
+use crate::models::user::{Role, User, UserFilter, UserStatus};
+use crate::services::admin_service::{AdminError, DEFAULT_PAGE_SIZE};
+use crate::services::cookie_service::{ACCESS_TOKEN_COOKIE, CookieService};
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    search: Option<String>,
+    role: Option<Role>,
+    status: Option<UserStatus>,
+}
+
+#[derive(Deserialize)]
+pub struct SetRoleRequest {
+    role: Role,
+}
+
+#[derive(Deserialize)]
+pub struct SetStatusRequest {
+    status: UserStatus,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StatusReasonRequest {
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AdminUserResponse {
+    id: i64,
+    username: String,
+    email: String,
+    role: Role,
+    status: UserStatus,
+    status_reason: Option<String>,
+    status_changed_at: Option<DateTime<Utc>>,
+    password_reset_required: bool,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ListUsersResponse {
+    users: Vec<AdminUserResponse>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+#[derive(Serialize)]
+pub struct AdminActionResponse {
+    message: String,
+    success: bool,
+}
+
+#[derive(Serialize)]
+pub struct RevokeSessionsResponse {
+    message: String,
+    success: bool,
+    revoked: usize,
+}
+
+#[derive(Serialize)]
+pub struct ImpersonationResponse {
+    message: String,
+    success: bool,
+    user_id: i64,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            status: user.status,
+            status_reason: user.status_reason,
+            status_changed_at: user.status_changed_at,
+            password_reset_required: user.password_reset_required,
+            created_at: user.created_at,
+        }
+    }
+}
+
+fn admin_error_status(e: AdminError) -> StatusCode {
+    match e {
+        AdminError::UserNotFound => StatusCode::NOT_FOUND,
+        AdminError::SelfModification => StatusCode::BAD_REQUEST,
+        AdminError::ImpersonationNotAllowed => StatusCode::FORBIDDEN,
+        _ => {
+            error!("Admin action failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn record_status_change(state: &AppState, context: &RequestContext, actor: &User, user: &User) {
+    state.audit_log
+        .record(
+            context,
+            NewAuditEvent::success(AuditEventType::StatusChanged)
+                .actor(actor.id)
+                .user(user.id)
+                .metadata(json!({ "status": user.status, "reason": user.status_reason })),
+        )
+        .await;
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<ListUsersResponse>, StatusCode> {
+    let filter = UserFilter {
+        search: query.search.filter(|s| !s.trim().is_empty()),
+        role: query.role,
+        status: query.status,
+    };
+
+    let page = state.admin_service
+        .list_users(&filter, query.page.unwrap_or(1), query.per_page.unwrap_or(DEFAULT_PAGE_SIZE))
+        .await
+        .map_err(admin_error_status)?;
+
+    Ok(Json(ListUsersResponse {
+        users: page.users.into_iter().map(AdminUserResponse::from).collect(),
+        page: page.page,
+        per_page: page.per_page,
+        total: page.total,
+    }))
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<i64>,
+) -> Result<Json<AdminUserResponse>, StatusCode> {
+    let user = state.admin_service
+        .get_user(user_id)
+        .await
+        .map_err(admin_error_status)?;
+
+    Ok(Json(user.into()))
+}
+
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+    payload: Option<Json<StatusReasonRequest>>,
+) -> Result<Json<AdminUserResponse>, StatusCode> {
+    let reason = payload.and_then(|Json(payload)| payload.reason);
+    let user = state.admin_service
+        .set_status(Some(&current_user.0), user_id, UserStatus::Disabled, reason.as_deref())
+        .await
+        .map_err(admin_error_status)?;
+    record_status_change(&state, &context, &current_user.0, &user).await;
+
+    info!("Admin {} disabled user {}", current_user.0.id, user_id);
+    Ok(Json(user.into()))
+}
+
+pub async fn enable_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+) -> Result<Json<AdminUserResponse>, StatusCode> {
+    let user = state.admin_service
+        .set_status(Some(&current_user.0), user_id, UserStatus::Active, None)
+        .await
+        .map_err(admin_error_status)?;
+    record_status_change(&state, &context, &current_user.0, &user).await;
+
+    info!("Admin {} enabled user {}", current_user.0.id, user_id);
+    Ok(Json(user.into()))
+}
+
+pub async fn set_status(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+    Json(payload): Json<SetStatusRequest>,
+) -> Result<Json<AdminUserResponse>, StatusCode> {
+    let user = state.admin_service
+        .set_status(Some(&current_user.0), user_id, payload.status, payload.reason.as_deref())
+        .await
+        .map_err(admin_error_status)?;
+    record_status_change(&state, &context, &current_user.0, &user).await;
+
+    info!("Admin {} set status of user {} to {:?}", current_user.0.id, user_id, payload.status);
+    Ok(Json(user.into()))
+}
+
+pub async fn set_role(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+    Json(payload): Json<SetRoleRequest>,
+) -> Result<Json<AdminUserResponse>, StatusCode> {
+    let user = state.admin_service
+        .set_role(Some(&current_user.0), user_id, payload.role)
+        .await
+        .map_err(admin_error_status)?;
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::RoleChanged)
+                .actor(current_user.0.id)
+                .user(user.id)
+                .metadata(json!({ "role": user.role })),
+        )
+        .await;
+
+    info!("Admin {} set role of user {} to {:?}", current_user.0.id, user_id, payload.role);
+    Ok(Json(user.into()))
+}
+
+pub async fn force_password_reset(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+) -> Result<Json<AdminActionResponse>, StatusCode> {
+    state.admin_service
+        .force_password_reset(user_id)
+        .await
+        .map_err(admin_error_status)?;
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::PasswordResetForced)
+                .actor(current_user.0.id)
+                .user(user_id),
+        )
+        .await;
+
+    info!("Admin {} forced password reset for user {}", current_user.0.id, user_id);
+    Ok(Json(AdminActionResponse {
+        message: "Password reset required; reset link sent".to_string(),
+        success: true,
+    }))
+}
+
+pub async fn revoke_sessions(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    Path(user_id): Path<i64>,
+) -> Result<Json<RevokeSessionsResponse>, StatusCode> {
+    let revoked = state.admin_service
+        .revoke_sessions(user_id)
+        .await
+        .map_err(admin_error_status)?;
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::SessionsRevoked)
+                .actor(current_user.0.id)
+                .user(user_id)
+                .metadata(json!({ "revoked": revoked })),
+        )
+        .await;
+
+    info!("Admin {} revoked all sessions of user {}", current_user.0.id, user_id);
+    Ok(Json(RevokeSessionsResponse {
+        message: "All sessions revoked".to_string(),
+        success: true,
+        revoked,
+    }))
+}
+
+/// Swap the admin's access token for one acting as `user_id`. The admin's
+/// refresh token is kept, so `/refresh` returns them to their own session.
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    context: RequestContext,
+    cookies: CookieService,
+    Path(user_id): Path<i64>,
+) -> Result<(HeaderMap, Json<ImpersonationResponse>), StatusCode> {
+    let token = state.admin_service
+        .impersonate(&current_user.0, user_id)
+        .await
+        .map_err(admin_error_status)?;
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::ImpersonationStarted)
+                .actor(current_user.0.id)
+                .user(user_id),
+        )
+        .await;
+
+    info!("Admin {} is impersonating user {}", current_user.0.id, user_id);
+    Ok((
+        cookies.set_access_cookie(&token),
+        Json(ImpersonationResponse {
+            message: "Impersonation started".to_string(),
+            success: true,
+            user_id,
+        }),
+    ))
+}
+
+pub async fn stop_impersonation(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Extension(auth_method): Extension<AuthMethod>,
+    context: RequestContext,
+    cookies: CookieService,
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<AdminActionResponse>), StatusCode> {
+    let actor_id = auth_method.actor_id().ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Clearing the cookie is not enough: a copy of the token would keep working until it expires
+    if let Some(access_token) = CookieService::extract_token(&headers, ACCESS_TOKEN_COOKIE) {
+        state.jwt_service.revoke_access_token(&access_token).await.map_err(|e| {
+            error!("Failed to revoke impersonation token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::ImpersonationStopped)
+                .actor(actor_id)
+                .user(current_user.0.id),
+        )
+        .await;
+    info!("Admin {} stopped impersonating user {}", actor_id, current_user.0.id);
+
+    Ok((
+        cookies.clear_access_cookie(),
+        Json(AdminActionResponse {
+            message: "Impersonation stopped".to_string(),
+            success: true,
+        }),
+    ))
+}
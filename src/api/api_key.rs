@@ -0,0 +1,170 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, error, debug};
+
+use crate::AppState;
+use crate::api::context::RequestContext;
+use crate::middleware::auth::{AuthMethod, CurrentUser};
+use crate::models::api_key::ApiKey;
+use crate::models::audit_event::{AuditEventType, NewAuditEvent};
+use crate::services::api_key_service::{ApiKeyError, MAX_EXPIRES_IN_DAYS};
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    name: String,
+    scopes: Vec<String>,
+    expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    id: i64,
+    name: String,
+    prefix: String,
+    scopes: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct CreateApiKeyResponse {
+    message: String,
+    success: bool,
+    key: String,
+    api_key: ApiKeyResponse,
+}
+
+#[derive(Serialize)]
+pub struct ListApiKeysResponse {
+    api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Serialize)]
+pub struct DeleteApiKeyResponse {
+    message: String,
+    success: bool,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            scopes: api_key.scopes().into_iter().map(str::to_string).collect(),
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            created_at: api_key.created_at,
+        }
+    }
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Extension(auth_method): Extension<AuthMethod>,
+    context: RequestContext,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), StatusCode> {
+    debug!(user_id = %current_user.0.id, "API key creation attempt");
+    auth_method.require_session()?;
+
+    let expiry_out_of_range = payload.expires_in_days.is_some_and(|days| !(1..=MAX_EXPIRES_IN_DAYS).contains(&days));
+    if payload.name.trim().is_empty() || expiry_out_of_range {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let created = state.api_key_service
+        .create_key(current_user.0.id, payload.name.trim(), &payload.scopes, payload.expires_in_days)
+        .await
+        .map_err(|e| match e {
+            ApiKeyError::InvalidScope(_) | ApiKeyError::InvalidExpiry(_) => {
+                info!("Rejected API key creation: {}", e);
+                StatusCode::BAD_REQUEST
+            },
+            _ => {
+                error!("Failed to create API key: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::ApiKeyCreated)
+                .user(current_user.0.id)
+                .metadata(json!({ "key_id": created.api_key.id, "prefix": created.api_key.prefix })),
+        )
+        .await;
+
+    info!("Created API key {} for user {}", created.api_key.id, current_user.0.id);
+    Ok((StatusCode::CREATED, Json(CreateApiKeyResponse {
+        message: "API key created".to_string(),
+        success: true,
+        key: created.key,
+        api_key: created.api_key.into(),
+    })))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Extension(auth_method): Extension<AuthMethod>,
+) -> Result<Json<ListApiKeysResponse>, StatusCode> {
+    auth_method.require_scope("api_keys:read")?;
+
+    let api_keys = state.api_key_service
+        .list_keys(current_user.0.id)
+        .await
+        .map_err(|e| {
+            error!("Failed to list API keys: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(ListApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKeyResponse::from).collect(),
+    }))
+}
+
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Extension(auth_method): Extension<AuthMethod>,
+    context: RequestContext,
+    Path(key_id): Path<i64>,
+) -> Result<Json<DeleteApiKeyResponse>, StatusCode> {
+    auth_method.require_session()?;
+
+    state.api_key_service
+        .revoke_key(current_user.0.id, key_id)
+        .await
+        .map_err(|e| match e {
+            ApiKeyError::NotFound => StatusCode::NOT_FOUND,
+            _ => {
+                error!("Failed to revoke API key: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    state.audit_log
+        .record(
+            &context,
+            NewAuditEvent::success(AuditEventType::ApiKeyRevoked)
+                .user(current_user.0.id)
+                .metadata(json!({ "key_id": key_id })),
+        )
+        .await;
+
+    info!("Revoked API key {} for user {}", key_id, current_user.0.id);
+    Ok(Json(DeleteApiKeyResponse {
+        message: "API key revoked".to_string(),
+        success: true,
+    }))
+}
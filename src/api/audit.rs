@@ -0,0 +1,121 @@
+use axum::{
+    Json,
+    extract::{Extension, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::AppState;
+use crate::middleware::auth::{AuthMethod, CurrentUser};
+use crate::models::audit_event::{AuditEvent, AuditEventFilter, AuditEventType, AuditOutcome};
+use crate::services::admin_service::DEFAULT_PAGE_SIZE;
+
+#[derive(Deserialize)]
+pub struct SecurityEventsQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditEventsQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    user_id: Option<i64>,
+    actor_id: Option<i64>,
+    event_type: Option<AuditEventType>,
+    outcome: Option<AuditOutcome>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct AuditEventResponse {
+    id: i64,
+    event_type: AuditEventType,
+    outcome: AuditOutcome,
+    actor_id: Option<i64>,
+    user_id: Option<i64>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    metadata: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ListAuditEventsResponse {
+    events: Vec<AuditEventResponse>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            metadata: event.metadata.and_then(|m| serde_json::from_str(&m).ok()),
+            id: event.id,
+            event_type: event.event_type,
+            outcome: event.outcome,
+            actor_id: event.actor_id,
+            user_id: event.user_id,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            created_at: event.created_at,
+        }
+    }
+}
+
+async fn list_events(
+    state: &AppState,
+    filter: AuditEventFilter,
+    page: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<Json<ListAuditEventsResponse>, StatusCode> {
+    let page = state.audit_log
+        .list(&filter, page.unwrap_or(1), per_page.unwrap_or(DEFAULT_PAGE_SIZE))
+        .await
+        .map_err(|e| {
+            error!("Failed to list audit events: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(ListAuditEventsResponse {
+        events: page.events.into_iter().map(AuditEventResponse::from).collect(),
+        page: page.page,
+        per_page: page.per_page,
+        total: page.total,
+    }))
+}
+
+/// Security events concerning the current user, newest first.
+pub async fn list_my_security_events(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Extension(auth_method): Extension<AuthMethod>,
+    Query(query): Query<SecurityEventsQuery>,
+) -> Result<Json<ListAuditEventsResponse>, StatusCode> {
+    auth_method.require_scope("user:read")?;
+
+    let filter = AuditEventFilter {
+        user_id: Some(current_user.0.id),
+        ..Default::default()
+    };
+    list_events(&state, filter, query.page, query.per_page).await
+}
+
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    Query(query): Query<AuditEventsQuery>,
+) -> Result<Json<ListAuditEventsResponse>, StatusCode> {
+    let filter = AuditEventFilter {
+        user_id: query.user_id,
+        actor_id: query.actor_id,
+        event_type: query.event_type,
+        outcome: query.outcome,
+        since: query.since,
+        until: query.until,
+    };
+    list_events(&state, filter, query.page, query.per_page).await
+}
@@ -1,19 +1,25 @@
 use axum::{
     Json,
     http::{StatusCode, HeaderMap},
-    extract::State,
+    extract::{Query, State},
 };
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tracing::{info, error, debug};
 
-use crate::services::auth_service::AuthError;
+use crate::api::context::RequestContext;
+use crate::api::error::ApiError;
+use crate::models::audit_event::{AuditEventType, NewAuditEvent};
+use crate::services::auth_service::{AuthError, Registration};
 use crate::AppState;
-use jsonwebtoken::errors::ErrorKind;
-use crate::services::cookie_service::{CookieService, REFRESH_TOKEN_COOKIE};
+use crate::services::cookie_service::{CookieService, MAGIC_LINK_BINDING_COOKIE, REFRESH_TOKEN_COOKIE};
+use crate::services::magic_link_service::MagicLinkService;
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
-    email: String,
+    /// Email address or username; sent as `email` or `username` too
+    #[serde(alias = "email", alias = "username")]
+    login: String,
     password: String,
 }
 
@@ -24,11 +30,34 @@ pub struct RegisterRequest {
     email: String,
 }
 
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkVerifyQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetQuery {
+    token: String,
+}
+
 #[derive(Serialize)]
 pub struct RegisterResponse {
     message: String,
     success: bool,
-    id: i64,
+    /// Left out when registration is enumeration resistant, as it would tell new accounts apart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -37,6 +66,18 @@ pub struct LoginResponse {
     success: bool,
 }
 
+#[derive(Serialize)]
+pub struct PasswordResetResponse {
+    message: String,
+    success: bool,
+}
+
+#[derive(Serialize)]
+pub struct MagicLinkResponse {
+    message: String,
+    success: bool,
+}
+
 #[derive(Serialize)]
 pub struct RefreshTokenResponse {
     message: String,
@@ -51,27 +92,51 @@ pub struct LogoutResponse {
 
 pub async fn login(
     State(state): State<AppState>,
+    context: RequestContext,
+    cookies: CookieService,
     Json(payload): Json<LoginRequest>,
-) -> Result<(HeaderMap, Json<LoginResponse>), StatusCode> {
-    debug!("Login attempt for email: {}", payload.email);
+) -> Result<(HeaderMap, Json<LoginResponse>), ApiError> {
+    debug!("Login attempt");
     
-    let token_pair = state.auth_service
-        .login(&payload.email, &payload.password)
-        .await
+    let attempt = state.auth_service
+        .login(&payload.login, &payload.password)
+        .await;
+    let result = attempt.result;
+
+    let event = match &result {
+        Ok(token_pair) => NewAuditEvent::success(AuditEventType::Login).user(token_pair.user_id),
+        Err(e) => {
+            let mut metadata = json!({ "reason": e.to_string() });
+            let key = if payload.login.contains('@') { "email" } else { "username" };
+            metadata[key] = json!(payload.login);
+            // Attempts against an existing account show up in its owner's security events
+            let event = NewAuditEvent::failure(AuditEventType::Login).metadata(metadata);
+            match attempt.user_id {
+                Some(user_id) => event.user(user_id),
+                None => event,
+            }
+        }
+    };
+    state.audit_log.record(&context, event).await;
+
+    let token_pair = result
         .map_err(|e| match e {
             AuthError::InvalidCredentials => {
-                error!("Invalid credentials for email: {}", payload.email);
-                StatusCode::UNAUTHORIZED
+                info!("Login rejected: invalid credentials");
+                ApiError::Status(StatusCode::UNAUTHORIZED)
             },
+            AuthError::AccountInactive(status) => ApiError::AccountInactive(status),
+            AuthError::PasswordResetRequired => ApiError::Status(StatusCode::FORBIDDEN),
+            AuthError::Overloaded => ApiError::Overloaded,
             _ => {
-                error!("Internal server error during login: {:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
+                error!("Internal server error during login: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
             }
         })?;
 
     // Set auth cookies
-    let headers = CookieService::set_auth_cookies(&token_pair.access_token, &token_pair.refresh_token);
-    info!("User successfully logged in: {}", payload.email);
+    let headers = cookies.set_auth_cookies(&token_pair.access_token, &token_pair.refresh_token);
+    info!("User {} successfully logged in", token_pair.user_id);
 
     Ok((headers, Json(LoginResponse { 
         message: "Login successful".to_string(),
@@ -79,32 +144,144 @@ pub async fn login(
     })))
 }
 
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    cookies: CookieService,
+    Json(payload): Json<MagicLinkRequest>,
+) -> (HeaderMap, Json<MagicLinkResponse>) {
+    debug!("Magic link requested");
+
+    let nonce = MagicLinkService::new_binding_nonce();
+    let ttl = state.magic_link_service.ttl();
+
+    // Look the account up and send the link in the background, so the
+    // response comes back at the same point whether or not it exists.
+    let magic_link_service = state.magic_link_service.clone();
+    let binding_nonce = nonce.clone();
+    tokio::spawn(async move {
+        if let Err(e) = magic_link_service.send_link(&payload.email, &binding_nonce).await {
+            match e {
+                AuthError::UserNotFound => debug!("No account for magic link request"),
+                _ => error!("Failed to send magic link: {}", e),
+            }
+        }
+    });
+
+    let headers = cookies.set_magic_link_binding_cookie(
+        &nonce,
+        time::Duration::seconds(ttl.num_seconds()),
+    );
+
+    (headers, Json(MagicLinkResponse {
+        message: "If an account exists for this email, a sign-in link has been sent".to_string(),
+        success: true,
+    }))
+}
+
+pub async fn verify_magic_link(
+    State(state): State<AppState>,
+    context: RequestContext,
+    cookies: CookieService,
+    headers: HeaderMap,
+    Query(query): Query<MagicLinkVerifyQuery>,
+) -> Result<(HeaderMap, Json<LoginResponse>), ApiError> {
+    debug!("Magic link verification attempt");
+
+    let nonce = CookieService::extract_token(&headers, MAGIC_LINK_BINDING_COOKIE);
+
+    let result = state.magic_link_service
+        .verify_link(&query.token, nonce.as_deref())
+        .await;
+
+    let event = match &result {
+        Ok(token_pair) => NewAuditEvent::success(AuditEventType::MagicLinkLogin).user(token_pair.user_id),
+        Err(e) => NewAuditEvent::failure(AuditEventType::MagicLinkLogin)
+            .metadata(json!({ "reason": e.to_string() })),
+    };
+    state.audit_log.record(&context, event).await;
+
+    let token_pair = result
+        .map_err(|e| match e {
+            AuthError::InvalidToken | AuthError::UserNotFound => {
+                info!("Magic link rejected: {}", e);
+                ApiError::Status(StatusCode::UNAUTHORIZED)
+            },
+            AuthError::AccountInactive(status) => ApiError::AccountInactive(status),
+            _ => {
+                error!("Internal server error during magic link login: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })?;
+
+    let mut headers = cookies.set_auth_cookies(&token_pair.access_token, &token_pair.refresh_token);
+    cookies.clear_magic_link_binding_cookie(&mut headers);
+    info!("User successfully logged in via magic link");
+
+    Ok((headers, Json(LoginResponse {
+        message: "Login successful".to_string(),
+        success: true,
+    })))
+}
+
 pub async fn register(
     State(state): State<AppState>,
+    context: RequestContext,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<RegisterResponse>, StatusCode> {
-    debug!("Registration attempt for email: {}", payload.email);
+) -> Result<Json<RegisterResponse>, ApiError> {
+    debug!("Registration attempt");
 
-    let id = state.auth_service
-        .register(&payload.username, &payload.password, &payload.email)
-        .await
-        .map_err(|e| {
-            error!("Failed to register user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+    let result = state.auth_service
+        .register_or_notify(&payload.username, &payload.password, &payload.email)
+        .await;
+
+    let event = match &result {
+        Ok(Registration::Created(id) | Registration::Notified { user_id: Some(id) }) => {
+            NewAuditEvent::success(AuditEventType::Registration).user(*id)
+        }
+        Ok(Registration::Notified { user_id: None }) => NewAuditEvent::failure(AuditEventType::Registration)
+            .metadata(json!({ "email": payload.email, "reason": AuthError::AccountExists.to_string() })),
+        Err(e) => NewAuditEvent::failure(AuditEventType::Registration)
+            .metadata(json!({ "email": payload.email, "reason": e.to_string() })),
+    };
+    state.audit_log.record(&context, event).await;
+
+    let registration = result
+        .map_err(|e| match e {
+            AuthError::InvalidUsername => ApiError::Status(StatusCode::BAD_REQUEST),
+            // The same for a taken email and a taken username
+            AuthError::AccountExists => ApiError::Status(StatusCode::CONFLICT),
+            AuthError::BreachedPassword => ApiError::BreachedPassword,
+            AuthError::Overloaded => ApiError::Overloaded,
+            _ => {
+                error!("Failed to register user: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
         })?;
 
-    info!("Successfully registered new user with id: {}", id);
-    Ok(Json(RegisterResponse { 
-        message: "Registration successful".to_string(),
-        success: true,
-        id,
-    }))
+    let response = match registration {
+        Registration::Created(id) => {
+            info!("Successfully registered new user with id: {}", id);
+            RegisterResponse {
+                message: "Registration successful".to_string(),
+                success: true,
+                id: Some(id),
+            }
+        }
+        Registration::Notified { .. } => RegisterResponse {
+            message: "Check your email to finish registering".to_string(),
+            success: true,
+            id: None,
+        },
+    };
+    Ok(Json(response))
 }
 
 pub async fn refresh_token(
     State(state): State<AppState>,
+    context: RequestContext,
+    cookies: CookieService,
     headers: HeaderMap,
-) -> Result<(HeaderMap, Json<RefreshTokenResponse>), StatusCode> {
+) -> Result<(HeaderMap, Json<RefreshTokenResponse>), ApiError> {
     debug!("Token refresh attempt");
 
     let refresh_token = CookieService::extract_token(&headers, REFRESH_TOKEN_COOKIE)
@@ -113,22 +290,32 @@ pub async fn refresh_token(
             StatusCode::UNAUTHORIZED
         })?;
 
-    let new_access_token = state.jwt_service
+    let result = state.jwt_service
         .refresh_tokens(&refresh_token)
-        .await
-        .map_err(|e| match e.kind() {
-            ErrorKind::InvalidToken => {
+        .await;
+
+    let event = match &result {
+        Ok(token_pair) => NewAuditEvent::success(AuditEventType::TokenRefresh).user(token_pair.user_id),
+        Err(e) => NewAuditEvent::failure(AuditEventType::TokenRefresh)
+            .metadata(json!({ "reason": e.to_string() })),
+    };
+    state.audit_log.record(&context, event).await;
+
+    let new_access_token = result
+        .map_err(|e| match e {
+            AuthError::InvalidToken => {
                 error!("Invalid refresh token provided");
-                StatusCode::UNAUTHORIZED
+                ApiError::Status(StatusCode::UNAUTHORIZED)
             },
+            AuthError::AccountInactive(status) => ApiError::AccountInactive(status),
             _ => {
-                error!("Internal server error during token refresh: {:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
+                error!("Internal server error during token refresh: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
             }
         })?;
 
     // Set auth cookies
-    let headers = CookieService::set_auth_cookies(&new_access_token.access_token, &new_access_token.refresh_token);
+    let headers = cookies.set_auth_cookies(&new_access_token.access_token, &new_access_token.refresh_token);
     info!("Successfully refreshed tokens");
 
     Ok((headers, Json(RefreshTokenResponse { 
@@ -139,6 +326,8 @@ pub async fn refresh_token(
 
 pub async fn logout(
     State(state): State<AppState>,
+    context: RequestContext,
+    cookies: CookieService,
     headers: HeaderMap,
 ) -> Result<(HeaderMap, Json<LogoutResponse>), StatusCode> {
     debug!("Logout attempt");
@@ -150,16 +339,19 @@ pub async fn logout(
         })?;
     
     // Revoke the refresh token
-    state.jwt_service
+    let user_id = state.jwt_service
         .revoke_token(&refresh_token)
         .await
         .map_err(|e| {
             error!("Failed to revoke refresh token: {:?}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    state.audit_log
+        .record(&context, NewAuditEvent::success(AuditEventType::Logout).user(user_id))
+        .await;
 
     // Clear auth cookies
-    let headers = CookieService::clear_auth_cookies();
+    let headers = cookies.clear_auth_cookies();
     info!("User successfully logged out");
     
     Ok((headers, Json(LogoutResponse { 
@@ -167,3 +359,64 @@ pub async fn logout(
         success: true,
     })))
 }
+
+/// Landing point of the emailed reset link when no `PASSWORD_RESET_URL` page
+/// is configured: checks the token without using it up.
+pub async fn check_password_reset(
+    State(state): State<AppState>,
+    Query(query): Query<PasswordResetQuery>,
+) -> Result<Json<PasswordResetResponse>, ApiError> {
+    debug!("Password reset link check");
+
+    state.password_reset_service
+        .check_token(&query.token)
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidToken => ApiError::Status(StatusCode::UNAUTHORIZED),
+            _ => {
+                error!("Internal server error during password reset link check: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })?;
+
+    Ok(Json(PasswordResetResponse {
+        message: "Reset link is valid, POST the token and a new password to /password-reset".to_string(),
+        success: true,
+    }))
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<Json<PasswordResetResponse>, ApiError> {
+    debug!("Password reset attempt");
+
+    let result = state.password_reset_service
+        .reset_password(&payload.token, &payload.new_password)
+        .await;
+
+    let event = match &result {
+        Ok(user_id) => NewAuditEvent::success(AuditEventType::PasswordReset).user(*user_id),
+        Err(e) => NewAuditEvent::failure(AuditEventType::PasswordReset)
+            .metadata(json!({ "reason": e.to_string() })),
+    };
+    state.audit_log.record(&context, event).await;
+
+    let user_id = result
+        .map_err(|e| match e {
+            AuthError::InvalidToken | AuthError::UserNotFound => ApiError::Status(StatusCode::UNAUTHORIZED),
+            AuthError::BreachedPassword => ApiError::BreachedPassword,
+            AuthError::Overloaded => ApiError::Overloaded,
+            _ => {
+                error!("Internal server error during password reset: {}", e);
+                ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })?;
+
+    info!("Password reset for user {}", user_id);
+    Ok(Json(PasswordResetResponse {
+        message: "Password has been reset".to_string(),
+        success: true,
+    }))
+}
@@ -0,0 +1,41 @@
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use crate::api::forwarded::ClientIp;
+use crate::config::TrustedProxies;
+
+/// Client details recorded alongside security events.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestContext
+where
+    TrustedProxies: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Absent when the router is not served with connect info (e.g. in tests)
+        let ip = Option::<ClientIp>::from_request_parts(parts, state)
+            .await?
+            .map(|ClientIp(ip)| ip.to_string());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(Self { ip, user_agent })
+    }
+}
@@ -0,0 +1,69 @@
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::models::user::UserStatus;
+
+/// Error returned by handlers and middleware. Most failures are a bare status
+/// code; refusals the client must be able to tell apart carry a JSON body.
+#[derive(Debug)]
+pub enum ApiError {
+    Status(StatusCode),
+    AccountInactive(UserStatus),
+    /// Password hashing is saturated; retrying shortly is expected to work
+    Overloaded,
+    /// The new password appears in a known data breach and must be changed
+    BreachedPassword,
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    error: &'static str,
+    message: String,
+    success: bool,
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::Status(status)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Status(status) => status.into_response(),
+            ApiError::AccountInactive(status) => (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: status.error_code(),
+                    message: "Account is not active".to_string(),
+                    success: false,
+                }),
+            )
+                .into_response(),
+            ApiError::Overloaded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                Json(ErrorResponse {
+                    error: "overloaded",
+                    message: "Too many requests in progress, try again shortly".to_string(),
+                    success: false,
+                }),
+            )
+                .into_response(),
+            ApiError::BreachedPassword => (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "breached_password",
+                    message: "This password has appeared in a data breach, choose another".to_string(),
+                    success: false,
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
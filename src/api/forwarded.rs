@@ -0,0 +1,205 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, request::Parts, uri},
+};
+
+use crate::config::TrustedProxies;
+
+const FORWARDED: &str = "forwarded";
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// The address of the client, looking through trusted proxies. Rejects with
+/// 500 when the server tagged the request with neither connect info nor `UnixPeer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Tags requests that arrived over a Unix socket. Only local processes can
+/// connect there, usually the reverse proxy in front, so the peer counts as a
+/// trusted proxy at the loopback address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnixPeer;
+
+/// The scheme the client used: what a trusted proxy reports, otherwise
+/// whether the connection to us is TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme(pub uri::Scheme);
+
+impl Scheme {
+    pub fn is_https(&self) -> bool {
+        self.0 == uri::Scheme::HTTPS
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    TrustedProxies: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        resolve_origin(parts, &TrustedProxies::from_ref(state))
+            .map(|origin| ClientIp(origin.ip))
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Client address unavailable"))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Scheme
+where
+    TrustedProxies: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let forwarded = resolve_origin(parts, &TrustedProxies::from_ref(state))
+            .and_then(|origin| origin.proto)
+            .and_then(|proto| match proto.as_str() {
+                "https" => Some(uri::Scheme::HTTPS),
+                "http" => Some(uri::Scheme::HTTP),
+                _ => None,
+            });
+        // The server tags each request with the scheme of its own connection
+        let connection = parts.extensions.get::<uri::Scheme>().cloned().unwrap_or(uri::Scheme::HTTP);
+
+        Ok(Scheme(forwarded.unwrap_or(connection)))
+    }
+}
+
+/// Where the request came from before any trusted proxies.
+struct Origin {
+    ip: IpAddr,
+    proto: Option<String>,
+}
+
+/// One entry of a forwarding header, describing the client of the proxy
+/// that added it.
+#[derive(Default)]
+struct Hop {
+    ip: Option<IpAddr>,
+    proto: Option<String>,
+}
+
+/// Walk the forwarding chain from the nearest hop outwards, stopping at the
+/// first address that is not a trusted proxy. Everything further left could
+/// have been written by that untrusted client.
+fn resolve_origin(parts: &Parts, proxies: &TrustedProxies) -> Option<Origin> {
+    let (ip, trusted) = match parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(peer)) => {
+            let ip = peer.ip().to_canonical();
+            (ip, proxies.contains(&ip))
+        }
+        None => {
+            parts.extensions.get::<UnixPeer>()?;
+            (IpAddr::V4(Ipv4Addr::LOCALHOST), true)
+        }
+    };
+    let mut origin = Origin { ip, proto: None };
+    if !trusted {
+        return Some(origin);
+    }
+
+    for hop in forwarded_hops(&parts.headers).into_iter().rev() {
+        if hop.proto.is_some() {
+            origin.proto = hop.proto;
+        }
+        // An obfuscated or unknown node hides everything beyond it
+        let Some(ip) = hop.ip else {
+            break;
+        };
+        origin.ip = ip;
+        if !proxies.contains(&ip) {
+            break;
+        }
+    }
+
+    Some(origin)
+}
+
+/// Hops from `Forwarded`, else `X-Forwarded-For`, else `X-Real-IP`, in the
+/// order they were added (client first).
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Hop> {
+    let forwarded = parse_forwarded(headers);
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    let mut hops: Vec<Hop> = header_list(headers, X_FORWARDED_FOR)
+        .map(|node| Hop { ip: parse_node(node), proto: None })
+        .collect();
+    if hops.is_empty() {
+        hops.extend(header_list(headers, X_REAL_IP).take(1).map(|node| Hop {
+            ip: parse_node(node),
+            proto: None,
+        }));
+    }
+
+    // One protocol per hop when the proxies keep the lists aligned, otherwise
+    // only the nearest proxy's value can be attributed
+    let protos: Vec<String> = header_list(headers, X_FORWARDED_PROTO).map(str::to_ascii_lowercase).collect();
+    if protos.len() == hops.len() {
+        for (hop, proto) in hops.iter_mut().zip(protos) {
+            hop.proto = Some(proto);
+        }
+    } else if let Some(proto) = protos.into_iter().last() {
+        match hops.last_mut() {
+            Some(hop) => hop.proto = Some(proto),
+            // Only the protocol was forwarded, not the client address
+            None => hops.push(Hop { ip: None, proto: Some(proto) }),
+        }
+    }
+
+    hops
+}
+
+/// RFC 7239: `for=192.0.2.60;proto=https, for="[2001:db8::1]:4711"`
+fn parse_forwarded(headers: &HeaderMap) -> Vec<Hop> {
+    header_list(headers, FORWARDED)
+        .map(|element| {
+            let mut hop = Hop::default();
+            for pair in element.split(';') {
+                let Some((key, value)) = pair.split_once('=') else {
+                    continue;
+                };
+                let value = value.trim().trim_matches('"');
+                match key.trim().to_ascii_lowercase().as_str() {
+                    "for" => hop.ip = parse_node(value),
+                    "proto" => hop.proto = Some(value.to_ascii_lowercase()),
+                    _ => {}
+                }
+            }
+            hop
+        })
+        .collect()
+}
+
+/// Comma-separated values across every instance of the header
+fn header_list<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// An address with an optional port, IPv6 possibly in brackets
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let ip = match node.strip_prefix('[') {
+        Some(rest) => rest.split(']').next()?.parse().ok()?,
+        None => node
+            .parse::<IpAddr>()
+            .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+            .ok()?,
+    };
+    Some(ip.to_canonical())
+}
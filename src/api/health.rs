@@ -0,0 +1,31 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+};
+use serde::Serialize;
+
+use crate::AppState;
+use crate::services::health_service::{HealthStatus, ReadinessReport};
+
+#[derive(Serialize)]
+pub struct LivenessResponse {
+    status: HealthStatus,
+}
+
+/// The process is up and serving requests. Dependencies are not checked, so
+/// an outage elsewhere does not get the container restarted.
+pub async fn live() -> Json<LivenessResponse> {
+    Json(LivenessResponse { status: HealthStatus::Ok })
+}
+
+/// Whether the service can handle traffic: 200 when ok or degraded, 503 when
+/// a required dependency is down or the server is shutting down.
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessReport>) {
+    let report = state.health_service.readiness().await;
+    let status = match report.status {
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+        HealthStatus::Unavailable | HealthStatus::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
@@ -1,2 +1,9 @@
 pub mod auth;
-pub mod user; 
\ No newline at end of file
+pub mod user;
+pub mod api_key;
+pub mod admin;
+pub mod error;
+pub mod context;
+pub mod forwarded;
+pub mod audit;
+pub mod health;
\ No newline at end of file
@@ -1,25 +1,32 @@
 use axum::{
     extract::Extension,
-    response::IntoResponse,
+    http::StatusCode,
     Json,
 };
 use serde::Serialize;
 
-use crate::middleware::auth::CurrentUser;
+use crate::middleware::auth::{AuthMethod, CurrentUser};
 
 #[derive(Serialize)]
 pub struct UserResponse {
     id: i64,
     username: String,
     email: String,
+    /// Id of the admin acting as this user, when impersonating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    impersonated_by: Option<i64>,
 }
 
 pub async fn get_current_user(
     Extension(current_user): Extension<CurrentUser>,
-) -> impl IntoResponse {
-    Json(UserResponse {
+    Extension(auth_method): Extension<AuthMethod>,
+) -> Result<Json<UserResponse>, StatusCode> {
+    auth_method.require_scope("user:read")?;
+
+    Ok(Json(UserResponse {
         id: current_user.0.id,
         username: current_user.0.username,
         email: current_user.0.email,
-    })
-} 
\ No newline at end of file
+        impersonated_by: auth_method.actor_id(),
+    }))
+}
@@ -0,0 +1,105 @@
+//! `migrate`: schema management, so a deploy can migrate as its own step.
+
+use std::process::ExitCode;
+
+use clap::Subcommand;
+use tracing::error;
+
+use crate::config::Config;
+use crate::db::{Database, MigrationState, MigrationStatus};
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// List every migration and whether it is applied
+    Status,
+    /// Revert the most recently applied migrations
+    #[command(alias = "revert")]
+    Down {
+        /// How many migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// Fail unless every migration is applied and unchanged
+    Check,
+}
+
+/// Run a `migrate` subcommand against the configured database. Migrations
+/// are never applied implicitly here, whatever `AUTO_MIGRATE` says.
+pub async fn migrate(config: &Config, command: MigrateCommand) -> ExitCode {
+    let db = match Database::connect(config).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!(error = %e, "Failed to connect to database");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = run_migrate(&db, command).await;
+    db.close().await;
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            error!(error = %e, "Migration failed");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `Ok(false)` when `check` finds the schema out of date.
+pub async fn run_migrate(db: &Database, command: MigrateCommand) -> Result<bool, sqlx::migrate::MigrateError> {
+    match command {
+        MigrateCommand::Up => {
+            let pending: Vec<_> = db
+                .migration_status()
+                .await?
+                .into_iter()
+                .filter(|m| m.state == MigrationState::Pending)
+                .collect();
+            db.migrate().await?;
+            if pending.is_empty() {
+                println!("Nothing to apply, the schema is up to date");
+            }
+            for migration in &pending {
+                println!("Applied {}", describe(migration));
+            }
+        }
+        MigrateCommand::Status => {
+            for migration in db.migration_status().await? {
+                println!("{:<9} {}", migration.state.name(), describe(&migration));
+            }
+        }
+        MigrateCommand::Down { steps } => {
+            let reverted = db.revert(steps).await?;
+            if reverted.is_empty() {
+                println!("Nothing to revert");
+            }
+            for migration in &reverted {
+                println!("Reverted {}", describe(migration));
+            }
+        }
+        MigrateCommand::Check => {
+            let outstanding: Vec<_> = db
+                .migration_status()
+                .await?
+                .into_iter()
+                .filter(|m| m.state != MigrationState::Applied)
+                .collect();
+            for migration in &outstanding {
+                println!("{:<9} {}", migration.state.name(), describe(migration));
+            }
+            if !outstanding.is_empty() {
+                error!(outstanding = outstanding.len(), "Database schema is not up to date");
+                return Ok(false);
+            }
+            println!("The schema is up to date");
+        }
+    }
+    Ok(true)
+}
+
+fn describe(migration: &MigrationStatus) -> String {
+    format!("{} {}", migration.version, migration.description).trim_end().to_string()
+}
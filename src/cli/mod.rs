@@ -0,0 +1,33 @@
+//! Command line: `serve` runs the API, the other subcommands are operator
+//! tools that run once and exit.
+
+use clap::{Parser, Subcommand};
+
+pub mod migrate;
+pub mod user;
+
+pub use migrate::{MigrateCommand, migrate};
+pub use user::{SessionsCommand, UserCommand, sessions, user};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Defaults to `serve`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Run the API server
+    Serve,
+    /// Manage the database schema
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Create and fix user accounts
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage signed-in sessions
+    #[command(subcommand)]
+    Sessions(SessionsCommand),
+}
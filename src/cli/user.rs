@@ -0,0 +1,294 @@
+//! `user` and `sessions`: account fixes for operators, going through the
+//! same services as the admin API so the outcome is identical.
+
+use std::convert::Infallible;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use clap::{Subcommand, ValueEnum};
+use serde_json::json;
+use tracing::error;
+
+use crate::api::context::RequestContext;
+use crate::config::Config;
+use crate::db;
+use crate::models::audit_event::{AuditEventType, NewAuditEvent};
+use crate::models::user::{Role, User, UserFilter, UserStatus};
+use crate::services::admin_service::{AdminError, DEFAULT_PAGE_SIZE};
+use crate::services::auth_service::AuthError;
+use crate::services::email_service::EmailService;
+use crate::AppState;
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum UserCommand {
+    /// Create a user, e.g. the first admin
+    Create {
+        #[arg(long)]
+        username: String,
+        #[arg(long)]
+        email: String,
+        /// Read from stdin when omitted, keeping it out of shell history
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(long, value_enum, default_value_t = Role::User)]
+        role: Role,
+    },
+    /// Replace a user's password and sign them out everywhere
+    SetPassword {
+        user: UserRef,
+        /// Read from stdin when omitted, keeping it out of shell history
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Disable an account and sign it out everywhere
+    Disable {
+        user: UserRef,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Make a disabled or locked account active again
+    Enable { user: UserRef },
+    /// Change a user's role
+    GrantRole {
+        user: UserRef,
+        #[arg(value_enum)]
+        role: Role,
+    },
+    /// List users by id
+    List {
+        /// Match against username or email
+        #[arg(long)]
+        search: Option<String>,
+        #[arg(long, value_enum)]
+        role: Option<Role>,
+        #[arg(long, value_enum)]
+        status: Option<UserStatus>,
+        #[arg(long, default_value_t = 1)]
+        page: i64,
+        #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+        per_page: i64,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum SessionsCommand {
+    /// Sign a user out everywhere
+    Revoke {
+        #[arg(long)]
+        user: UserRef,
+    },
+}
+
+/// A user named on the command line, by id or by email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserRef {
+    Id(i64),
+    Email(String),
+}
+
+impl FromStr for UserRef {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse().map_or_else(|_| UserRef::Email(s.to_string()), UserRef::Id))
+    }
+}
+
+pub async fn user(config: &Config, command: UserCommand) -> ExitCode {
+    let command = match command {
+        UserCommand::Create { username, email, password, role } => match read_password(password) {
+            Ok(password) => UserCommand::Create { username, email, password: Some(password), role },
+            Err(e) => return password_error(e),
+        },
+        UserCommand::SetPassword { user, password } => match read_password(password) {
+            Ok(password) => UserCommand::SetPassword { user, password: Some(password) },
+            Err(e) => return password_error(e),
+        },
+        command => command,
+    };
+
+    let Some(state) = connect(config).await else {
+        return ExitCode::FAILURE;
+    };
+    let result = run_user(&state, command).await;
+    state.db.close().await;
+    report(result)
+}
+
+pub async fn sessions(config: &Config, command: SessionsCommand) -> ExitCode {
+    let Some(state) = connect(config).await else {
+        return ExitCode::FAILURE;
+    };
+    let result = run_sessions(&state, command).await;
+    state.db.close().await;
+    report(result)
+}
+
+pub async fn run_user(state: &AppState, command: UserCommand) -> Result<(), AdminError> {
+    match command {
+        UserCommand::Create { username, email, password, role } => {
+            let password = password.unwrap_or_default();
+            let id = state.auth_service.register(&username, &password, &email).await?;
+            record(state, NewAuditEvent::success(AuditEventType::Registration).user(id), json!({})).await;
+
+            let user = if role == Role::User {
+                state.admin_service.get_user(id).await?
+            } else {
+                let user = state.admin_service.set_role(None, id, role).await?;
+                record(state, NewAuditEvent::success(AuditEventType::RoleChanged).user(id), json!({ "role": role })).await;
+                user
+            };
+            println!("Created {}", describe(&user));
+        }
+        UserCommand::SetPassword { user, password } => {
+            let user = find(state, &user).await?;
+            state.auth_service.set_password(user.id, &password.unwrap_or_default()).await?;
+            record(state, NewAuditEvent::success(AuditEventType::PasswordReset).user(user.id), json!({})).await;
+            println!("Set the password of {} and signed them out everywhere", describe(&user));
+        }
+        UserCommand::Disable { user, reason } => {
+            let user = find(state, &user).await?;
+            let user = state.admin_service.set_status(None, user.id, UserStatus::Disabled, reason.as_deref()).await?;
+            record_status_change(state, &user).await;
+            println!("Disabled {} and signed them out everywhere", describe(&user));
+        }
+        UserCommand::Enable { user } => {
+            let user = find(state, &user).await?;
+            let user = state.admin_service.set_status(None, user.id, UserStatus::Active, None).await?;
+            record_status_change(state, &user).await;
+            println!("Enabled {}", describe(&user));
+        }
+        UserCommand::GrantRole { user, role } => {
+            let user = find(state, &user).await?;
+            let user = state.admin_service.set_role(None, user.id, role).await?;
+            record(state, NewAuditEvent::success(AuditEventType::RoleChanged).user(user.id), json!({ "role": role })).await;
+            println!("Updated {}", describe(&user));
+        }
+        UserCommand::List { search, role, status, page, per_page } => {
+            let filter = UserFilter { search: search.filter(|s| !s.trim().is_empty()), role, status };
+            let page = state.admin_service.list_users(&filter, page, per_page).await?;
+            for user in &page.users {
+                println!(
+                    "{:>6}  {:<20}  {:<32}  {:<5}  {:<20}  {}",
+                    user.id,
+                    user.username,
+                    user.email,
+                    name(user.role),
+                    name(user.status),
+                    user.created_at.format("%Y-%m-%d %H:%M:%S"),
+                );
+            }
+            let pages = (page.total + page.per_page - 1) / page.per_page;
+            println!("Page {} of {}, {} users", page.page, pages.max(1), page.total);
+        }
+    }
+    Ok(())
+}
+
+pub async fn run_sessions(state: &AppState, command: SessionsCommand) -> Result<(), AdminError> {
+    match command {
+        SessionsCommand::Revoke { user } => {
+            let user = find(state, &user).await?;
+            let revoked = state.admin_service.revoke_sessions(user.id).await?;
+            record(
+                state,
+                NewAuditEvent::success(AuditEventType::SessionsRevoked).user(user.id),
+                json!({ "revoked": revoked }),
+            )
+            .await;
+            println!("Revoked {} sessions of {}", revoked, describe(&user));
+        }
+    }
+    Ok(())
+}
+
+/// The services the server would run with, migrating first if `AUTO_MIGRATE` allows.
+async fn connect(config: &Config) -> Option<AppState> {
+    let db = match db::create_db_pool(config).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!(error = %e, "Failed to connect to database");
+            return None;
+        }
+    };
+    let redis_store = match db::create_redis_store() {
+        Ok(store) => store,
+        Err(e) => {
+            error!(error = %e, "Failed to connect to Redis");
+            return None;
+        }
+    };
+    Some(crate::build_state(db, redis_store, config.clone(), EmailService::from_env()))
+}
+
+async fn find(state: &AppState, user: &UserRef) -> Result<User, AdminError> {
+    match user {
+        UserRef::Id(id) => state.admin_service.get_user(*id).await,
+        UserRef::Email(email) => state.users.find_by_email(email).await?.ok_or(AdminError::UserNotFound),
+    }
+}
+
+/// Command line changes have no actor or client to record, so the
+/// metadata says where they came from instead.
+async fn record(state: &AppState, event: NewAuditEvent, mut metadata: serde_json::Value) {
+    metadata["source"] = json!("cli");
+    state.audit_log.record(&RequestContext::default(), event.metadata(metadata)).await;
+}
+
+async fn record_status_change(state: &AppState, user: &User) {
+    let metadata = json!({ "status": user.status, "reason": user.status_reason });
+    record(state, NewAuditEvent::success(AuditEventType::StatusChanged).user(user.id), metadata).await;
+}
+
+/// The password given as an argument, or else one line of stdin.
+fn read_password(password: Option<String>) -> std::io::Result<String> {
+    let password = match password {
+        Some(password) => password,
+        None => {
+            let stdin = std::io::stdin();
+            if stdin.is_terminal() {
+                eprint!("Password: ");
+                std::io::stderr().flush()?;
+            }
+            let mut line = String::new();
+            stdin.lock().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if password.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the password must not be empty"));
+    }
+    Ok(password)
+}
+
+fn password_error(e: std::io::Error) -> ExitCode {
+    error!(error = %e, "Failed to read password");
+    ExitCode::FAILURE
+}
+
+fn report(result: Result<(), AdminError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(AdminError::Auth(AuthError::AccountExists)) => {
+            error!("A user with that email or username already exists");
+            ExitCode::FAILURE
+        }
+        Err(AdminError::Auth(AuthError::BreachedPassword)) => {
+            error!("That password appears in a known data breach, choose another");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            error!(error = %e, "Command failed");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn describe(user: &User) -> String {
+    format!("user {} {} <{}> ({}, {})", user.id, user.username, user.email, name(user.role), name(user.status))
+}
+
+fn name(value: impl ValueEnum) -> String {
+    value.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+}
@@ -0,0 +1,408 @@
+use dotenv::dotenv;
+use ipnet::IpNet;
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::services::password_hasher::{self, PasswordHashAlgorithm};
+
+/// Application settings read from the environment (and `.env`).
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// `sqlite:` or `postgres://` connection string; the scheme picks the backend
+    pub database_url: String,
+    /// Connection pool bounds, for either backend
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    /// How long a query waits for a free pooled connection before failing
+    pub db_acquire_timeout_secs: u64,
+    pub sqlite_journal_mode: SqliteJournalMode,
+    pub sqlite_synchronous: SqliteSynchronous,
+    /// How long a connection waits on a locked database before `SQLITE_BUSY`
+    pub sqlite_busy_timeout_ms: u64,
+    pub sqlite_foreign_keys: bool,
+    /// Create the database file on first start
+    pub sqlite_create_if_missing: bool,
+    /// Apply pending migrations on startup, rather than leaving them to `migrate up`
+    pub auto_migrate: bool,
+    /// Store email addresses with the part before `@` lowercased, not just the domain
+    pub email_lowercase_local_part: bool,
+    /// Answer `/register` the same whether or not the email or username is
+    /// taken, emailing the outcome instead
+    pub enumeration_resistant_registration: bool,
+    /// Algorithm for new password hashes; stored hashes of the other are upgraded at login
+    pub password_hash_algorithm: PasswordHashAlgorithm,
+    /// Argon2id memory cost in KiB, iterations and lanes
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    /// bcrypt work factor, between 4 and 31
+    pub bcrypt_cost: u32,
+    /// Password hashes run at once on blocking threads, and how many may wait
+    /// for one before requests get a 503
+    pub password_hash_max_concurrent: usize,
+    pub password_hash_max_queued: usize,
+    /// Sorted `HASH:COUNT` file of breached SHA-1 hashes that new passwords are checked against
+    pub breached_passwords_file: Option<PathBuf>,
+    /// Pwned Passwords compatible range API to check new passwords against, when there is no file
+    pub breached_passwords_api_url: Option<String>,
+    /// Breaches a password must have appeared in to be refused
+    pub breached_password_min_count: u64,
+    /// JWT signing secret
+    pub secret_key: String,
+    /// Externally reachable base URL, used to build links sent by email
+    pub public_url: String,
+    /// Lifetime of a magic login link in minutes
+    pub magic_link_ttl_minutes: i64,
+    /// Page password reset links point at, `{public_url}/password-reset` when unset
+    pub password_reset_url: Option<String>,
+    /// Lifetime of a password reset link in minutes
+    pub password_reset_ttl_minutes: i64,
+    /// Bearer token Prometheus must present to scrape `/metrics`; the endpoint
+    /// answers 404 while unset
+    pub metrics_token: Option<String>,
+    /// Service name attached to exported traces
+    pub service_name: String,
+    /// Base URL of an OTLP/HTTP collector; traces are only exported when set
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of new traces to sample, between 0.0 and 1.0
+    pub trace_sample_ratio: f64,
+    /// Whether the service is unready without Redis, rather than degraded
+    pub redis_required: bool,
+    /// How long to wait for in-flight requests after a shutdown signal
+    pub shutdown_timeout_secs: u64,
+    /// How long to keep accepting connections after a shutdown signal, with
+    /// readiness already failing, so load balancers stop routing here first
+    pub shutdown_readiness_delay_secs: u64,
+    /// Log filter directives, overridden by `RUST_LOG` when set
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// Interface to listen on
+    pub host: String,
+    pub port: u16,
+    /// Listen on this Unix socket instead of `host:port`
+    pub unix_socket: Option<PathBuf>,
+    /// PEM certificate chain; TLS is terminated here when both paths are set
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key for `tls_cert_path`
+    pub tls_key_path: Option<PathBuf>,
+    /// How often to check the certificate files for changes, 0 to never reload
+    pub tls_reload_interval_secs: u64,
+    pub cookie_secure: CookieSecure,
+    /// Proxies whose forwarding headers are believed
+    pub trusted_proxies: TrustedProxies,
+    pub environment: Environment,
+    /// Browser origins allowed to call the API with credentials
+    pub cors_allowed_origins: AllowedOrigins,
+    /// Response headers readable by cross-origin scripts
+    pub cors_exposed_headers: Vec<String>,
+    /// How long browsers may cache a preflight response
+    pub cors_max_age_secs: u64,
+    /// `Strict-Transport-Security` max-age, sent over HTTPS only; 0 disables
+    pub hsts_max_age_secs: u64,
+    pub hsts_include_subdomains: bool,
+    pub hsts_preload: bool,
+    /// Values for the fixed security headers; `None` omits the header
+    pub content_type_options: Option<String>,
+    pub frame_options: Option<String>,
+    pub content_security_policy: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub permissions_policy: Option<String>,
+    /// `Cache-Control` on responses carrying tokens or account data
+    pub auth_cache_control: Option<String>,
+}
+
+/// How log lines are written to stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines for local development
+    Pretty,
+    /// One JSON object per line for log aggregators
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format: {}", other)),
+        }
+    }
+}
+
+/// Whether auth cookies carry the `Secure` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CookieSecure {
+    /// Secure when the request arrived over TLS or a proxy reports HTTPS
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for CookieSecure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(CookieSecure::Auto),
+            "true" | "always" => Ok(CookieSecure::Always),
+            "false" | "never" => Ok(CookieSecure::Never),
+            other => Err(format!("unknown cookie secure mode: {}", other)),
+        }
+    }
+}
+
+/// Networks of the load balancers and proxies in front of the service.
+/// Forwarding headers from any other peer could be forged by the client.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+}
+
+/// A comma-separated list of CIDRs or bare addresses, e.g. `10.0.0.0/8, ::1`.
+impl std::str::FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("invalid proxy address: {}", entry))
+            })
+            .collect::<Result<_, _>>()
+            .map(TrustedProxies)
+    }
+}
+
+/// Deployment environment, used to pick defaults for unset settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Environment {
+    Development,
+    Production,
+}
+
+impl Environment {
+    /// Local frontends in development; production origins must be listed explicitly
+    pub fn default_cors_origins(self) -> AllowedOrigins {
+        match self {
+            Environment::Development => "http://localhost:3000, http://127.0.0.1:3000".parse().unwrap(),
+            Environment::Production => AllowedOrigins::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for Environment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "development" | "dev" => Ok(Environment::Development),
+            "production" | "prod" => Ok(Environment::Production),
+            other => Err(format!("unknown environment: {}", other)),
+        }
+    }
+}
+
+/// Origins such as `https://app.example.com` or `https://*.example.com`,
+/// where `*` stands for one or more subdomain labels.
+#[derive(Clone, Debug, Default)]
+pub struct AllowedOrigins(Vec<OriginPattern>);
+
+#[derive(Clone, Debug)]
+enum OriginPattern {
+    Exact(String),
+    /// Matches `{prefix}<subdomains>{suffix}`, e.g. `https://` and `.example.com`
+    Subdomains { prefix: String, suffix: String },
+}
+
+impl AllowedOrigins {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.0.iter().any(|pattern| match pattern {
+            OriginPattern::Exact(exact) => origin == *exact,
+            OriginPattern::Subdomains { prefix, suffix } => origin
+                .strip_prefix(prefix.as_str())
+                .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomains| {
+                    !subdomains.is_empty()
+                        && !subdomains.starts_with('.')
+                        && subdomains.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+        })
+    }
+}
+
+/// A comma-separated list. A bare `*` is rejected: credentialed requests
+/// cannot be allowed from any origin.
+impl std::str::FromStr for AllowedOrigins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let entry = entry.trim_end_matches('/').to_ascii_lowercase();
+                let (scheme, host) = entry
+                    .split_once("://")
+                    .filter(|(scheme, _)| *scheme == "http" || *scheme == "https")
+                    .ok_or_else(|| format!("invalid origin: {}", entry))?;
+                if host.is_empty() || host.contains('/') {
+                    return Err(format!("invalid origin: {}", entry));
+                }
+                match host.strip_prefix('*') {
+                    Some(suffix) if suffix.starts_with('.') && !suffix.contains('*') => Ok(OriginPattern::Subdomains {
+                        prefix: format!("{}://", scheme),
+                        suffix: suffix.to_string(),
+                    }),
+                    None if !host.contains('*') => Ok(OriginPattern::Exact(entry.clone())),
+                    _ => Err(format!("invalid origin pattern: {}", entry)),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(AllowedOrigins)
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        let environment = strict_var("APP_ENV", Environment::Development);
+
+        Self {
+            database_url: env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:db.sqlite".to_string()),
+            db_max_connections: parse_var("DB_MAX_CONNECTIONS", 10_u32).max(1),
+            db_min_connections: parse_var("DB_MIN_CONNECTIONS", 0),
+            db_acquire_timeout_secs: parse_var("DB_ACQUIRE_TIMEOUT_SECS", 30),
+            sqlite_journal_mode: parse_var("SQLITE_JOURNAL_MODE", SqliteJournalMode::Wal),
+            sqlite_synchronous: parse_var("SQLITE_SYNCHRONOUS", SqliteSynchronous::Normal),
+            sqlite_busy_timeout_ms: parse_var("SQLITE_BUSY_TIMEOUT_MS", 5000),
+            sqlite_foreign_keys: parse_var("SQLITE_FOREIGN_KEYS", true),
+            sqlite_create_if_missing: parse_var("SQLITE_CREATE_IF_MISSING", true),
+            auto_migrate: parse_var("AUTO_MIGRATE", true),
+            email_lowercase_local_part: parse_var("EMAIL_LOWERCASE_LOCAL_PART", true),
+            enumeration_resistant_registration: parse_var("ENUMERATION_RESISTANT_REGISTRATION", false),
+            password_hash_algorithm: parse_var("PASSWORD_HASH_ALGORITHM", PasswordHashAlgorithm::Argon2id),
+            argon2_memory_kib: parse_var("ARGON2_MEMORY_KIB", argon2::Params::DEFAULT_M_COST),
+            argon2_iterations: parse_var("ARGON2_ITERATIONS", argon2::Params::DEFAULT_T_COST),
+            argon2_parallelism: parse_var("ARGON2_PARALLELISM", argon2::Params::DEFAULT_P_COST),
+            bcrypt_cost: parse_var("BCRYPT_COST", bcrypt::DEFAULT_COST),
+            password_hash_max_concurrent: parse_var(
+                "PASSWORD_HASH_MAX_CONCURRENT",
+                password_hasher::default_max_concurrent(),
+            )
+            .max(1),
+            password_hash_max_queued: parse_var("PASSWORD_HASH_MAX_QUEUED", password_hasher::DEFAULT_MAX_QUEUED),
+            breached_passwords_file: path_var("BREACHED_PASSWORDS_FILE"),
+            breached_passwords_api_url: env::var("BREACHED_PASSWORDS_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            breached_password_min_count: parse_var("BREACHED_PASSWORD_MIN_COUNT", 1),
+            secret_key: env::var("SECRET_KEY").expect("SECRET_KEY must be set"),
+            public_url: env::var("PUBLIC_URL")
+                .unwrap_or_else(|_| "http://localhost:3000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            magic_link_ttl_minutes: parse_var("MAGIC_LINK_TTL_MINUTES", 15),
+            password_reset_url: env::var("PASSWORD_RESET_URL").ok().filter(|url| !url.is_empty()),
+            password_reset_ttl_minutes: parse_var("PASSWORD_RESET_TTL_MINUTES", 60),
+            metrics_token: env::var("METRICS_TOKEN").ok().filter(|token| !token.is_empty()),
+            service_name: env::var("OTEL_SERVICE_NAME")
+                .unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string()),
+            otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|endpoint| !endpoint.is_empty()),
+            trace_sample_ratio: parse_var("OTEL_TRACES_SAMPLER_ARG", 1.0_f64).clamp(0.0, 1.0),
+            redis_required: parse_var("REDIS_REQUIRED", true),
+            shutdown_timeout_secs: parse_var("SHUTDOWN_TIMEOUT_SECS", 30),
+            shutdown_readiness_delay_secs: parse_var("SHUTDOWN_READINESS_DELAY_SECS", 5),
+            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            log_format: parse_var("LOG_FORMAT", LogFormat::Pretty),
+            host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            port: parse_var("PORT", 3000),
+            unix_socket: path_var("UNIX_SOCKET"),
+            tls_cert_path: path_var("TLS_CERT_PATH"),
+            tls_key_path: path_var("TLS_KEY_PATH"),
+            tls_reload_interval_secs: parse_var("TLS_RELOAD_INTERVAL_SECS", 30),
+            cookie_secure: strict_var("COOKIE_SECURE", CookieSecure::Auto),
+            trusted_proxies: strict_var("TRUSTED_PROXIES", TrustedProxies::default()),
+            environment,
+            cors_allowed_origins: strict_var("CORS_ALLOWED_ORIGINS", environment.default_cors_origins()),
+            cors_exposed_headers: env::var("CORS_EXPOSED_HEADERS")
+                .unwrap_or_else(|_| "x-request-id".to_string())
+                .split(',')
+                .map(|header| header.trim().to_ascii_lowercase())
+                .filter(|header| !header.is_empty())
+                .collect(),
+            cors_max_age_secs: parse_var("CORS_MAX_AGE_SECS", 3600),
+            hsts_max_age_secs: parse_var("HSTS_MAX_AGE_SECS", 31_536_000),
+            hsts_include_subdomains: parse_var("HSTS_INCLUDE_SUBDOMAINS", false),
+            hsts_preload: parse_var("HSTS_PRELOAD", false),
+            content_type_options: header_var("X_CONTENT_TYPE_OPTIONS", "nosniff"),
+            frame_options: header_var("X_FRAME_OPTIONS", "DENY"),
+            content_security_policy: header_var("CONTENT_SECURITY_POLICY", "default-src 'none'; frame-ancestors 'none'"),
+            referrer_policy: header_var("REFERRER_POLICY", "no-referrer"),
+            permissions_policy: header_var(
+                "PERMISSIONS_POLICY",
+                "camera=(), geolocation=(), microphone=(), payment=()",
+            ),
+            auth_cache_control: header_var("AUTH_CACHE_CONTROL", "no-store"),
+        }
+    }
+}
+
+/// A response header value, `default` when unset and `None` when set empty.
+fn header_var(name: &str, default: &str) -> Option<String> {
+    match env::var(name) {
+        Ok(value) if value.trim().is_empty() => None,
+        Ok(value) => Some(value.trim().to_string()),
+        Err(_) => Some(default.to_string()),
+    }
+}
+
+fn path_var(name: &str) -> Option<PathBuf> {
+    env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+/// Parse a security-relevant environment variable, `default` when unset.
+/// Unlike `parse_var`, an invalid value stops startup: falling back would
+/// quietly change who is trusted or what is allowed. Logging is configured from here too, so
+/// the error goes straight to stderr.
+fn strict_var<T: std::str::FromStr<Err = String>>(name: &str, default: T) -> T {
+    parse_setting(name, env::var(name).ok().as_deref(), default).unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// `value` of the variable `name` parsed, `default` when unset, or an error naming the variable.
+pub fn parse_setting<T: std::str::FromStr<Err = String>>(name: &str, value: Option<&str>, default: T) -> Result<T, String> {
+    match value {
+        Some(value) => value.parse().map_err(|e| format!("{}={:?}: {}", name, value, e)),
+        None => Ok(default),
+    }
+}
+
+/// Parse an optional environment variable, falling back to `default` when unset or invalid.
+pub fn parse_var<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
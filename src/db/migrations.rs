@@ -0,0 +1,172 @@
+//! Inspecting and reverting the schema, for the `migrate` subcommands.
+
+use std::collections::{BTreeMap, HashMap};
+
+use sqlx::Pool;
+use sqlx::migrate::{AppliedMigration, Migrate, MigrateError, Migrator};
+
+use tracing::info;
+
+use super::Database;
+use crate::models::user::{identity_key, normalize_username};
+
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the migration's SQL has changed since
+    Modified,
+    /// Started but never finished; needs fixing by hand
+    Failed,
+    /// Applied by a build that has a migration this one lacks
+    Unknown,
+}
+
+impl MigrationState {
+    pub fn name(self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Failed => "failed",
+            MigrationState::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    /// Empty for `Unknown` migrations, which only exist in the database
+    pub description: String,
+    pub state: MigrationState,
+}
+
+impl Database {
+    /// The migrations this build ships for its backend.
+    pub fn migrator(&self) -> &'static Migrator {
+        match self {
+            Database::Sqlite(_) => &SQLITE_MIGRATOR,
+            Database::Postgres(_) => &POSTGRES_MIGRATOR,
+        }
+    }
+
+    /// Every migration, known to this build or recorded in the database, by version.
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let (applied, failed) = match self {
+            Database::Sqlite(pool) => applied_migrations(pool).await?,
+            Database::Postgres(pool) => applied_migrations(pool).await?,
+        };
+        let applied: HashMap<i64, AppliedMigration> = applied.into_iter().map(|m| (m.version, m)).collect();
+
+        let mut status = BTreeMap::new();
+        for migration in self.migrator().iter().filter(|m| !m.migration_type.is_down_migration()) {
+            let state = match applied.get(&migration.version) {
+                _ if failed == Some(migration.version) => MigrationState::Failed,
+                Some(applied) if applied.checksum == migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Modified,
+                None => MigrationState::Pending,
+            };
+            status.insert(
+                migration.version,
+                MigrationStatus { version: migration.version, description: migration.description.to_string(), state },
+            );
+        }
+        for &version in applied.keys() {
+            status.entry(version).or_insert(MigrationStatus {
+                version,
+                description: String::new(),
+                state: MigrationState::Unknown,
+            });
+        }
+        Ok(status.into_values().collect())
+    }
+
+    /// Revert the `steps` most recently applied migrations, newest first,
+    /// returning what was reverted.
+    pub async fn revert(&self, steps: usize) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let mut applied: Vec<MigrationStatus> = self
+            .migration_status()
+            .await?
+            .into_iter()
+            .filter(|m| m.state != MigrationState::Pending)
+            .collect();
+        applied.reverse();
+
+        // Everything newer than the first migration to keep is reverted
+        let target = applied.get(steps).map_or(0, |m| m.version);
+        match self {
+            Database::Sqlite(pool) => self.migrator().undo(pool, target).await?,
+            Database::Postgres(pool) => self.migrator().undo(pool, target).await?,
+        }
+        applied.truncate(steps);
+        Ok(applied)
+    }
+}
+
+impl Database {
+    /// Fill in the identity keys of users that predate them, NFKC-normalising
+    /// their usernames as registration does. Part of `migrate`, since SQL
+    /// cannot compute the keys.
+    pub(super) async fn backfill_identity_keys(&self) -> Result<(), MigrateError> {
+        let select = "SELECT id, username, email FROM users WHERE username_key IS NULL OR email_key IS NULL";
+        let users: Vec<(i64, String, String)> = match self {
+            Database::Sqlite(pool) => sqlx::query_as(select).fetch_all(pool).await?,
+            Database::Postgres(pool) => sqlx::query_as(select).fetch_all(pool).await?,
+        };
+        if users.is_empty() {
+            return Ok(());
+        }
+
+        info!(count = users.len(), "Filling in identity keys of existing users");
+        let update = "UPDATE users SET username = $1, username_key = $2, email_key = $3 WHERE id = $4";
+        for (id, username, email) in users {
+            let username = normalize_username(&username);
+            let (username_key, email_key) = (identity_key(&username), identity_key(&email));
+            let result = match self {
+                Database::Sqlite(pool) => sqlx::query(update)
+                    .bind(&username)
+                    .bind(&username_key)
+                    .bind(&email_key)
+                    .bind(id)
+                    .execute(pool)
+                    .await
+                    .map(|_| ()),
+                Database::Postgres(pool) => sqlx::query(update)
+                    .bind(&username)
+                    .bind(&username_key)
+                    .bind(&email_key)
+                    .bind(id)
+                    .execute(pool)
+                    .await
+                    .map(|_| ()),
+            };
+            match result {
+                Err(e) if e.as_database_error().is_some_and(|e| e.is_unique_violation()) => {
+                    return Err(MigrateError::Execute(sqlx::Error::Protocol(format!(
+                        "user {} ({}, {}) matches another user once case and Unicode forms are ignored; \
+                         resolve them before migrating",
+                        id, username, email
+                    ))));
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Applied migrations, and the version of one that failed partway if any.
+async fn applied_migrations<DB>(pool: &Pool<DB>) -> Result<(Vec<AppliedMigration>, Option<i64>), MigrateError>
+where
+    DB: sqlx::Database,
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let failed = conn.dirty_version().await?;
+    Ok((conn.list_applied_migrations().await?, failed))
+}
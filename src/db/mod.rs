@@ -1,37 +1,243 @@
-use sqlx::SqlitePool;
-use dotenv::dotenv;
-use std::env;
-use tracing::{info, error};
+use sqlx::{PgPool, SqlitePool};
+use sqlx::migrate::MigrateError;
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn, error};
 
+use crate::config::Config;
+
+mod migrations;
 pub mod redis;
+pub use migrations::{MigrationState, MigrationStatus};
 pub use redis::RedisStore;
 
-pub async fn create_db_pool() -> Result<SqlitePool, sqlx::Error> {
-    // Load .env file
-    dotenv().ok();
-    
-    // Get database URL from environment variable
-    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    info!(url = %db_url, "Initializing database connection");
-    
+/// The database engine behind `DATABASE_URL`, chosen by its scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    pub fn from_url(url: &str) -> Result<Self, sqlx::Error> {
+        let scheme = url.split(':').next().unwrap_or_default().to_ascii_lowercase();
+        match scheme.as_str() {
+            "sqlite" => Ok(Backend::Sqlite),
+            "postgres" | "postgresql" => Ok(Backend::Postgres),
+            "mysql" | "mariadb" => Err(sqlx::Error::Configuration(
+                "MySQL is not supported, use a sqlite: or postgres:// DATABASE_URL".into(),
+            )),
+            _ => Err(sqlx::Error::Configuration(
+                format!("unsupported DATABASE_URL scheme {:?}, expected sqlite: or postgres://", scheme).into(),
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Sqlite => "sqlite",
+            Backend::Postgres => "postgres",
+        }
+    }
+}
+
+/// A connection pool for whichever backend is configured. Persistence goes
+/// through the repositories built from it; this only covers what every
+/// backend shares.
+#[derive(Debug, Clone)]
+pub enum Database {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl From<SqlitePool> for Database {
+    fn from(pool: SqlitePool) -> Self {
+        Database::Sqlite(pool)
+    }
+}
+
+impl From<PgPool> for Database {
+    fn from(pool: PgPool) -> Self {
+        Database::Postgres(pool)
+    }
+}
+
+/// Pragmas as SQLite reports them, which can differ from what was asked
+/// for: an in-memory database never uses WAL, for instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteSettings {
+    pub journal_mode: String,
+    pub synchronous: String,
+    pub busy_timeout_ms: i64,
+    pub foreign_keys: bool,
+}
+
+impl Database {
+    /// Open a pool sized and tuned from `config`.
+    pub async fn connect(config: &Config) -> Result<Self, sqlx::Error> {
+        match Backend::from_url(&config.database_url)? {
+            Backend::Sqlite => {
+                let options = SqliteConnectOptions::from_str(&config.database_url)?
+                    .journal_mode(config.sqlite_journal_mode)
+                    .synchronous(config.sqlite_synchronous)
+                    .busy_timeout(Duration::from_millis(config.sqlite_busy_timeout_ms))
+                    .foreign_keys(config.sqlite_foreign_keys)
+                    .create_if_missing(config.sqlite_create_if_missing);
+                Ok(Database::Sqlite(pool_options(config).connect_with(options).await?))
+            }
+            Backend::Postgres => {
+                let options = PgConnectOptions::from_str(&config.database_url)?;
+                Ok(Database::Postgres(pool_options(config).connect_with(options).await?))
+            }
+        }
+    }
+
+    /// The pragmas in effect on a pooled connection, or `None` for other backends.
+    pub async fn sqlite_settings(&self) -> Result<Option<SqliteSettings>, sqlx::Error> {
+        let Database::Sqlite(pool) = self else {
+            return Ok(None);
+        };
+        let mut conn = pool.acquire().await?;
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&mut *conn).await?;
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous").fetch_one(&mut *conn).await?;
+        let busy_timeout_ms: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&mut *conn).await?;
+        let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys").fetch_one(&mut *conn).await?;
+
+        let synchronous = match synchronous {
+            0 => "off",
+            1 => "normal",
+            2 => "full",
+            3 => "extra",
+            _ => "unknown",
+        };
+        Ok(Some(SqliteSettings {
+            journal_mode: journal_mode.to_ascii_lowercase(),
+            synchronous: synchronous.to_string(),
+            busy_timeout_ms,
+            foreign_keys,
+        }))
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Database::Sqlite(_) => Backend::Sqlite,
+            Database::Postgres(_) => Backend::Postgres,
+        }
+    }
+
+    /// Apply the migrations for this backend, from `migrations/<backend>`,
+    /// then fill in identity keys the SQL could not compute.
+    pub async fn migrate(&self) -> Result<(), MigrateError> {
+        match self {
+            Database::Sqlite(pool) => self.migrator().run(pool).await?,
+            Database::Postgres(pool) => self.migrator().run(pool).await?,
+        }
+        self.backfill_identity_keys().await
+    }
+
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            Database::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+            Database::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        }
+    }
+
+    /// Open connections, both idle and in use.
+    pub fn size(&self) -> u32 {
+        match self {
+            Database::Sqlite(pool) => pool.size(),
+            Database::Postgres(pool) => pool.size(),
+        }
+    }
+
+    pub fn num_idle(&self) -> usize {
+        match self {
+            Database::Sqlite(pool) => pool.num_idle(),
+            Database::Postgres(pool) => pool.num_idle(),
+        }
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        match self {
+            Database::Sqlite(pool) => pool.options().get_max_connections(),
+            Database::Postgres(pool) => pool.options().get_max_connections(),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Database::Sqlite(pool) => pool.close().await,
+            Database::Postgres(pool) => pool.close().await,
+        }
+    }
+}
+
+fn pool_options<DB: sqlx::Database>(config: &Config) -> PoolOptions<DB> {
+    PoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections.min(config.db_max_connections))
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout_secs))
+}
+
+pub async fn create_db_pool(config: &Config) -> Result<Database, sqlx::Error> {
+    // The URL may carry credentials, so only the backend is logged
+    info!("Initializing database connection");
+
     // Create connection pool
-    let pool = match SqlitePool::connect(&db_url).await {
-        Ok(pool) => {
-            info!("Successfully connected to database");
-            pool
+    let db = match Database::connect(config).await {
+        Ok(db) => {
+            info!(
+                backend = db.backend().name(),
+                max_connections = db.max_connections(),
+                min_connections = config.db_min_connections.min(config.db_max_connections),
+                acquire_timeout_secs = config.db_acquire_timeout_secs,
+                "Successfully connected to database"
+            );
+            db
         }
         Err(e) => {
             error!(error = %e, "Failed to connect to database");
             return Err(e);
         }
     };
-    
+
+    match db.sqlite_settings().await {
+        Ok(Some(settings)) => info!(
+            journal_mode = %settings.journal_mode,
+            synchronous = %settings.synchronous,
+            busy_timeout_ms = settings.busy_timeout_ms,
+            foreign_keys = settings.foreign_keys,
+            "SQLite settings in effect"
+        ),
+        Ok(None) => {}
+        Err(e) => warn!(error = %e, "Failed to read SQLite settings"),
+    }
+
+    if !config.auto_migrate {
+        // Migrations are a separate deploy step; an unmigrated schema is worth a warning, not a refusal to start
+        info!("Automatic migrations disabled");
+        match db.migration_status().await {
+            Ok(status) => {
+                let outstanding = status.iter().filter(|m| m.state != MigrationState::Applied).count();
+                if outstanding > 0 {
+                    warn!(outstanding, "Database schema is not up to date, run `migrate up`");
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to read migration status"),
+        }
+        return Ok(db);
+    }
+
     // Run migrations
     info!("Running database migrations");
-    match sqlx::migrate!().run(&pool).await {
+    match db.migrate().await {
         Ok(_) => {
             info!("Successfully ran database migrations");
-            Ok(pool)
+            Ok(db)
         }
         Err(e) => {
             error!(error = %e, "Failed to run database migrations");
@@ -42,4 +248,4 @@ pub async fn create_db_pool() -> Result<SqlitePool, sqlx::Error> {
 
 pub fn create_redis_store() -> Result<RedisStore, ::redis::RedisError> {
     RedisStore::new()
-} 
\ No newline at end of file
+}
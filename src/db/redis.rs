@@ -3,31 +3,76 @@ use redis::{Client, RedisError};
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 use std::env;
+use std::future::Future;
+use std::time::Instant;
 use tracing::{info, instrument};
 
+use crate::metrics::metrics;
+
 #[derive(Clone)]
 pub struct RedisStore {
     client: Client,
+    namespace: String,
 }
 
 impl RedisStore {
     const ALLOWLIST_PREFIX: &str = "refresh_allowlist:";
     const BLACKLIST_PREFIX: &str = "token_blacklist:";
+    const SINGLE_USE_PREFIX: &str = "single_use:";
+    const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+    const USER_REVOKED_AT_PREFIX: &str = "user_tokens_revoked_at_ms:";
 
     #[instrument]
     pub fn new() -> Result<Self, RedisError> {
         dotenv().ok();
         let redis_url = env::var("REDIS_URL")
             .unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
-        info!(url = %redis_url, "Initializing Redis connection");
+        let namespace = env::var("REDIS_KEY_PREFIX").unwrap_or_default();
+        info!(url = %redis_url, namespace = %namespace, "Initializing Redis connection");
+
+        Self::open(&redis_url).map(|store| store.with_namespace(namespace))
+    }
+
+    /// Create a store for `redis_url` without reading the environment.
+    pub fn open(redis_url: &str) -> Result<Self, RedisError> {
+        Client::open(redis_url).map(|client| Self { client, namespace: String::new() })
+    }
+
+    /// Prefix every key with `namespace`, so several deployments (or test
+    /// runs) can share one Redis without seeing each other's keys.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
 
-        Client::open(redis_url).map(|client| Self { client })
+    fn key(&self, prefix: &str, id: impl std::fmt::Display) -> String {
+        format!("{}{}{}", self.namespace, prefix, id)
     }
 
     async fn conn(&self) -> Result<ConnectionManager, RedisError> {
         ConnectionManager::new(self.client.clone()).await
     }
 
+    /// Run one store operation, recording its latency and any error.
+    async fn observed<T>(
+        operation: &str,
+        fut: impl Future<Output = Result<T, RedisError>>,
+    ) -> Result<T, RedisError> {
+        let started = Instant::now();
+        let result = fut.await;
+        metrics().observe_redis(operation, started, result.is_ok());
+        result
+    }
+
+    /// Round-trip a `PING`, for health checks.
+    pub async fn ping(&self) -> Result<(), RedisError> {
+        Self::observed("ping", async {
+            let mut con = self.conn().await?;
+            redis::cmd("PING").query_async::<_, String>(&mut con).await.map(|_| ())
+        })
+        .await
+    }
+
     /* ----------  ALLOWLIST  (for refresh tokens) ---------- */
 
     pub async fn add_to_allowlist(
@@ -36,34 +81,123 @@ impl RedisStore {
         user_id: i64,
         ttl_secs: u64,
     ) -> Result<(), RedisError> {
-        let key = format!("{}{}", Self::ALLOWLIST_PREFIX, jti);
-        let mut con = self.conn().await?;
-        con.set_ex::<_, _, ()>(key, user_id, ttl_secs).await
+        let key = self.key(Self::ALLOWLIST_PREFIX, jti);
+        let sessions_key = self.key(Self::USER_SESSIONS_PREFIX, user_id);
+        Self::observed("add_to_allowlist", async {
+            let mut con = self.conn().await?;
+            con.set_ex::<_, _, ()>(key, user_id, ttl_secs).await?;
+
+            // Index the JTI by user so all of a user's sessions can be revoked at once
+            con.sadd::<_, _, ()>(&sessions_key, jti).await?;
+            con.expire::<_, ()>(sessions_key, ttl_secs as i64).await
+        })
+        .await
     }
 
     pub async fn remove_from_allowlist(&self, jti: &str) -> Result<(), RedisError> {
-        let key = format!("{}{}", Self::ALLOWLIST_PREFIX, jti);
-        let mut con = self.conn().await?;
-        con.del::<_, ()>(key).await
+        let key = self.key(Self::ALLOWLIST_PREFIX, jti);
+        Self::observed("remove_from_allowlist", async {
+            let mut con = self.conn().await?;
+            con.del::<_, ()>(key).await
+        })
+        .await
     }
 
     pub async fn is_allowlisted(&self, jti: &str) -> redis::RedisResult<bool> {
-        let key = format!("{}{}", Self::ALLOWLIST_PREFIX, jti);
-        let mut con = self.conn().await?;
-        con.exists(key).await
+        let key = self.key(Self::ALLOWLIST_PREFIX, jti);
+        Self::observed("is_allowlisted", async {
+            let mut con = self.conn().await?;
+            con.exists(key).await
+        })
+        .await
+    }
+
+    /* ----------  PER-USER REVOCATION ---------- */
+
+    /// Drop every allow-listed refresh token for `user_id` and record the
+    /// revocation time, so access tokens issued before it can be rejected.
+    pub async fn revoke_user_sessions(
+        &self,
+        user_id: i64,
+        revoked_at_ms: i64,
+        ttl_secs: u64,
+    ) -> Result<usize, RedisError> {
+        let sessions_key = self.key(Self::USER_SESSIONS_PREFIX, user_id);
+        let revoked_at_key = self.key(Self::USER_REVOKED_AT_PREFIX, user_id);
+        Self::observed("revoke_user_sessions", async {
+            let mut con = self.conn().await?;
+
+            let jtis: Vec<String> = con.smembers(&sessions_key).await?;
+            for jti in &jtis {
+                con.del::<_, ()>(self.key(Self::ALLOWLIST_PREFIX, jti)).await?;
+            }
+            con.del::<_, ()>(sessions_key).await?;
+            con.set_ex::<_, _, ()>(revoked_at_key, revoked_at_ms, ttl_secs).await?;
+
+            Ok(jtis.len())
+        })
+        .await
+    }
+
+    /// Unix time in milliseconds of the last "revoke all sessions" for `user_id`, if any.
+    pub async fn user_tokens_revoked_at_ms(&self, user_id: i64) -> redis::RedisResult<Option<i64>> {
+        let key = self.key(Self::USER_REVOKED_AT_PREFIX, user_id);
+        Self::observed("user_tokens_revoked_at_ms", async {
+            let mut con = self.conn().await?;
+            con.get(key).await
+        })
+        .await
     }
 
     /* ----------  BLACKLIST  (for access OR refresh) ---------- */
 
     pub async fn blacklist_token(&self, token: &str, ttl_secs: u64) -> Result<(), RedisError> {
-        let key = format!("{}{}", Self::BLACKLIST_PREFIX, token);
-        let mut con = self.conn().await?;
-        con.set_ex::<_, _, ()>(key, 1u8, ttl_secs).await
+        let key = self.key(Self::BLACKLIST_PREFIX, token);
+        Self::observed("blacklist_token", async {
+            let mut con = self.conn().await?;
+            con.set_ex::<_, _, ()>(key, 1u8, ttl_secs).await
+        })
+        .await
     }
 
     pub async fn is_blacklisted(&self, token: &str) -> redis::RedisResult<bool> {
-        let key = format!("{}{}", Self::BLACKLIST_PREFIX, token);
-        let mut con = self.conn().await?;
-        con.exists(key).await
+        let key = self.key(Self::BLACKLIST_PREFIX, token);
+        Self::observed("is_blacklisted", async {
+            let mut con = self.conn().await?;
+            con.exists(key).await
+        })
+        .await
+    }
+
+    /* ----------  SINGLE-USE KEYS  (for one-time links) ---------- */
+
+    pub async fn store_single_use(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), RedisError> {
+        let key = self.key(Self::SINGLE_USE_PREFIX, key);
+        Self::observed("store_single_use", async {
+            let mut con = self.conn().await?;
+            con.set_ex::<_, _, ()>(key, value, ttl_secs).await
+        })
+        .await
+    }
+
+    /// Read a single-use key without consuming it.
+    pub async fn peek_single_use(&self, key: &str) -> redis::RedisResult<Option<String>> {
+        let key = self.key(Self::SINGLE_USE_PREFIX, key);
+        Self::observed("peek_single_use", async {
+            let mut con = self.conn().await?;
+            con.get(key).await
+        })
+        .await
+    }
+
+    /// Atomically read and delete a single-use key. Returns `None` if it was
+    /// never stored, has expired, or was already consumed.
+    pub async fn consume_single_use(&self, key: &str) -> redis::RedisResult<Option<String>> {
+        let key = self.key(Self::SINGLE_USE_PREFIX, key);
+        Self::observed("consume_single_use", async {
+            let mut con = self.conn().await?;
+            con.get_del(key).await
+        })
+        .await
     }
 }
@@ -1,37 +1,77 @@
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Json, Router,
-    middleware::from_fn_with_state,
+    extract::{FromRef, State},
+    middleware::{from_fn, from_fn_with_state},
+    response::{IntoResponse, Response},
 };
 use serde::Serialize;
-use std::net::SocketAddr;
 use tower_http::cors::CorsLayer;
 use http::{
-    Method, 
-    HeaderValue, 
-    header::{AUTHORIZATION, ACCEPT, CONTENT_TYPE},
+    HeaderMap, StatusCode,
+    header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE},
 };
-use sqlx::SqlitePool;
-use tracing::{info, error, Level};
+use subtle::ConstantTimeEq;
+use tracing::{info, error};
+use clap::Parser;
+use std::process::ExitCode;
+use std::sync::Arc;
 use std::time::Duration;
 
+mod cli;
+mod config;
 mod db;
 mod api;
 mod models;
+mod repositories;
 mod services;
 mod middleware;
+mod metrics;
+mod telemetry;
+mod server;
 #[cfg(test)]
 mod tests;
 
 use services::jwt_service::JwtService;
 use services::auth_service::AuthService;
+use services::password_hasher::PasswordHasher;
+use services::password_policy::PasswordPolicy;
+use services::api_key_service::ApiKeyService;
+use services::email_service::EmailService;
+use services::magic_link_service::MagicLinkService;
+use services::password_reset_service::PasswordResetService;
+use services::admin_service::AdminService;
+use services::audit_service::AuditLog;
+use services::health_service::HealthService;
+use cli::{Cli, Command};
+use config::{Config, CookieSecure, TrustedProxies};
+use db::Database;
+use repositories::{Repositories, UserRepository};
+use middleware::security_headers::SecurityHeaders;
 
 #[derive(Clone)]
 pub struct AppState {
-    db: SqlitePool,
-    redis: db::RedisStore,
+    db: Database,
+    users: Arc<dyn UserRepository>,
     jwt_service: JwtService,
     auth_service: AuthService,
+    api_key_service: ApiKeyService,
+    magic_link_service: MagicLinkService,
+    password_reset_service: PasswordResetService,
+    admin_service: AdminService,
+    audit_log: AuditLog,
+    health_service: HealthService,
+    cookie_secure: CookieSecure,
+    trusted_proxies: TrustedProxies,
+    cors: CorsLayer,
+    security_headers: SecurityHeaders,
+    metrics_token: Option<Arc<str>>,
+}
+
+impl FromRef<AppState> for TrustedProxies {
+    fn from_ref(state: &AppState) -> Self {
+        state.trusted_proxies.clone()
+    }
 }
 
 #[derive(Serialize)]
@@ -45,68 +85,192 @@ async fn hello_world() -> Json<Message> {
     })
 }
 
-pub fn create_router(pool: SqlitePool, redis_store: db::RedisStore) -> Router {
-    // Get the secret key from environment
-    dotenv::dotenv().ok();
-    let secret_key = std::env::var("SECRET_KEY").expect("SECRET_KEY must be set");
-    
+async fn not_found() -> StatusCode {
+    StatusCode::NOT_FOUND
+}
+
+/// Prometheus metrics, for scrapers presenting `METRICS_TOKEN` as a bearer
+/// token. They reveal auth failure rates and the route inventory, so without
+/// a token configured the endpoint is not served at all.
+async fn metrics_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(token) = &state.metrics_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let presented = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !presented.is_some_and(|presented| bool::from(presented.as_bytes().ct_eq(token.as_bytes()))) {
+        return (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")]).into_response();
+    }
+
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::metrics().render(&state.db),
+    )
+        .into_response()
+}
+
+pub fn create_router(db: impl Into<Database>, redis_store: db::RedisStore) -> Router {
+    build_router(db, redis_store, Config::from_env(), EmailService::from_env())
+}
+
+pub fn build_router(
+    db: impl Into<Database>,
+    redis_store: db::RedisStore,
+    config: Config,
+    email_service: EmailService,
+) -> Router {
+    router(build_state(db, redis_store, config, email_service))
+}
+
+/// Wire up every service the handlers share.
+pub fn build_state(
+    db: impl Into<Database>,
+    redis_store: db::RedisStore,
+    config: Config,
+    email_service: EmailService,
+) -> AppState {
+    let db = db.into();
+    let repositories = Repositories::new(&db);
+
     // Create the JWT service
-    let jwt_service = JwtService::new(redis_store.clone(), secret_key);
-    let auth_service = AuthService::new(pool.clone(), jwt_service.clone());
+    let jwt_service = JwtService::new(repositories.users.clone(), redis_store.clone(), config.secret_key.clone());
+    let password_hasher = PasswordHasher::from_config(&config);
+    let password_policy = PasswordPolicy::from_config(&config);
+    let mut auth_service = AuthService::new(repositories.users.clone(), jwt_service.clone())
+        .with_password_hasher(password_hasher.clone())
+        .with_password_policy(password_policy.clone())
+        .with_lowercase_email_local_part(config.email_lowercase_local_part);
+    if config.enumeration_resistant_registration {
+        auth_service = auth_service.with_registration_notices(email_service.clone(), config.public_url.clone());
+    }
+    let api_key_service = ApiKeyService::new(repositories.api_keys.clone());
+    let magic_link_service = MagicLinkService::new(
+        repositories.users.clone(),
+        redis_store.clone(),
+        jwt_service.clone(),
+        email_service.clone(),
+        config.public_url.clone(),
+        chrono::Duration::minutes(config.magic_link_ttl_minutes),
+    );
+    let password_reset_service = PasswordResetService::new(
+        repositories.users.clone(),
+        redis_store.clone(),
+        jwt_service.clone(),
+        email_service,
+        config.password_reset_url.clone().unwrap_or_else(|| format!("{}/password-reset", config.public_url)),
+        chrono::Duration::minutes(config.password_reset_ttl_minutes),
+    )
+    .with_password_hasher(password_hasher)
+    .with_password_policy(password_policy);
+    let admin_service = AdminService::new(repositories.users.clone(), jwt_service.clone(), password_reset_service.clone());
 
-    let state = AppState {
-        db: pool,
-        redis: redis_store,
+    let audit_log = AuditLog::new(repositories.audit_events.clone());
+    let health_service = HealthService::new(db.clone(), redis_store, config.redis_required);
+
+    AppState {
+        db,
+        users: repositories.users,
         jwt_service,
         auth_service,
-    };
-
-    // Create a CORS layer
-    let cors = CorsLayer::new()
-        .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE])
-        .allow_credentials(true)
-        .max_age(Duration::from_secs(3600));
+        api_key_service,
+        magic_link_service,
+        password_reset_service,
+        admin_service,
+        audit_log,
+        health_service,
+        cookie_secure: config.cookie_secure,
+        cors: middleware::cors::cors_layer(&config),
+        security_headers: SecurityHeaders::from_config(&config),
+        trusted_proxies: config.trusted_proxies,
+        metrics_token: config.metrics_token.map(Arc::from),
+    }
+}
 
+pub fn router(state: AppState) -> Router {
     // Create protected routes
     let protected_routes = Router::new()
         .route("/me", get(api::user::get_current_user))
-        .layer(from_fn_with_state(state.clone(), middleware::auth::auth_middleware));
+        .route("/me/api-keys", post(api::api_key::create_api_key).get(api::api_key::list_api_keys))
+        .route("/me/api-keys/:id", delete(api::api_key::delete_api_key))
+        .route("/me/security-events", get(api::audit::list_my_security_events))
+        .route("/impersonation/stop", post(api::admin::stop_impersonation))
+        .layer(from_fn_with_state(state.clone(), middleware::auth::auth_middleware))
+        .layer(from_fn_with_state(state.clone(), middleware::security_headers::no_store));
 
-    // build our application with routes
-    Router::new()
-        .route("/", get(hello_world))
+    // Create admin routes (auth runs first, then the admin role check)
+    let admin_routes = Router::new()
+        .route("/users", get(api::admin::list_users))
+        .route("/users/:id", get(api::admin::get_user))
+        .route("/users/:id/disable", post(api::admin::disable_user))
+        .route("/users/:id/enable", post(api::admin::enable_user))
+        .route("/users/:id/status", put(api::admin::set_status))
+        .route("/users/:id/role", put(api::admin::set_role))
+        .route("/users/:id/force-password-reset", post(api::admin::force_password_reset))
+        .route("/users/:id/revoke-sessions", post(api::admin::revoke_sessions))
+        .route("/users/:id/impersonate", post(api::admin::impersonate_user))
+        .route("/audit-events", get(api::audit::list_audit_events))
+        .layer(from_fn(middleware::admin::admin_middleware))
+        .layer(from_fn_with_state(state.clone(), middleware::auth::auth_middleware))
+        .layer(from_fn_with_state(state.clone(), middleware::security_headers::no_store));
+
+    // Create public routes that issue or revoke credentials
+    let auth_routes = Router::new()
         .route("/login", post(api::auth::login))
+        .route("/login/magic-link", post(api::auth::request_magic_link))
+        .route("/login/magic-link/verify", get(api::auth::verify_magic_link))
         .route("/register", post(api::auth::register))
         .route("/refresh", post(api::auth::refresh_token))
         .route("/logout", post(api::auth::logout))
+        .route("/password-reset", get(api::auth::check_password_reset).post(api::auth::reset_password))
+        .layer(from_fn_with_state(state.clone(), middleware::security_headers::no_store));
+
+    // build our application with routes
+    Router::new()
+        .route("/", get(hello_world))
+        .route("/metrics", get(metrics_handler))
+        .route("/health/live", get(api::health::live))
+        .route("/health/ready", get(api::health::ready))
+        .merge(auth_routes)
         .merge(protected_routes)
-        .layer(cors)
+        .nest("/admin", admin_routes)
+        .fallback(not_found)
+        .layer(from_fn(middleware::metrics::track_metrics))
+        .layer(state.cors.clone())
+        .layer(from_fn_with_state(state.clone(), middleware::security_headers::security_headers))
+        .layer(from_fn(middleware::request_id::trace_request))
         .with_state(state)
 }
 
 #[tokio::main]
-async fn main() {
-    // Initialize structured logging with timestamp and log level
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .with_thread_ids(true)
-        .with_level(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_thread_names(true)
-        .with_max_level(Level::DEBUG)
-        .init();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = Config::from_env();
 
+    // Initialize structured logging, and trace export if configured
+    let _telemetry = telemetry::init(&config);
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            serve(config).await;
+            ExitCode::SUCCESS
+        }
+        Command::Migrate(command) => cli::migrate(&config, command).await,
+        Command::User(command) => cli::user(&config, command).await,
+        Command::Sessions(command) => cli::sessions(&config, command).await,
+    }
+}
+
+async fn serve(config: Config) {
     info!("Starting Axum API server...");
 
     // Initialize database
     info!("Initializing database connection...");
-    let pool = match db::create_db_pool().await {
-        Ok(pool) => {
+    let db = match db::create_db_pool(&config).await {
+        Ok(db) => {
             info!("Successfully connected to database");
-            pool
+            db
         },
         Err(e) => {
             error!("Failed to connect to database: {}", e);
@@ -127,25 +291,49 @@ async fn main() {
         }
     };
 
-    // Create the router
-    info!("Configuring API routes...");
-    let app = create_router(pool, redis_store);
-
-    // run it with hyper
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    info!("🚀 Server starting on http://{}", addr);
-    
-    match tokio::net::TcpListener::bind(addr).await {
-        Ok(listener) => {
-            info!("Successfully bound to port 3000");
-            if let Err(e) = axum::serve(listener, app).await {
-                error!("Server error: {}", e);
-                std::process::exit(1);
+    let tls = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let reload_interval = Duration::from_secs(config.tls_reload_interval_secs);
+            match server::TlsAcceptor::from_pem_files(cert_path, key_path, reload_interval) {
+                Ok(acceptor) => Some(acceptor),
+                Err(e) => {
+                    error!("Failed to load TLS certificate: {}", e);
+                    std::process::exit(1);
+                }
             }
         },
+        (None, None) => None,
+        _ => {
+            error!("TLS_CERT_PATH and TLS_KEY_PATH must be set together");
+            std::process::exit(1);
+        }
+    };
+
+    let listener = match server::Listener::bind(&config).await {
+        Ok(listener) => listener,
         Err(e) => {
-            error!("Failed to bind to port 3000: {}", e);
+            error!("Failed to bind listener: {}", e);
             std::process::exit(1);
         }
-    }
+    };
+
+    // Create the router
+    info!("Configuring API routes...");
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let readiness_delay = Duration::from_secs(config.shutdown_readiness_delay_secs);
+    let state = build_state(db.clone(), redis_store, config, EmailService::from_env());
+    let health_service = state.health_service.clone();
+    let app = router(state);
+
+    let addr = listener.local_addr().unwrap_or_default();
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("🚀 Server listening on {} ({})", addr, scheme);
+
+    let shutdown = async move { health_service.shutdown_after(server::shutdown_signal(), readiness_delay).await };
+    server::serve(listener, tls, app, shutdown, drain_timeout).await;
+
+    // Redis connections are opened per operation, so only the pool needs closing
+    info!("Closing database connections...");
+    db.close().await;
+    info!("Shutdown complete");
 }
@@ -0,0 +1,185 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use tracing::error;
+
+use crate::db::Database;
+
+/// Every metric the service exports, registered in a dedicated registry.
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub logins_total: IntCounterVec,
+    pub token_refreshes_total: IntCounterVec,
+    pub token_revocations_total: IntCounterVec,
+    pub password_verify_duration_seconds: HistogramVec,
+    pub password_hash_queue_depth: IntGauge,
+    pub password_hash_in_flight: IntGauge,
+    pub password_hash_rejected_total: IntCounter,
+    pub db_pool_connections: IntGaugeVec,
+    pub db_pool_max_connections: IntGauge,
+    pub redis_command_duration_seconds: HistogramVec,
+    pub redis_command_errors_total: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by method, route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method, route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let logins_total = IntCounterVec::new(
+            Opts::new("auth_logins_total", "Password login attempts by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let token_refreshes_total = IntCounterVec::new(
+            Opts::new("auth_token_refreshes_total", "Refresh token exchanges by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let token_revocations_total = IntCounterVec::new(
+            Opts::new("auth_token_revocations_total", "Token revocations by kind"),
+            &["kind"],
+        )
+        .unwrap();
+        // Password hashes are deliberately slow, so the default buckets are too fine
+        let password_verify_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("auth_password_verify_duration_seconds", "Password hash verification time")
+                .buckets(vec![0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+            &["algorithm"],
+        )
+        .unwrap();
+        let password_hash_queue_depth = IntGauge::new(
+            "auth_password_hash_queue_depth",
+            "Password hashes and verifications waiting for a blocking thread",
+        )
+        .unwrap();
+        let password_hash_in_flight =
+            IntGauge::new("auth_password_hash_in_flight", "Password hashes and verifications running").unwrap();
+        let password_hash_rejected_total = IntCounter::new(
+            "auth_password_hash_rejected_total",
+            "Password hashes and verifications refused because the queue was full",
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let db_pool_max_connections =
+            IntGauge::new("db_pool_max_connections", "Configured database pool size").unwrap();
+        let redis_command_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("redis_command_duration_seconds", "Redis store operation latency")
+                .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25]),
+            &["operation"],
+        )
+        .unwrap();
+        let redis_command_errors_total = IntCounterVec::new(
+            Opts::new("redis_command_errors_total", "Failed Redis store operations"),
+            &["operation"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests_total.clone())).unwrap();
+        registry.register(Box::new(http_request_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(logins_total.clone())).unwrap();
+        registry.register(Box::new(token_refreshes_total.clone())).unwrap();
+        registry.register(Box::new(token_revocations_total.clone())).unwrap();
+        registry.register(Box::new(password_verify_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(password_hash_queue_depth.clone())).unwrap();
+        registry.register(Box::new(password_hash_in_flight.clone())).unwrap();
+        registry.register(Box::new(password_hash_rejected_total.clone())).unwrap();
+        registry.register(Box::new(db_pool_connections.clone())).unwrap();
+        registry.register(Box::new(db_pool_max_connections.clone())).unwrap();
+        registry.register(Box::new(redis_command_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(redis_command_errors_total.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            logins_total,
+            token_refreshes_total,
+            token_revocations_total,
+            password_verify_duration_seconds,
+            password_hash_queue_depth,
+            password_hash_in_flight,
+            password_hash_rejected_total,
+            db_pool_connections,
+            db_pool_max_connections,
+            redis_command_duration_seconds,
+            redis_command_errors_total,
+        }
+    }
+
+    /// Record the outcome of a password login.
+    pub fn record_login(&self, success: bool) {
+        self.logins_total.with_label_values(&[outcome(success)]).inc();
+    }
+
+    pub fn record_token_refresh(&self, success: bool) {
+        self.token_refreshes_total.with_label_values(&[outcome(success)]).inc();
+    }
+
+    /// `kind` is `token` for a single refresh token, `access_token` for a
+    /// single access token, or `all_sessions`.
+    pub fn record_token_revocation(&self, kind: &str) {
+        self.token_revocations_total.with_label_values(&[kind]).inc();
+    }
+
+    pub fn observe_password_verify(&self, algorithm: &str, started: Instant) {
+        self.password_verify_duration_seconds
+            .with_label_values(&[algorithm])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    pub fn observe_redis(&self, operation: &str, started: Instant, success: bool) {
+        self.redis_command_duration_seconds
+            .with_label_values(&[operation])
+            .observe(started.elapsed().as_secs_f64());
+        if !success {
+            self.redis_command_errors_total.with_label_values(&[operation]).inc();
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format. Pool gauges are
+    /// sampled at scrape time.
+    pub fn render(&self, db: &Database) -> String {
+        let size = db.size() as i64;
+        let idle = db.num_idle() as i64;
+        self.db_pool_connections.with_label_values(&["idle"]).set(idle);
+        self.db_pool_connections.with_label_values(&["active"]).set(size - idle);
+        self.db_pool_max_connections.set(db.max_connections() as i64);
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success { "success" } else { "failure" }
+}
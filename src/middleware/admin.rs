@@ -0,0 +1,33 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{debug, warn};
+
+use crate::middleware::auth::{AuthMethod, CurrentUser};
+
+/// Only let admins through. Must run after `auth_middleware`.
+/// Admin routes are session-only: API keys are never accepted here.
+pub async fn admin_middleware(
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let current_user = request
+        .extensions()
+        .get::<CurrentUser>()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !current_user.0.is_admin() {
+        warn!(user_id = %current_user.0.id, "Non-admin attempted to access admin route");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Some(auth_method) = request.extensions().get::<AuthMethod>() {
+        auth_method.require_session()?;
+    }
+
+    debug!(user_id = %current_user.0.id, "Admin access granted");
+    Ok(next.run(request).await)
+}
@@ -1,7 +1,7 @@
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{HeaderMap, Request, StatusCode, header::AUTHORIZATION},
     middleware::Next,
     response::Response,
 };
@@ -9,65 +9,175 @@ use tracing::{info, warn, error, debug};
 
 use crate::{
     AppState,
-    models::user::User,
+    api::error::ApiError,
+    models::{api_key::ApiKey, user::User},
     services::cookie_service::{ACCESS_TOKEN_COOKIE, CookieService},
 };
 
+pub const API_KEY_HEADER: &str = "x-api-key";
+const API_KEY_AUTH_SCHEME: &str = "ApiKey ";
+
 #[derive(Clone)]
 pub struct CurrentUser(pub User);
 
+/// How the current request was authenticated.
+#[derive(Clone)]
+pub enum AuthMethod {
+    Session,
+    ApiKey(ApiKey),
+    /// An admin session acting as the current user
+    Impersonation { actor_id: i64 },
+}
+
+impl AuthMethod {
+    /// Session logins carry every scope; API keys only those they were issued with.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match self {
+            AuthMethod::Session | AuthMethod::Impersonation { .. } => true,
+            AuthMethod::ApiKey(api_key) => api_key.has_scope(scope),
+        }
+    }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), StatusCode> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            warn!(scope = %scope, "API key missing required scope");
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+
+    /// Sensitive endpoints need the user's own session: API keys and
+    /// impersonating admins are refused.
+    pub fn require_session(&self) -> Result<(), StatusCode> {
+        match self {
+            AuthMethod::Session => Ok(()),
+            AuthMethod::ApiKey(_) => {
+                warn!("Session-only endpoint called with an API key");
+                Err(StatusCode::FORBIDDEN)
+            }
+            AuthMethod::Impersonation { actor_id } => {
+                warn!(actor_id = %actor_id, "Session-only endpoint called while impersonating");
+                Err(StatusCode::FORBIDDEN)
+            }
+        }
+    }
+
+    /// The admin behind an impersonated request, if any.
+    pub fn actor_id(&self) -> Option<i64> {
+        match self {
+            AuthMethod::Impersonation { actor_id } => Some(*actor_id),
+            _ => None,
+        }
+    }
+}
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut request: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     debug!("Auth middleware started");
-    debug!("Request headers: {:?}", request.headers());
-
-    // Get the access token from cookies
-    let access_token = match CookieService::extract_token(&request.headers(), ACCESS_TOKEN_COOKIE) {
-        Some(token) => {
-            debug!(token_length = token.len(), "Access token found in cookies");
-            token
-        },
-        None => {
-            warn!("No access token found in cookies");
-            return Err(StatusCode::UNAUTHORIZED);
+
+    let (user_id, auth_method) = match extract_api_key(request.headers()) {
+        Some(key) => {
+            debug!("API key found in request headers");
+            let api_key = match state.api_key_service.verify_key(&key).await {
+                Ok(api_key) => api_key,
+                Err(e) => {
+                    warn!(error = %e, "Invalid API key presented");
+                    return Err(StatusCode::UNAUTHORIZED.into());
+                }
+            };
+            (api_key.user_id, AuthMethod::ApiKey(api_key))
         }
-    };
+        None => {
+            // Get the access token from cookies
+            let access_token = match CookieService::extract_token(request.headers(), ACCESS_TOKEN_COOKIE) {
+                Some(token) => {
+                    debug!(token_length = token.len(), "Access token found in cookies");
+                    token
+                },
+                None => {
+                    warn!("No access token found in cookies");
+                    return Err(StatusCode::UNAUTHORIZED.into());
+                }
+            };
 
-    // Decode and verify the token
-    let claims = match state.jwt_service.verify_access_token(&access_token).await {
-        Ok(claims) => {
-            debug!(user_id = %claims.sub, "Token verified successfully");
-            claims
-        },
-        Err(e) => {
-            warn!(error = %e, "Invalid token verification attempt");
-            return Err(StatusCode::UNAUTHORIZED);
+            // Decode and verify the token
+            let claims = match state.jwt_service.verify_access_token(&access_token).await {
+                Ok(claims) => {
+                    debug!(user_id = %claims.sub, "Token verified successfully");
+                    claims
+                },
+                Err(e) => {
+                    warn!(error = %e, "Invalid token verification attempt");
+                    return Err(StatusCode::UNAUTHORIZED.into());
+                }
+            };
+            match claims.act {
+                Some(actor) => {
+                    // The impersonating admin must still be an active admin
+                    match state.users.find_by_id(actor.sub).await {
+                        Ok(Some(admin)) if admin.is_admin() && admin.is_active() => {}
+                        Ok(_) => {
+                            warn!(actor_id = %actor.sub, "Impersonation token from a user who is no longer an active admin");
+                            return Err(StatusCode::UNAUTHORIZED.into());
+                        }
+                        Err(e) => {
+                            error!(error = %e, "Database error during impersonation check");
+                            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+                        }
+                    }
+                    (claims.sub, AuthMethod::Impersonation { actor_id: actor.sub })
+                }
+                None => (claims.sub, AuthMethod::Session),
+            }
         }
     };
 
     // Find user by ID
-    let user = match User::find_by_id(&state.db, claims.sub).await {
+    let user = match state.users.find_by_id(user_id).await {
         Ok(Some(user)) => {
-            info!(user_id = %user.id, "User found and authenticated successfully");
+            info!(user_id = %user.id, "User found");
             user
         }
         Ok(None) => {
-            warn!(user_id = %claims.sub, "Token verification failed - user not found");
-            return Err(StatusCode::UNAUTHORIZED);
+            warn!(user_id = %user_id, "Token verification failed - user not found");
+            return Err(StatusCode::UNAUTHORIZED.into());
         }
         Err(e) => {
             error!(error = %e, "Database error during token verification");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
         }
     };
 
+    if !user.is_active() {
+        warn!(user_id = %user.id, status = ?user.status, "Request refused - account not active");
+        return Err(ApiError::AccountInactive(user.status));
+    }
+
     // Add the user to request extensions
     request.extensions_mut().insert(CurrentUser(user));
+    request.extensions_mut().insert(auth_method);
     debug!("User added to request extensions");
 
     // Continue with the request
     Ok(next.run(request).await)
 }
+
+/// Read an API key from `Authorization: ApiKey <key>` or `X-API-Key: <key>`.
+fn extract_api_key(headers: &HeaderMap) -> Option<String> {
+    let from_authorization = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(API_KEY_AUTH_SCHEME))
+        .map(|key| key.trim().to_string());
+
+    from_authorization.or_else(|| {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|key| key.trim().to_string())
+    })
+}
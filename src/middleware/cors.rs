@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use http::{
+    HeaderName, Method,
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::middleware::{auth::API_KEY_HEADER, request_id::REQUEST_ID_HEADER};
+
+/// Credentialed CORS for the configured browser origins. Requests from any
+/// other origin get no CORS headers, so the browser blocks the response.
+pub fn cors_layer(config: &Config) -> CorsLayer {
+    if config.cors_allowed_origins.is_empty() {
+        warn!("CORS_ALLOWED_ORIGINS is empty, cross-origin browser requests will be refused");
+    }
+
+    let origins = config.cors_allowed_origins.clone();
+    let exposed_headers: Vec<HeaderName> = config
+        .cors_exposed_headers
+        .iter()
+        .filter_map(|name| match HeaderName::try_from(name.as_str()) {
+            Ok(header) => Some(header),
+            Err(_) => {
+                warn!(header = %name, "Ignoring invalid CORS exposed header");
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin.to_str().is_ok_and(|origin| origins.matches(origin))
+        }))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+        .allow_headers([
+            AUTHORIZATION,
+            ACCEPT,
+            CONTENT_TYPE,
+            HeaderName::from_static(API_KEY_HEADER),
+            HeaderName::from_static(REQUEST_ID_HEADER),
+        ])
+        .expose_headers(exposed_headers)
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(config.cors_max_age_secs))
+}
@@ -1 +1,6 @@
-pub mod auth; 
\ No newline at end of file
+pub mod auth; 
+pub mod admin;
+pub mod cors;
+pub mod metrics;
+pub mod request_id;
+pub mod security_headers;
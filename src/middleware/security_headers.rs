@@ -0,0 +1,100 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderName, HeaderValue, Request, header},
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+
+use crate::AppState;
+use crate::api::forwarded::Scheme;
+use crate::config::Config;
+
+/// Header values built once from configuration.
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    fixed: Vec<(HeaderName, HeaderValue)>,
+    hsts: Option<HeaderValue>,
+    auth_cache_control: Option<HeaderValue>,
+}
+
+impl SecurityHeaders {
+    pub fn from_config(config: &Config) -> Self {
+        let fixed = [
+            (header::X_CONTENT_TYPE_OPTIONS, &config.content_type_options),
+            (header::X_FRAME_OPTIONS, &config.frame_options),
+            (header::CONTENT_SECURITY_POLICY, &config.content_security_policy),
+            (header::REFERRER_POLICY, &config.referrer_policy),
+            (HeaderName::from_static("permissions-policy"), &config.permissions_policy),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = header_value(&name, value.as_deref()?)?;
+            Some((name, value))
+        })
+        .collect();
+
+        let hsts = (config.hsts_max_age_secs > 0).then(|| {
+            let mut value = format!("max-age={}", config.hsts_max_age_secs);
+            if config.hsts_include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            if config.hsts_preload {
+                value.push_str("; preload");
+            }
+            HeaderValue::try_from(value).unwrap()
+        });
+
+        let auth_cache_control = config
+            .auth_cache_control
+            .as_deref()
+            .and_then(|value| header_value(&header::CACHE_CONTROL, value));
+
+        Self { fixed, hsts, auth_cache_control }
+    }
+}
+
+fn header_value(name: &HeaderName, value: &str) -> Option<HeaderValue> {
+    match HeaderValue::from_str(value) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!(header = %name, "Ignoring invalid security header value");
+            None
+        }
+    }
+}
+
+/// Add the configured security headers to every response, keeping any a
+/// handler set itself. HSTS is only sent over HTTPS, as browsers ignore it
+/// on plain HTTP.
+pub async fn security_headers(
+    State(state): State<AppState>,
+    scheme: Scheme,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    let security_headers = &state.security_headers;
+
+    for (name, value) in &security_headers.fixed {
+        headers.entry(name).or_insert_with(|| value.clone());
+    }
+    if let Some(hsts) = &security_headers.hsts
+        && scheme.is_https()
+    {
+        headers.entry(header::STRICT_TRANSPORT_SECURITY).or_insert_with(|| hsts.clone());
+    }
+
+    response
+}
+
+/// Keep tokens and account data out of browser and proxy caches.
+pub async fn no_store(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    if let Some(cache_control) = &state.security_headers.auth_cache_control {
+        response.headers_mut().insert(header::CACHE_CONTROL, cache_control.clone());
+    }
+    response
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AuditEventType {
+    Login,
+    MagicLinkLogin,
+    Logout,
+    TokenRefresh,
+    Registration,
+    PasswordReset,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    StatusChanged,
+    RoleChanged,
+    PasswordResetForced,
+    SessionsRevoked,
+    ImpersonationStarted,
+    ImpersonationStopped,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub event_type: AuditEventType,
+    pub outcome: AuditOutcome,
+    /// Who performed the action, when not the user themselves
+    pub actor_id: Option<i64>,
+    /// The account the event is about
+    pub user_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// JSON object with event-specific details
+    pub metadata: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event to be recorded. Request details are filled in by `AuditLog`.
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub event_type: AuditEventType,
+    pub outcome: AuditOutcome,
+    pub actor_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Optional filters for querying the audit log.
+#[derive(Debug, Default)]
+pub struct AuditEventFilter {
+    pub user_id: Option<i64>,
+    pub actor_id: Option<i64>,
+    pub event_type: Option<AuditEventType>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl NewAuditEvent {
+    pub fn success(event_type: AuditEventType) -> Self {
+        Self::new(event_type, AuditOutcome::Success)
+    }
+
+    pub fn failure(event_type: AuditEventType) -> Self {
+        Self::new(event_type, AuditOutcome::Failure)
+    }
+
+    fn new(event_type: AuditEventType, outcome: AuditOutcome) -> Self {
+        Self {
+            event_type,
+            outcome,
+            actor_id: None,
+            user_id: None,
+            ip: None,
+            user_agent: None,
+            metadata: None,
+        }
+    }
+
+    pub fn user(mut self, user_id: i64) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn actor(mut self, actor_id: i64) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
@@ -7,7 +7,17 @@ pub struct AccessClaims {
     pub sub: i64,          // user id
     pub exp: i64,          // expiration time
     pub iat: i64,          // issued at
-    pub token_type: String // "access"
+    #[serde(default)]
+    pub iat_ms: i64,       // issued at in milliseconds, ordered against session revocations
+    pub token_type: String, // "access"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaim>, // acting party when impersonating (RFC 8693)
+}
+
+/// RFC 8693 `act` claim: the admin acting on behalf of `sub`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActorClaim {
+    pub sub: i64,          // admin user id
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,10 +29,22 @@ pub struct RefreshClaims {
     pub token_type: String // "refresh"
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkClaims {
+    pub sub: i64,          // user id
+    pub exp: i64,          // expiration time
+    pub iat: i64,          // issued at
+    pub jti: String,       // single-use id, consumed in Redis
+    pub bnd: String,       // hash of the browser binding nonce
+    pub token_type: String // "magic_link"
+}
+
 #[derive(Debug, Serialize)]
 pub struct TokenPair {
     pub access_token: String,
     pub refresh_token: String,
+    #[serde(skip)]
+    pub user_id: i64,
 }
 
 impl AccessClaims {
@@ -34,7 +56,17 @@ impl AccessClaims {
             sub: user_id,
             exp: expires_at.timestamp(),
             iat: now.timestamp(),
+            iat_ms: now.timestamp_millis(),
             token_type: "access".to_string(),
+            act: None,
+        }
+    }
+
+    /// Access token for `user_id` issued to the admin `actor_id`.
+    pub fn impersonating(user_id: i64, actor_id: i64) -> Self {
+        Self {
+            act: Some(ActorClaim { sub: actor_id }),
+            ..Self::new(user_id)
         }
     }
 }
@@ -53,3 +85,19 @@ impl RefreshClaims {
         }
     }
 }
+
+impl MagicLinkClaims {
+    pub fn new(user_id: i64, jti: String, binding_hash: String, ttl: Duration) -> Self {
+        let now = Utc::now();
+        let expires_at = now + ttl;
+
+        Self {
+            sub: user_id,
+            exp: expires_at.timestamp(),
+            iat: now.timestamp(),
+            jti,
+            bnd: binding_hash,
+            token_type: "magic_link".to_string(),
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod user;
-pub mod jwt; 
\ No newline at end of file
+pub mod jwt;
+pub mod api_key;
+pub mod audit_event;
\ No newline at end of file
@@ -1,79 +1,104 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
-use bcrypt::{hash, DEFAULT_COST};
+use unicode_normalization::UnicodeNormalization;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum UserStatus {
+    Active,
+    Locked,
+    Disabled,
+    PendingVerification,
+}
+
+impl UserStatus {
+    /// Machine-readable error code returned when a non-active account is refused.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "account_active",
+            UserStatus::Locked => "account_locked",
+            UserStatus::Disabled => "account_disabled",
+            UserStatus::PendingVerification => "account_pending_verification",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct User {
     pub id: i64,
     pub username: String,
     pub password_hash: String,
     pub email: String,
+    pub role: Role,
+    pub status: UserStatus,
+    pub status_reason: Option<String>,
+    pub status_changed_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    pub created_at: DateTime<Utc>,
 }
 
-impl User {
-    pub async fn create(
-        pool: &SqlitePool,
-        username: &str,
-        password: &str,
-        email: &str,
-    ) -> Result<User, sqlx::Error> {
-        let password_hash = hash(password.as_bytes(), DEFAULT_COST)
-            .map_err(|_| sqlx::Error::Protocol("Failed to hash password".into()))?;
+/// Optional filters for listing users. `search` matches email or username.
+#[derive(Debug, Default)]
+pub struct UserFilter {
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub status: Option<UserStatus>,
+}
 
-        let user = sqlx::query_as!(
-            User,
-            r#"
-            INSERT INTO users (username, password_hash, email)
-            VALUES (?, ?, ?)
-            RETURNING 
-                id as "id!", 
-                username as "username!", 
-                password_hash as "password_hash!",
-                email as "email!"
-            "#,
-            username,
-            password_hash,
-            email
-        )
-        .fetch_one(pool)
-        .await?;
+impl UserFilter {
+    /// `search` as a substring `LIKE` pattern, with `\`, `%` and `_` escaped
+    /// so they match literally. Queries must say `ESCAPE '\'`.
+    pub fn search_pattern(&self) -> Option<String> {
+        self.search.as_ref().map(|search| {
+            let escaped = search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+}
 
-        Ok(user)
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
     }
 
-    pub async fn find_by_email(pool: &SqlitePool, email: &str) -> Result<Option<User>, sqlx::Error> {
-        sqlx::query_as!(
-            User,
-            r#"
-            SELECT 
-                id as "id!", 
-                username as "username!", 
-                password_hash as "password_hash!",
-                email as "email!"
-            FROM users
-            WHERE email = ?
-            "#,
-            email
-        )
-        .fetch_optional(pool)
-        .await
+    pub fn is_active(&self) -> bool {
+        self.status == UserStatus::Active
     }
+}
 
-    pub async fn find_by_id(pool: &SqlitePool, user_id: i64) -> Result<Option<User>, sqlx::Error> {
-        sqlx::query_as!(
-            User,
-            r#"
-            SELECT 
-                id as "id!", 
-                username as "username!", 
-                password_hash as "password_hash!",
-                email as "email!"
-            FROM users
-            WHERE id = ?
-            "#,
-            user_id
-        )
-        .fetch_optional(pool)
-        .await
-    }    
-} 
\ No newline at end of file
+/// Canonical form of an email address: trimmed, with the domain lowercased,
+/// and the local part too when `lowercase_local_part` is set. Lookups ignore
+/// case either way; this only decides what is stored and mailed to.
+pub fn normalize_email(email: &str, lowercase_local_part: bool) -> String {
+    let email = email.trim();
+    match email.rsplit_once('@') {
+        Some((local, domain)) if lowercase_local_part => format!("{}@{}", local.to_lowercase(), domain.to_lowercase()),
+        Some((local, domain)) => format!("{}@{}", local, domain.to_lowercase()),
+        None => email.to_string(),
+    }
+}
+
+/// Canonical form of a username: NFKC-normalised, so compatibility forms
+/// such as full-width letters collapse into one name, then trimmed. Case is
+/// kept for display; lookups ignore it.
+pub fn normalize_username(username: &str) -> String {
+    username.nfkc().collect::<String>().trim().to_string()
+}
+
+/// What emails and usernames are matched on, stored as `email_key` and
+/// `username_key`: NFKC-normalised, trimmed and lowercased with Unicode rules,
+/// so `Ölaf` and `ölaf` are one account on every backend.
+pub fn identity_key(value: &str) -> String {
+    value.nfkc().collect::<String>().trim().to_lowercase()
+}
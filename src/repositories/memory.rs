@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use super::UserRepository;
+use crate::models::user::{Role, User, UserFilter, UserStatus, identity_key};
+
+/// Users kept in a `Vec`, for testing services without a database. Clones
+/// share the same users, so a test can keep one to inspect what a service
+/// wrote through another.
+#[derive(Clone, Default)]
+pub struct InMemoryUserRepository {
+    users: Arc<Mutex<Vec<User>>>,
+}
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, user_id: i64, change: impl FnOnce(&mut User)) {
+        if let Some(user) = self.users.lock().unwrap().iter_mut().find(|user| user.id == user_id) {
+            change(user);
+        }
+    }
+
+    fn matching(&self, filter: &UserFilter) -> Vec<User> {
+        let search = filter.search.as_ref().map(|s| s.to_lowercase());
+        self.users
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|user| {
+                // Case-insensitive like SQLite's LIKE
+                search.as_ref().is_none_or(|search| {
+                    user.email.to_lowercase().contains(search) || user.username.to_lowercase().contains(search)
+                })
+            })
+            .filter(|user| filter.role.is_none_or(|role| user.role == role))
+            .filter(|user| filter.status.is_none_or(|status| user.status == status))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn create(&self, username: &str, password_hash: &str, email: &str) -> Result<User, sqlx::Error> {
+        let mut users = self.users.lock().unwrap();
+        if users
+            .iter()
+            .any(|user| identity_key(&user.email) == identity_key(email) || identity_key(&user.username) == identity_key(username))
+        {
+            return Err(sqlx::Error::Protocol("UNIQUE constraint failed: users".into()));
+        }
+
+        let user = User {
+            id: users.last().map_or(1, |user| user.id + 1),
+            username: username.to_string(),
+            password_hash: password_hash.to_string(),
+            email: email.to_string(),
+            role: Role::User,
+            status: UserStatus::Active,
+            status_reason: None,
+            status_changed_at: None,
+            password_reset_required: false,
+            created_at: Utc::now(),
+        };
+        users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let email = identity_key(email);
+        Ok(self.users.lock().unwrap().iter().find(|user| identity_key(&user.email) == email).cloned())
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        let username = identity_key(username);
+        Ok(self.users.lock().unwrap().iter().find(|user| identity_key(&user.username) == username).cloned())
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.users.lock().unwrap().iter().find(|user| user.id == user_id).cloned())
+    }
+
+    async fn list(&self, filter: &UserFilter, limit: i64, offset: i64) -> Result<Vec<User>, sqlx::Error> {
+        Ok(self
+            .matching(filter)
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64, sqlx::Error> {
+        Ok(self.matching(filter).len() as i64)
+    }
+
+    async fn set_role(&self, user_id: i64, role: Role) -> Result<(), sqlx::Error> {
+        self.update(user_id, |user| user.role = role);
+        Ok(())
+    }
+
+    async fn set_status(&self, user_id: i64, status: UserStatus, reason: Option<&str>) -> Result<(), sqlx::Error> {
+        self.update(user_id, |user| {
+            user.status = status;
+            user.status_reason = reason.map(str::to_string);
+            user.status_changed_at = Some(Utc::now());
+        });
+        Ok(())
+    }
+
+    async fn set_password_reset_required(&self, user_id: i64, required: bool) -> Result<(), sqlx::Error> {
+        self.update(user_id, |user| user.password_reset_required = required);
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<(), sqlx::Error> {
+        self.update(user_id, |user| {
+            user.password_hash = password_hash.to_string();
+            user.password_reset_required = false;
+        });
+        Ok(())
+    }
+
+    async fn replace_password_hash(&self, user_id: i64, current: &str, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let mut users = self.users.lock().unwrap();
+        let user = users.iter_mut().find(|user| user.id == user_id && user.password_hash == current);
+        Ok(user.map(|user| user.password_hash = password_hash.to_string()).is_some())
+    }
+}
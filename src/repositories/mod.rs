@@ -0,0 +1,103 @@
+//! Persistence behind traits, so services work the same on every backend.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::db::Database;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_event::{AuditEvent, AuditEventFilter, NewAuditEvent};
+use crate::models::user::{Role, User, UserFilter, UserStatus};
+
+#[cfg(test)]
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn create(&self, username: &str, password_hash: &str, email: &str) -> Result<User, sqlx::Error>;
+
+    /// Emails and usernames match by `identity_key`, regardless of case.
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error>;
+
+    /// Ordered by id.
+    async fn list(&self, filter: &UserFilter, limit: i64, offset: i64) -> Result<Vec<User>, sqlx::Error>;
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64, sqlx::Error>;
+
+    async fn set_role(&self, user_id: i64, role: Role) -> Result<(), sqlx::Error>;
+
+    async fn set_status(&self, user_id: i64, status: UserStatus, reason: Option<&str>) -> Result<(), sqlx::Error>;
+
+    async fn set_password_reset_required(&self, user_id: i64, required: bool) -> Result<(), sqlx::Error>;
+
+    /// Replace the password hash and clear any pending forced reset.
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<(), sqlx::Error>;
+
+    /// Swap in a new hash of the same password, only if the stored hash is still
+    /// `current`, leaving any forced reset in place. Returns whether it was swapped.
+    async fn replace_password_hash(&self, user_id: i64, current: &str, password_hash: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey, sqlx::Error>;
+
+    async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+
+    async fn list_for_user(&self, user_id: i64) -> Result<Vec<ApiKey>, sqlx::Error>;
+
+    /// Delete a key owned by `user_id`. Returns `false` if no such key exists.
+    async fn delete_for_user(&self, user_id: i64, key_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn touch_last_used(&self, key_id: i64) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+pub trait AuditEventRepository: Send + Sync {
+    async fn create(&self, event: &NewAuditEvent) -> Result<AuditEvent, sqlx::Error>;
+
+    /// Newest events first.
+    async fn list(&self, filter: &AuditEventFilter, limit: i64, offset: i64) -> Result<Vec<AuditEvent>, sqlx::Error>;
+
+    async fn count(&self, filter: &AuditEventFilter) -> Result<i64, sqlx::Error>;
+}
+
+/// One repository of each kind, sharing a backend.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+    pub audit_events: Arc<dyn AuditEventRepository>,
+}
+
+impl Repositories {
+    pub fn new(db: &Database) -> Self {
+        match db {
+            Database::Sqlite(pool) => Self {
+                users: Arc::new(sqlite::SqliteUserRepository::new(pool.clone())),
+                api_keys: Arc::new(sqlite::SqliteApiKeyRepository::new(pool.clone())),
+                audit_events: Arc::new(sqlite::SqliteAuditEventRepository::new(pool.clone())),
+            },
+            Database::Postgres(pool) => Self {
+                users: Arc::new(postgres::PgUserRepository::new(pool.clone())),
+                api_keys: Arc::new(postgres::PgApiKeyRepository::new(pool.clone())),
+                audit_events: Arc::new(postgres::PgAuditEventRepository::new(pool.clone())),
+            },
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::{ApiKeyRepository, AuditEventRepository, UserRepository};
+use crate::models::api_key::ApiKey;
+use crate::models::audit_event::{AuditEvent, AuditEventFilter, NewAuditEvent};
+use crate::models::user::{Role, User, UserFilter, UserStatus, identity_key};
+
+// Queries are checked at runtime here: the compile-time macros can only
+// describe one backend, and that is SQLite.
+
+macro_rules! user_columns {
+    () => {
+        "id, username, password_hash, email, role, status, status_reason, status_changed_at, \
+         password_reset_required, created_at"
+    };
+}
+
+macro_rules! api_key_columns {
+    () => {
+        "id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at, created_at"
+    };
+}
+
+macro_rules! audit_event_columns {
+    () => {
+        "id, event_type, outcome, actor_id, user_id, ip, user_agent, metadata, created_at"
+    };
+}
+
+// Untyped NULL parameters need a cast before Postgres will compare them
+macro_rules! user_filter {
+    () => {
+        "WHERE ($1::text IS NULL OR email ILIKE $1 ESCAPE '\\' OR username ILIKE $1 ESCAPE '\\')
+           AND ($2::text IS NULL OR role = $2)
+           AND ($3::text IS NULL OR status = $3)"
+    };
+}
+
+macro_rules! audit_event_filter {
+    () => {
+        "WHERE ($1::bigint IS NULL OR user_id = $1)
+           AND ($2::bigint IS NULL OR actor_id = $2)
+           AND ($3::text IS NULL OR event_type = $3)
+           AND ($4::text IS NULL OR outcome = $4)
+           AND ($5::timestamptz IS NULL OR created_at >= $5)
+           AND ($6::timestamptz IS NULL OR created_at < $6)"
+    };
+}
+
+#[derive(Clone)]
+pub struct PgUserRepository {
+    pool: PgPool,
+}
+
+impl PgUserRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    async fn create(&self, username: &str, password_hash: &str, email: &str) -> Result<User, sqlx::Error> {
+        sqlx::query_as(concat!(
+            "INSERT INTO users (username, password_hash, email, username_key, email_key) ",
+            "VALUES ($1, $2, $3, $4, $5) RETURNING ",
+            user_columns!()
+        ))
+        .bind(username)
+        .bind(password_hash)
+        .bind(email)
+        .bind(identity_key(username))
+        .bind(identity_key(email))
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as(concat!("SELECT ", user_columns!(), " FROM users WHERE email_key = $1"))
+            .bind(identity_key(email))
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as(concat!("SELECT ", user_columns!(), " FROM users WHERE username_key = $1"))
+            .bind(identity_key(username))
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as(concat!("SELECT ", user_columns!(), " FROM users WHERE id = $1"))
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn list(&self, filter: &UserFilter, limit: i64, offset: i64) -> Result<Vec<User>, sqlx::Error> {
+        let search = filter.search_pattern();
+        sqlx::query_as(concat!(
+            "SELECT ",
+            user_columns!(),
+            " FROM users ",
+            user_filter!(),
+            " ORDER BY id LIMIT $4 OFFSET $5"
+        ))
+        .bind(search)
+        .bind(filter.role)
+        .bind(filter.status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64, sqlx::Error> {
+        let search = filter.search_pattern();
+        sqlx::query_scalar(concat!("SELECT COUNT(*) FROM users ", user_filter!()))
+            .bind(search)
+            .bind(filter.role)
+            .bind(filter.status)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn set_role(&self, user_id: i64, role: Role) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET role = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+            .bind(role)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_status(&self, user_id: i64, status: UserStatus, reason: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET status = $1, status_reason = $2, status_changed_at = $3, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $4
+            "#,
+        )
+        .bind(status)
+        .bind(reason)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_password_reset_required(&self, user_id: i64, required: bool) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET password_reset_required = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+            .bind(required)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = $1, password_reset_required = FALSE, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $2
+            "#,
+        )
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn replace_password_hash(&self, user_id: i64, current: &str, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3")
+            .bind(password_hash)
+            .bind(user_id)
+            .bind(current)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+#[derive(Clone)]
+pub struct PgApiKeyRepository {
+    pool: PgPool,
+}
+
+impl PgApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for PgApiKeyRepository {
+    async fn create(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey, sqlx::Error> {
+        sqlx::query_as(concat!(
+            "INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at) ",
+            "VALUES ($1, $2, $3, $4, $5, $6) RETURNING ",
+            api_key_columns!()
+        ))
+        .bind(user_id)
+        .bind(name)
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(scopes)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as(concat!("SELECT ", api_key_columns!(), " FROM api_keys WHERE prefix = $1"))
+            .bind(prefix)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn list_for_user(&self, user_id: i64) -> Result<Vec<ApiKey>, sqlx::Error> {
+        sqlx::query_as(concat!("SELECT ", api_key_columns!(), " FROM api_keys WHERE user_id = $1 ORDER BY id"))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn delete_for_user(&self, user_id: i64, key_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_id = $2")
+            .bind(key_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch_last_used(&self, key_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_keys SET last_used_at = $1 WHERE id = $2")
+            .bind(Utc::now())
+            .bind(key_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct PgAuditEventRepository {
+    pool: PgPool,
+}
+
+impl PgAuditEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AuditEventRepository for PgAuditEventRepository {
+    async fn create(&self, event: &NewAuditEvent) -> Result<AuditEvent, sqlx::Error> {
+        let metadata = event.metadata.as_ref().map(|m| m.to_string());
+
+        sqlx::query_as(concat!(
+            "INSERT INTO audit_events (event_type, outcome, actor_id, user_id, ip, user_agent, metadata, created_at) ",
+            "VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING ",
+            audit_event_columns!()
+        ))
+        .bind(event.event_type)
+        .bind(event.outcome)
+        .bind(event.actor_id)
+        .bind(event.user_id)
+        .bind(&event.ip)
+        .bind(&event.user_agent)
+        .bind(metadata)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn list(&self, filter: &AuditEventFilter, limit: i64, offset: i64) -> Result<Vec<AuditEvent>, sqlx::Error> {
+        sqlx::query_as(concat!(
+            "SELECT ",
+            audit_event_columns!(),
+            " FROM audit_events ",
+            audit_event_filter!(),
+            " ORDER BY created_at DESC, id DESC LIMIT $7 OFFSET $8"
+        ))
+        .bind(filter.user_id)
+        .bind(filter.actor_id)
+        .bind(filter.event_type)
+        .bind(filter.outcome)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self, filter: &AuditEventFilter) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(concat!("SELECT COUNT(*) FROM audit_events ", audit_event_filter!()))
+            .bind(filter.user_id)
+            .bind(filter.actor_id)
+            .bind(filter.event_type)
+            .bind(filter.outcome)
+            .bind(filter.since)
+            .bind(filter.until)
+            .fetch_one(&self.pool)
+            .await
+    }
+}
@@ -0,0 +1,521 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use super::{ApiKeyRepository, AuditEventRepository, UserRepository};
+use crate::models::api_key::ApiKey;
+use crate::models::audit_event::{AuditEvent, AuditEventFilter, AuditEventType, AuditOutcome, NewAuditEvent};
+use crate::models::user::{Role, User, UserFilter, UserStatus, identity_key};
+
+#[derive(Clone)]
+pub struct SqliteUserRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteUserRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn create(
+        &self,
+        username: &str,
+        password_hash: &str,
+        email: &str,
+    ) -> Result<User, sqlx::Error> {
+        let (username_key, email_key) = (identity_key(username), identity_key(email));
+        sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (username, password_hash, email, username_key, email_key)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING
+                id as "id!",
+                username as "username!",
+                password_hash as "password_hash!",
+                email as "email!",
+                role as "role!: Role",
+                status as "status!: UserStatus",
+                status_reason,
+                status_changed_at as "status_changed_at: DateTime<Utc>",
+                password_reset_required as "password_reset_required!: bool",
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            username,
+            password_hash,
+            email,
+            username_key,
+            email_key
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let email_key = identity_key(email);
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                id as "id!",
+                username as "username!",
+                password_hash as "password_hash!",
+                email as "email!",
+                role as "role!: Role",
+                status as "status!: UserStatus",
+                status_reason,
+                status_changed_at as "status_changed_at: DateTime<Utc>",
+                password_reset_required as "password_reset_required!: bool",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM users
+            WHERE email_key = ?
+            "#,
+            email_key
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        let username_key = identity_key(username);
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                id as "id!",
+                username as "username!",
+                password_hash as "password_hash!",
+                email as "email!",
+                role as "role!: Role",
+                status as "status!: UserStatus",
+                status_reason,
+                status_changed_at as "status_changed_at: DateTime<Utc>",
+                password_reset_required as "password_reset_required!: bool",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM users
+            WHERE username_key = ?
+            "#,
+            username_key
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                id as "id!",
+                username as "username!",
+                password_hash as "password_hash!",
+                email as "email!",
+                role as "role!: Role",
+                status as "status!: UserStatus",
+                status_reason,
+                status_changed_at as "status_changed_at: DateTime<Utc>",
+                password_reset_required as "password_reset_required!: bool",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM users
+            WHERE id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn list(
+        &self,
+        filter: &UserFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<User>, sqlx::Error> {
+        let search = filter.search_pattern();
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                id as "id!",
+                username as "username!",
+                password_hash as "password_hash!",
+                email as "email!",
+                role as "role!: Role",
+                status as "status!: UserStatus",
+                status_reason,
+                status_changed_at as "status_changed_at: DateTime<Utc>",
+                password_reset_required as "password_reset_required!: bool",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM users
+            WHERE (? IS NULL OR email LIKE ? ESCAPE '\' OR username LIKE ? ESCAPE '\')
+              AND (? IS NULL OR role = ?)
+              AND (? IS NULL OR status = ?)
+            ORDER BY id
+            LIMIT ? OFFSET ?
+            "#,
+            search,
+            search,
+            search,
+            filter.role,
+            filter.role,
+            filter.status,
+            filter.status,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64, sqlx::Error> {
+        let search = filter.search_pattern();
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM users
+            WHERE (? IS NULL OR email LIKE ? ESCAPE '\' OR username LIKE ? ESCAPE '\')
+              AND (? IS NULL OR role = ?)
+              AND (? IS NULL OR status = ?)
+            "#,
+            search,
+            search,
+            search,
+            filter.role,
+            filter.role,
+            filter.status,
+            filter.status
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn set_role(&self, user_id: i64, role: Role) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE users SET role = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            role,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_status(
+        &self,
+        user_id: i64,
+        status: UserStatus,
+        reason: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET status = ?, status_reason = ?, status_changed_at = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+            status,
+            reason,
+            now,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_password_reset_required(
+        &self,
+        user_id: i64,
+        required: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE users SET password_reset_required = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            required,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = ?, password_reset_required = FALSE, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+            password_hash,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn replace_password_hash(&self, user_id: i64, current: &str, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = ? WHERE id = ? AND password_hash = ?",
+            password_hash,
+            user_id,
+            current
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+#[derive(Clone)]
+pub struct SqliteApiKeyRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteApiKeyRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for SqliteApiKeyRepository {
+    async fn create(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                key_hash as "key_hash!",
+                scopes as "scopes!",
+                expires_at as "expires_at: DateTime<Utc>",
+                last_used_at as "last_used_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            user_id,
+            name,
+            prefix,
+            key_hash,
+            scopes,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                key_hash as "key_hash!",
+                scopes as "scopes!",
+                expires_at as "expires_at: DateTime<Utc>",
+                last_used_at as "last_used_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM api_keys
+            WHERE prefix = ?
+            "#,
+            prefix
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn list_for_user(&self, user_id: i64) -> Result<Vec<ApiKey>, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                key_hash as "key_hash!",
+                scopes as "scopes!",
+                expires_at as "expires_at: DateTime<Utc>",
+                last_used_at as "last_used_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM api_keys
+            WHERE user_id = ?
+            ORDER BY id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn delete_for_user(&self, user_id: i64, key_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM api_keys WHERE id = ? AND user_id = ?",
+            key_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch_last_used(&self, key_id: i64) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query!(
+            "UPDATE api_keys SET last_used_at = ? WHERE id = ?",
+            now,
+            key_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct SqliteAuditEventRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteAuditEventRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AuditEventRepository for SqliteAuditEventRepository {
+    async fn create(&self, event: &NewAuditEvent) -> Result<AuditEvent, sqlx::Error> {
+        let metadata = event.metadata.as_ref().map(|m| m.to_string());
+        let now = Utc::now();
+
+        sqlx::query_as!(
+            AuditEvent,
+            r#"
+            INSERT INTO audit_events (event_type, outcome, actor_id, user_id, ip, user_agent, metadata, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING
+                id as "id!",
+                event_type as "event_type!: AuditEventType",
+                outcome as "outcome!: AuditOutcome",
+                actor_id,
+                user_id,
+                ip,
+                user_agent,
+                metadata,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            event.event_type,
+            event.outcome,
+            event.actor_id,
+            event.user_id,
+            event.ip,
+            event.user_agent,
+            metadata,
+            now
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn list(
+        &self,
+        filter: &AuditEventFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuditEvent>, sqlx::Error> {
+        sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT
+                id as "id!",
+                event_type as "event_type!: AuditEventType",
+                outcome as "outcome!: AuditOutcome",
+                actor_id,
+                user_id,
+                ip,
+                user_agent,
+                metadata,
+                created_at as "created_at!: DateTime<Utc>"
+            FROM audit_events
+            WHERE (? IS NULL OR user_id = ?)
+              AND (? IS NULL OR actor_id = ?)
+              AND (? IS NULL OR event_type = ?)
+              AND (? IS NULL OR outcome = ?)
+              AND (? IS NULL OR created_at >= ?)
+              AND (? IS NULL OR created_at < ?)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            filter.user_id,
+            filter.user_id,
+            filter.actor_id,
+            filter.actor_id,
+            filter.event_type,
+            filter.event_type,
+            filter.outcome,
+            filter.outcome,
+            filter.since,
+            filter.since,
+            filter.until,
+            filter.until,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self, filter: &AuditEventFilter) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM audit_events
+            WHERE (? IS NULL OR user_id = ?)
+              AND (? IS NULL OR actor_id = ?)
+              AND (? IS NULL OR event_type = ?)
+              AND (? IS NULL OR outcome = ?)
+              AND (? IS NULL OR created_at >= ?)
+              AND (? IS NULL OR created_at < ?)
+            "#,
+            filter.user_id,
+            filter.user_id,
+            filter.actor_id,
+            filter.actor_id,
+            filter.event_type,
+            filter.event_type,
+            filter.outcome,
+            filter.outcome,
+            filter.since,
+            filter.since,
+            filter.until,
+            filter.until
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+}
@@ -0,0 +1,237 @@
+mod tls;
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::ConnectInfo;
+use http::Request;
+use http::uri::Scheme;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+#[cfg(unix)]
+use crate::api::forwarded::UnixPeer;
+use crate::config::Config;
+
+pub use tls::TlsAcceptor;
+
+/// Where the server accepts connections.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// Who is on the other end of a connection, as tagged on its requests.
+#[derive(Clone, Copy)]
+enum Peer {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix,
+}
+
+enum Connection {
+    Tcp(TcpStream, SocketAddr),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Listener {
+    /// Bind the configured Unix socket, or `host:port` when none is set.
+    pub async fn bind(config: &Config) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = &config.unix_socket {
+            use std::os::unix::fs::FileTypeExt;
+
+            // A socket left behind by an unclean exit would make the bind fail
+            if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            return UnixListener::bind(path).map(Listener::Unix);
+        }
+
+        TcpListener::bind((config.host.as_str(), config.port)).await.map(Listener::Tcp)
+    }
+
+    /// The bound address, for logging.
+    pub fn local_addr(&self) -> io::Result<String> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().map(|addr| addr.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener
+                .local_addr()
+                .map(|addr| format!("unix:{}", addr.as_pathname().unwrap_or(std::path::Path::new("")).display())),
+        }
+    }
+
+    async fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok(Connection::Tcp(stream, addr))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok(Connection::Unix(stream))
+            }
+        }
+    }
+}
+
+/// Serve `app` over HTTP/1.1 and HTTP/2, terminating TLS when `tls` is set,
+/// until `shutdown` resolves. Then stop accepting connections and wait up to
+/// `drain_timeout` for in-flight requests to finish. Requests still running
+/// after that are dropped.
+pub async fn serve(
+    listener: Listener,
+    tls: Option<TlsAcceptor>,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    drain_timeout: Duration,
+) {
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let connection = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(connection) => connection,
+                Err(e) if is_connection_error(&e) => continue,
+                Err(e) => {
+                    // Usually out of file descriptors; back off instead of spinning
+                    warn!(error = %e, "Failed to accept connection");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let watcher = graceful.watcher();
+        let builder = builder.clone();
+        let tls = tls.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            match connection {
+                Connection::Tcp(stream, addr) => {
+                    serve_connection(stream, Peer::Tcp(addr), tls, app, builder, watcher).await
+                }
+                #[cfg(unix)]
+                Connection::Unix(stream) => serve_connection(stream, Peer::Unix, tls, app, builder, watcher).await,
+            }
+        });
+    }
+
+    drop(listener);
+    info!(timeout_secs = drain_timeout.as_secs(), "Shutdown signal received, draining connections");
+
+    tokio::select! {
+        _ = graceful.shutdown() => info!("All connections drained"),
+        _ = tokio::time::sleep(drain_timeout) => {
+            warn!("Drain timeout elapsed, dropping remaining connections");
+        }
+    }
+}
+
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset
+    )
+}
+
+async fn serve_connection<IO>(
+    io: IO,
+    peer: Peer,
+    tls: Option<TlsAcceptor>,
+    app: Router,
+    builder: Builder<TokioExecutor>,
+    watcher: Watcher,
+) where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    match tls {
+        Some(tls) => match tls.accept(io).await {
+            Ok(stream) => serve_http(stream, peer, Scheme::HTTPS, app, builder, watcher).await,
+            Err(e) => debug!(error = %e, "TLS handshake failed"),
+        },
+        None => serve_http(io, peer, Scheme::HTTP, app, builder, watcher).await,
+    }
+}
+
+/// Run one connection, tagging each request with its peer (the address over
+/// TCP, `UnixPeer` over a Unix socket) and the scheme it arrived over.
+async fn serve_http<IO>(
+    io: IO,
+    peer: Peer,
+    scheme: Scheme,
+    app: Router,
+    builder: Builder<TokioExecutor>,
+    watcher: Watcher,
+) where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |mut request: Request<Incoming>| {
+        match peer {
+            Peer::Tcp(addr) => {
+                request.extensions_mut().insert(ConnectInfo(addr));
+            }
+            #[cfg(unix)]
+            Peer::Unix => {
+                request.extensions_mut().insert(UnixPeer);
+            }
+        }
+        request.extensions_mut().insert(scheme.clone());
+        app.clone().oneshot(request)
+    });
+
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+    if let Err(e) = watcher.watch(connection).await {
+        debug!(error = %e, "Connection closed with an error");
+    }
+}
+
+/// Resolve on Ctrl+C or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
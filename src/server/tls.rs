@@ -0,0 +1,119 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::MissedTickBehavior;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::server::TlsStream;
+use tracing::{info, warn};
+
+/// Longest a client may take to complete the handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Terminates TLS with a certificate read from PEM files, picking up a
+/// renewed certificate without a restart.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    config: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl TlsAcceptor {
+    /// Load the certificate chain and key. Unless `reload_interval` is zero,
+    /// the files are polled for changes for as long as the acceptor is alive.
+    pub fn from_pem_files(
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+        reload_interval: Duration,
+    ) -> io::Result<Self> {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let config = Arc::new(RwLock::new(Arc::new(load_config(&cert_path, &key_path)?)));
+
+        if !reload_interval.is_zero() {
+            tokio::spawn(watch_files(Arc::downgrade(&config), cert_path, key_path, reload_interval));
+        }
+
+        Ok(Self { config })
+    }
+
+    pub async fn accept<IO>(&self, stream: IO) -> io::Result<TlsStream<IO>>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let config = self.config.read().unwrap().clone();
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, tokio_rustls::TlsAcceptor::from(config).accept(stream))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out"))?
+    }
+}
+
+fn load_config(cert_path: &Path, key_path: &Path) -> io::Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid_file(cert_path, e))?;
+    if certs.is_empty() {
+        return Err(invalid_file(cert_path, "no certificates found"));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| invalid_file(key_path, e))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(io::Error::other)?;
+    // Let clients negotiate HTTP/2, falling back to HTTP/1.1
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn invalid_file(path: &Path, error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error))
+}
+
+/// Swap in a new config whenever either file's modification time changes.
+/// A file that fails to load is logged and the current certificate kept.
+async fn watch_files(
+    config: Weak<RwLock<Arc<ServerConfig>>>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    interval: Duration,
+) {
+    let mut last_modified = modified(&cert_path, &key_path);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        let Some(config) = config.upgrade() else {
+            return;
+        };
+
+        let modified = modified(&cert_path, &key_path);
+        if modified == last_modified {
+            continue;
+        }
+        // Also recorded on failure, so a half-written pair is retried once
+        // the other file changes rather than on every tick
+        last_modified = modified;
+
+        match load_config(&cert_path, &key_path) {
+            Ok(new_config) => {
+                *config.write().unwrap() = Arc::new(new_config);
+                info!(cert = %cert_path.display(), "Reloaded TLS certificate");
+            }
+            Err(e) => warn!(error = %e, "Failed to reload TLS certificate, keeping the current one"),
+        }
+    }
+}
+
+fn modified(cert_path: &Path, key_path: &Path) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    (mtime(cert_path), mtime(key_path))
+}
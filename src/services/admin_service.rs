@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use tracing::{info, warn, instrument};
+
+use crate::models::user::{Role, User, UserFilter, UserStatus};
+use crate::repositories::UserRepository;
+use crate::services::auth_service::AuthError;
+use crate::services::jwt_service::JwtService;
+use crate::services::password_reset_service::PasswordResetService;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Clone)]
+pub struct AdminService {
+    users: Arc<dyn UserRepository>,
+    jwt_service: JwtService,
+    password_reset_service: PasswordResetService,
+}
+
+#[derive(Debug)]
+pub enum AdminError {
+    UserNotFound,
+    /// Admins may not disable or demote their own account
+    SelfModification,
+    /// Admins and inactive accounts cannot be impersonated
+    ImpersonationNotAllowed,
+    DatabaseError(sqlx::Error),
+    Auth(AuthError),
+}
+
+impl std::fmt::Display for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdminError::UserNotFound => write!(f, "user not found"),
+            AdminError::SelfModification => write!(f, "admins cannot modify their own role or status"),
+            AdminError::ImpersonationNotAllowed => write!(f, "this user cannot be impersonated"),
+            AdminError::DatabaseError(e) => write!(f, "database error: {}", e),
+            AdminError::Auth(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AdminError {
+    fn from(err: sqlx::Error) -> Self {
+        AdminError::DatabaseError(err)
+    }
+}
+
+impl From<AuthError> for AdminError {
+    fn from(err: AuthError) -> Self {
+        AdminError::Auth(err)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AdminError {
+    fn from(_: jsonwebtoken::errors::Error) -> Self {
+        AdminError::Auth(AuthError::TokenError)
+    }
+}
+
+/// One page of users plus the total number matching the filter.
+#[derive(Debug)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+impl AdminService {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        jwt_service: JwtService,
+        password_reset_service: PasswordResetService,
+    ) -> Self {
+        Self {
+            users,
+            jwt_service,
+            password_reset_service,
+        }
+    }
+
+    pub async fn list_users(&self, filter: &UserFilter, page: i64, per_page: i64) -> Result<UserPage, AdminError> {
+        let page = page.max(1);
+        let per_page = per_page.clamp(1, MAX_PAGE_SIZE);
+
+        let users = self.users.list(filter, per_page, (page - 1) * per_page).await?;
+        let total = self.users.count(filter).await?;
+
+        Ok(UserPage {
+            users,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    pub async fn get_user(&self, user_id: i64) -> Result<User, AdminError> {
+        self.users
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AdminError::UserNotFound)
+    }
+
+    /// Moving an account out of `active` also signs it out everywhere. The
+    /// actor is `None` for operators working from the command line.
+    #[instrument(skip(self, actor), fields(actor_id = ?actor.map(|actor| actor.id)))]
+    pub async fn set_status(
+        &self,
+        actor: Option<&User>,
+        user_id: i64,
+        status: UserStatus,
+        reason: Option<&str>,
+    ) -> Result<User, AdminError> {
+        if actor.is_some_and(|actor| actor.id == user_id) {
+            warn!("Admin attempted to change their own status");
+            return Err(AdminError::SelfModification);
+        }
+
+        let user = self.get_user(user_id).await?;
+        self.users.set_status(user.id, status, reason).await?;
+        if status != UserStatus::Active {
+            self.jwt_service.revoke_all_sessions(user.id).await?;
+        }
+
+        info!(user_id = %user.id, status = ?status, "User status changed");
+        self.get_user(user.id).await
+    }
+
+    #[instrument(skip(self, actor), fields(actor_id = ?actor.map(|actor| actor.id)))]
+    pub async fn set_role(&self, actor: Option<&User>, user_id: i64, role: Role) -> Result<User, AdminError> {
+        if actor.is_some_and(|actor| actor.id == user_id) {
+            warn!("Admin attempted to change their own role");
+            return Err(AdminError::SelfModification);
+        }
+
+        let user = self.get_user(user_id).await?;
+        self.users.set_role(user.id, role).await?;
+
+        info!(user_id = %user.id, role = ?role, "User role changed");
+        self.get_user(user.id).await
+    }
+
+    /// Require a new password: sessions are revoked and a reset link is emailed.
+    #[instrument(skip(self))]
+    pub async fn force_password_reset(&self, user_id: i64) -> Result<(), AdminError> {
+        let user = self.get_user(user_id).await?;
+
+        self.users.set_password_reset_required(user.id, true).await?;
+        self.jwt_service.revoke_all_sessions(user.id).await?;
+        self.password_reset_service.send_reset_link(&user).await?;
+
+        info!(user_id = %user.id, "Password reset forced by admin");
+        Ok(())
+    }
+
+    /// Issue an access token that lets `actor` act as `user_id`.
+    #[instrument(skip(self, actor), fields(actor_id = %actor.id))]
+    pub async fn impersonate(&self, actor: &User, user_id: i64) -> Result<String, AdminError> {
+        if actor.id == user_id {
+            warn!("Admin attempted to impersonate themselves");
+            return Err(AdminError::SelfModification);
+        }
+
+        let user = self.get_user(user_id).await?;
+        if user.is_admin() || !user.is_active() {
+            warn!(user_id = %user.id, "Impersonation refused");
+            return Err(AdminError::ImpersonationNotAllowed);
+        }
+
+        let token = self.jwt_service.create_impersonation_token(user.id, actor.id)?;
+
+        info!(user_id = %user.id, "Impersonation token issued");
+        Ok(token)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke_sessions(&self, user_id: i64) -> Result<usize, AdminError> {
+        let user = self.get_user(user_id).await?;
+        let revoked = self.jwt_service.revoke_all_sessions(user.id).await?;
+
+        info!(user_id = %user.id, revoked = %revoked, "All sessions revoked by admin");
+        Ok(revoked)
+    }
+}
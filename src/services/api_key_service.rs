@@ -0,0 +1,168 @@
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tracing::{info, warn, error, instrument};
+
+use crate::models::api_key::ApiKey;
+use crate::repositories::ApiKeyRepository;
+
+pub const API_KEY_PREFIX: &str = "ak";
+pub const SUPPORTED_SCOPES: &[&str] = &["user:read", "api_keys:read"];
+/// Longest lifetime a key may be created with, about ten years
+pub const MAX_EXPIRES_IN_DAYS: i64 = 3650;
+const PREFIX_LEN: usize = 8;
+const SECRET_LEN: usize = 32;
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    api_keys: Arc<dyn ApiKeyRepository>,
+}
+
+#[derive(Debug)]
+pub enum ApiKeyError {
+    InvalidKey,
+    Expired,
+    InvalidScope(String),
+    InvalidExpiry(i64),
+    NotFound,
+    DatabaseError(sqlx::Error),
+}
+
+impl std::fmt::Display for ApiKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiKeyError::InvalidKey => write!(f, "invalid API key"),
+            ApiKeyError::Expired => write!(f, "API key has expired"),
+            ApiKeyError::InvalidScope(scope) => write!(f, "unsupported scope: {}", scope),
+            ApiKeyError::InvalidExpiry(days) => write!(f, "unsupported expiry: {} days", days),
+            ApiKeyError::NotFound => write!(f, "API key not found"),
+            ApiKeyError::DatabaseError(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiKeyError {
+    fn from(err: sqlx::Error) -> Self {
+        ApiKeyError::DatabaseError(err)
+    }
+}
+
+/// A freshly created key. `key` is the only time the plaintext is available.
+#[derive(Debug)]
+pub struct CreatedApiKey {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+impl ApiKeyService {
+    pub fn new(api_keys: Arc<dyn ApiKeyRepository>) -> Self {
+        Self { api_keys }
+    }
+
+    /// Generate a new key for `user_id`. Only the hash and the visible prefix are stored.
+    #[instrument(skip(self))]
+    pub async fn create_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        scopes: &[String],
+        expires_in_days: Option<i64>,
+    ) -> Result<CreatedApiKey, ApiKeyError> {
+        if let Some(scope) = scopes.iter().find(|s| !SUPPORTED_SCOPES.contains(&s.as_str())) {
+            warn!(scope = %scope, "API key requested with unsupported scope");
+            return Err(ApiKeyError::InvalidScope(scope.clone()));
+        }
+
+        let prefix = Self::random_string(PREFIX_LEN);
+        let secret = Self::random_string(SECRET_LEN);
+        let key = format!("{}_{}_{}", API_KEY_PREFIX, prefix, secret);
+        let expires_at = match expires_in_days {
+            Some(days) => Some(
+                Duration::try_days(days)
+                    .filter(|_| (1..=MAX_EXPIRES_IN_DAYS).contains(&days))
+                    .and_then(|lifetime| Utc::now().checked_add_signed(lifetime))
+                    .ok_or(ApiKeyError::InvalidExpiry(days))?,
+            ),
+            None => None,
+        };
+
+        let api_key = self
+            .api_keys
+            .create(user_id, name, &prefix, &Self::hash_key(&key), &scopes.join(" "), expires_at)
+            .await?;
+
+        info!(user_id = %user_id, key_id = %api_key.id, prefix = %prefix, "API key created");
+        Ok(CreatedApiKey { api_key, key })
+    }
+
+    pub async fn list_keys(&self, user_id: i64) -> Result<Vec<ApiKey>, ApiKeyError> {
+        Ok(self.api_keys.list_for_user(user_id).await?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke_key(&self, user_id: i64, key_id: i64) -> Result<(), ApiKeyError> {
+        if self.api_keys.delete_for_user(user_id, key_id).await? {
+            info!(user_id = %user_id, key_id = %key_id, "API key revoked");
+            Ok(())
+        } else {
+            Err(ApiKeyError::NotFound)
+        }
+    }
+
+    /// Resolve a presented key to its stored record and record its use.
+    #[instrument(skip(self, key))]
+    pub async fn verify_key(&self, key: &str) -> Result<ApiKey, ApiKeyError> {
+        let prefix = Self::parse_prefix(key).ok_or(ApiKeyError::InvalidKey)?;
+
+        let api_key = self
+            .api_keys
+            .find_by_prefix(prefix)
+            .await?
+            .ok_or(ApiKeyError::InvalidKey)?;
+
+        let presented_hash = Self::hash_key(key);
+        if !bool::from(presented_hash.as_bytes().ct_eq(api_key.key_hash.as_bytes())) {
+            warn!(prefix = %prefix, "API key hash mismatch");
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        if api_key.is_expired() {
+            warn!(key_id = %api_key.id, "Expired API key presented");
+            return Err(ApiKeyError::Expired);
+        }
+
+        if let Err(e) = self.api_keys.touch_last_used(api_key.id).await {
+            error!(error = %e, key_id = %api_key.id, "Failed to record API key usage");
+        }
+
+        Ok(api_key)
+    }
+
+    /* ---------- PRIVATE HELPERS ---------- */
+
+    fn parse_prefix(key: &str) -> Option<&str> {
+        let mut parts = key.splitn(3, '_');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(API_KEY_PREFIX), Some(prefix), Some(secret))
+                if prefix.len() == PREFIX_LEN && secret.len() == SECRET_LEN =>
+            {
+                Some(prefix)
+            }
+            _ => None,
+        }
+    }
+
+    fn hash_key(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+
+    fn random_string(len: usize) -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect()
+    }
+}
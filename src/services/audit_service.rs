@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use tracing::{info, error};
+
+use crate::api::context::RequestContext;
+use crate::models::audit_event::{AuditEvent, AuditEventFilter, NewAuditEvent};
+use crate::repositories::AuditEventRepository;
+use crate::services::admin_service::MAX_PAGE_SIZE;
+
+/// Persistent log of security-relevant events.
+#[derive(Clone)]
+pub struct AuditLog {
+    events: Arc<dyn AuditEventRepository>,
+}
+
+/// One page of audit events plus the total number matching the filter.
+#[derive(Debug)]
+pub struct AuditEventPage {
+    pub events: Vec<AuditEvent>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+impl AuditLog {
+    pub fn new(events: Arc<dyn AuditEventRepository>) -> Self {
+        Self { events }
+    }
+
+    /// Record `event` with the client details from `context`. Failing to write
+    /// the audit trail is logged but never fails the request being audited.
+    pub async fn record(&self, context: &RequestContext, mut event: NewAuditEvent) {
+        event.ip = context.ip.clone();
+        event.user_agent = context.user_agent.clone();
+
+        info!(
+            target: "audit",
+            event_type = ?event.event_type,
+            outcome = ?event.outcome,
+            actor_id = ?event.actor_id,
+            user_id = ?event.user_id,
+            ip = ?event.ip,
+            "Audit event"
+        );
+
+        if let Err(e) = self.events.create(&event).await {
+            error!(error = %e, event_type = ?event.event_type, "Failed to write audit event");
+        }
+    }
+
+    pub async fn list(&self, filter: &AuditEventFilter, page: i64, per_page: i64) -> Result<AuditEventPage, sqlx::Error> {
+        let page = page.max(1);
+        let per_page = per_page.clamp(1, MAX_PAGE_SIZE);
+
+        let events = self.events.list(filter, per_page, (page - 1) * per_page).await?;
+        let total = self.events.count(filter).await?;
+
+        Ok(AuditEventPage {
+            events,
+            page,
+            per_page,
+            total,
+        })
+    }
+}
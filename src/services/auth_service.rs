@@ -1,15 +1,45 @@
-use sqlx::SqlitePool;
-use bcrypt::verify;
+use std::sync::Arc;
 use tracing::{info, warn, error, instrument};
 
-use crate::models::user::User;
+use crate::metrics::metrics;
+use crate::models::user::{User, UserStatus, normalize_email, normalize_username};
 use crate::models::jwt::TokenPair;
+use crate::repositories::UserRepository;
+use crate::services::email_service::EmailService;
 use crate::services::jwt_service::JwtService;
+use crate::services::password_hasher::PasswordHasher;
+use crate::services::password_policy::PasswordPolicy;
 
 #[derive(Clone)]
 pub struct AuthService {
-    pool: SqlitePool,
+    users: Arc<dyn UserRepository>,
     jwt_service: JwtService,
+    password_hasher: PasswordHasher,
+    password_policy: PasswordPolicy,
+    lowercase_email_local_part: bool,
+    registration_notices: Option<RegistrationNotices>,
+}
+
+/// What `register_or_notify` did.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Registration {
+    Created(i64),
+    /// The outcome was emailed rather than returned; `user_id` is set when an account was created
+    Notified { user_id: Option<i64> },
+}
+
+/// The outcome of a login, with the account it named when there was one, so
+/// failed attempts can be shown to the account's owner.
+#[derive(Debug)]
+pub struct LoginAttempt {
+    pub user_id: Option<i64>,
+    pub result: Result<TokenPair, AuthError>,
+}
+
+/// An existing account a registration collides with.
+enum Existing {
+    Email(User),
+    Username,
 }
 
 #[derive(Debug)]
@@ -20,6 +50,35 @@ pub enum AuthError {
     TokenError,
     InvalidToken,
     UserNotFound,
+    PasswordResetRequired,
+    AccountInactive(UserStatus),
+    /// Usernames must not be blank, and may not contain `@` so logins stay unambiguous
+    InvalidUsername,
+    /// Too many password hashes are already queued; the client should retry later
+    Overloaded,
+    /// The email address or username is taken, deliberately without saying which
+    AccountExists,
+    /// The new password appears in a known data breach
+    BreachedPassword,
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::InvalidCredentials => write!(f, "invalid credentials"),
+            AuthError::DatabaseError(e) => write!(f, "database error: {}", e),
+            AuthError::PasswordHashError => write!(f, "password hashing failed"),
+            AuthError::TokenError => write!(f, "token generation failed"),
+            AuthError::InvalidToken => write!(f, "invalid token"),
+            AuthError::UserNotFound => write!(f, "user not found"),
+            AuthError::PasswordResetRequired => write!(f, "password reset required"),
+            AuthError::AccountInactive(status) => write!(f, "account is not active: {:?}", status),
+            AuthError::InvalidUsername => write!(f, "usernames must not be blank or contain '@'"),
+            AuthError::Overloaded => write!(f, "too many password checks in progress"),
+            AuthError::AccountExists => write!(f, "email or username already registered"),
+            AuthError::BreachedPassword => write!(f, "password appears in a known data breach"),
+        }
+    }
 }
 
 impl From<sqlx::Error> for AuthError {
@@ -29,54 +88,135 @@ impl From<sqlx::Error> for AuthError {
 }
 
 impl From<jsonwebtoken::errors::Error> for AuthError {
-    fn from(_: jsonwebtoken::errors::Error) -> Self {
-        AuthError::TokenError
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        use jsonwebtoken::errors::ErrorKind;
+
+        // Rejected tokens are the client's problem; anything else is ours
+        match err.kind() {
+            ErrorKind::InvalidToken
+            | ErrorKind::InvalidSignature
+            | ErrorKind::ExpiredSignature
+            | ErrorKind::ImmatureSignature
+            | ErrorKind::InvalidAlgorithm
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_) => AuthError::InvalidToken,
+            _ => AuthError::TokenError,
+        }
     }
 }
 
 impl AuthService {
-    pub fn new(pool: SqlitePool, jwt_service: JwtService) -> Self {
-        Self { 
-            pool,
+    pub fn new(users: Arc<dyn UserRepository>, jwt_service: JwtService) -> Self {
+        Self {
+            users,
             jwt_service,
+            password_hasher: PasswordHasher::default(),
+            password_policy: PasswordPolicy::default(),
+            lowercase_email_local_part: true,
+            registration_notices: None,
         }
     }
 
-    #[instrument(skip(self, password))]
-    pub async fn login(&self, email: &str, password: &str) -> Result<TokenPair, AuthError> {
-        info!(email = %email, "Login attempt");
+    /// Make `register_or_notify` answer the same whether or not the email or
+    /// username is taken, and email the address's owner what happened instead.
+    pub fn with_registration_notices(mut self, email_service: EmailService, public_url: String) -> Self {
+        self.registration_notices = Some(RegistrationNotices { email_service, public_url });
+        self
+    }
+
+    /// How new passwords are hashed, and which stored hashes are upgraded at login.
+    pub fn with_password_hasher(mut self, password_hasher: PasswordHasher) -> Self {
+        self.password_hasher = password_hasher;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn password_hasher(&self) -> &PasswordHasher {
+        &self.password_hasher
+    }
+
+    /// Rules new passwords must meet, on registration and `set_password`.
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    /// Whether new addresses are stored with the part before `@` lowercased.
+    /// Domains always are, and lookups ignore case either way.
+    pub fn with_lowercase_email_local_part(mut self, enabled: bool) -> Self {
+        self.lowercase_email_local_part = enabled;
+        self
+    }
+
+    /// Sign in by email address or username, told apart by the `@`.
+    #[instrument(skip(self, login, password))]
+    pub async fn login(&self, login: &str, password: &str) -> LoginAttempt {
+        let attempt = self.authenticate(login, password).await;
+        metrics().record_login(attempt.result.is_ok());
+        attempt
+    }
+
+    async fn authenticate(&self, login: &str, password: &str) -> LoginAttempt {
+        info!("Login attempt");
         
-        // Find user by email
-        let user = match User::find_by_email(&self.pool, email).await {
+        // Find user by email or username
+        let user = if login.contains('@') {
+            self.users.find_by_email(&normalize_email(login, self.lowercase_email_local_part)).await
+        } else {
+            self.users.find_by_username(&normalize_username(login)).await
+        };
+        let user = match user {
             Ok(Some(user)) => user,
             Ok(None) => {
-                warn!(email = %email, "Login attempt with non-existent email");
-                return Err(AuthError::InvalidCredentials);
+                // Take as long as a wrong password would, so timing does not reveal the account is missing
+                let result = match self.password_hasher.verify_dummy(password).await {
+                    Ok(()) => {
+                        warn!("Login attempt with non-existent email or username");
+                        Err(AuthError::InvalidCredentials)
+                    }
+                    Err(e) => Err(e),
+                };
+                return LoginAttempt { user_id: None, result };
             }
             Err(e) => {
                 error!(error = %e, "Database error during login");
-                return Err(AuthError::DatabaseError(e));
+                return LoginAttempt { user_id: None, result: Err(AuthError::DatabaseError(e)) };
             }
         };
 
+        let result = self.authenticate_user(&user, password).await;
+        LoginAttempt { user_id: Some(user.id), result }
+    }
+
+    async fn authenticate_user(&self, user: &User, password: &str) -> Result<TokenPair, AuthError> {
         // Verify password
-        let password_matches = match verify(password, &user.password_hash) {
-            Ok(matches) => matches,
-            Err(e) => {
-                error!(error = %e, "Password verification error");
-                return Err(AuthError::PasswordHashError);
-            }
-        };
+        let password_matches = self.password_hasher.verify(password, &user.password_hash).await?;
 
         if !password_matches {
-            warn!(email = %email, "Failed login attempt - invalid password");
+            warn!(user_id = %user.id, "Failed login attempt - invalid password");
             return Err(AuthError::InvalidCredentials);
         }
 
+        // Only reveal the account state to someone who knows the password
+        if !user.is_active() {
+            warn!(user_id = %user.id, status = ?user.status, "Login blocked - account not active");
+            return Err(AuthError::AccountInactive(user.status));
+        }
+
+        if user.password_reset_required {
+            warn!(user_id = %user.id, "Login blocked - password reset required");
+            return Err(AuthError::PasswordResetRequired);
+        }
+
+        if self.password_hasher.needs_rehash(&user.password_hash) {
+            self.rehash_password(user, password).await;
+        }
+
         // Generate JWT tokens
         match self.jwt_service.create_tokens(user.id).await {
             Ok(token_pair) => {
-                info!(user_id = %user.id, email = %email, "User successfully logged in");
+                info!(user_id = %user.id, "User successfully logged in");
                 Ok(token_pair)
             }
             Err(e) => {
@@ -86,27 +226,90 @@ impl AuthService {
         }
     }
 
-    #[instrument(skip(self, password))]
+    #[instrument(skip(self, email, password))]
     pub async fn register(
         &self,
         username: &str,
         password: &str,
         email: &str,
     ) -> Result<i64, AuthError> {
-        info!(username = %username, email = %email, "New user registration attempt");
+        let (username, email) = self.normalize_registration(username, email)?;
+        self.password_policy.check(password).await?;
 
-        // Check if user already exists
-        if let Ok(Some(_)) = User::find_by_email(&self.pool, email).await {
-            warn!(email = %email, "Registration attempt with existing email");
-            return Err(AuthError::InvalidCredentials);
+        if self.find_existing(&username, &email).await?.is_some() {
+            warn!("Registration attempt with an existing email or username");
+            return Err(AuthError::AccountExists);
+        }
+
+        let password_hash = self.password_hasher.hash(password).await?;
+        self.create_user(&username, &password_hash, &email).await
+    }
+
+    /// Register as `register` does, or with registration notices enabled,
+    /// return `Registration::Notified` whatever the outcome and tell the
+    /// owner of the email address by email instead.
+    #[instrument(skip(self, email, password))]
+    pub async fn register_or_notify(
+        &self,
+        username: &str,
+        password: &str,
+        email: &str,
+    ) -> Result<Registration, AuthError> {
+        let Some(notices) = &self.registration_notices else {
+            return self.register(username, password, email).await.map(Registration::Created);
+        };
+        let (username, email) = self.normalize_registration(username, email)?;
+        // Refused whether or not the account exists, so this says nothing about it
+        self.password_policy.check(password).await?;
+
+        // Hash even for taken accounts, so they answer no faster
+        let password_hash = self.password_hasher.hash(password).await?;
+
+        let user_id = match self.find_existing(&username, &email).await? {
+            Some(Existing::Email(user)) => {
+                warn!(user_id = %user.id, "Registration attempt with existing email, notifying the owner");
+                notices.account_exists(&user).await;
+                None
+            }
+            Some(Existing::Username) => {
+                warn!("Registration attempt with existing username, notifying the registrant");
+                notices.username_taken(&email, &username).await;
+                None
+            }
+            None => {
+                let user_id = self.create_user(&username, &password_hash, &email).await?;
+                notices.welcome(&email, &username).await;
+                Some(user_id)
+            }
+        };
+        Ok(Registration::Notified { user_id })
+    }
+
+    fn normalize_registration(&self, username: &str, email: &str) -> Result<(String, String), AuthError> {
+        let username = normalize_username(username);
+        let email = normalize_email(email, self.lowercase_email_local_part);
+        info!(username = %username, "New user registration attempt");
+
+        if username.is_empty() || username.contains('@') {
+            warn!("Registration attempt with an invalid username");
+            return Err(AuthError::InvalidUsername);
         }
+        Ok((username, email))
+    }
 
-        match User::create(&self.pool, username, password, email).await {
+    async fn find_existing(&self, username: &str, email: &str) -> Result<Option<Existing>, AuthError> {
+        if let Some(user) = self.users.find_by_email(email).await? {
+            return Ok(Some(Existing::Email(user)));
+        }
+        Ok(self.users.find_by_username(username).await?.map(|_| Existing::Username))
+    }
+
+    async fn create_user(&self, username: &str, password_hash: &str, email: &str) -> Result<i64, AuthError> {
+        match self.users.create(username, password_hash, email).await {
             Ok(user) => {
                 info!(
                     user_id = %user.id,
                     username = %username,
-                    email = %email,
                     "New user successfully registered"
                 );
                 Ok(user.id)
@@ -118,4 +321,81 @@ impl AuthService {
         }
     }
 
-} 
\ No newline at end of file
+    /// Replace a user's password without a reset token, clearing any forced
+    /// reset and signing them out everywhere, as a completed reset does.
+    #[instrument(skip(self, password))]
+    pub async fn set_password(&self, user_id: i64, password: &str) -> Result<(), AuthError> {
+        let user = self.users.find_by_id(user_id).await?.ok_or(AuthError::UserNotFound)?;
+        self.password_policy.check(password).await?;
+
+        let password_hash = self.password_hasher.hash(password).await?;
+        self.users.update_password(user.id, &password_hash).await?;
+        self.jwt_service.revoke_all_sessions(user.id).await?;
+
+        info!(user_id = %user.id, "Password set");
+        Ok(())
+    }
+
+    /// Replace an outdated hash while the plaintext is at hand. Best effort:
+    /// the login goes ahead on the old hash if this fails, and a password
+    /// changed in the meantime is left alone.
+    async fn rehash_password(&self, user: &User, password: &str) {
+        let Ok(password_hash) = self.password_hasher.hash(password).await else {
+            return;
+        };
+        match self.users.replace_password_hash(user.id, &user.password_hash, &password_hash).await {
+            Ok(true) => info!(
+                user_id = %user.id,
+                algorithm = self.password_hasher.algorithm().name(),
+                "Password hash upgraded"
+            ),
+            Ok(false) => warn!(user_id = %user.id, "Password changed during login, hash not upgraded"),
+            Err(e) => error!(error = %e, "Failed to store upgraded password hash"),
+        }
+    }
+}
+
+/// Emails sent instead of registration errors, so the response never says
+/// whether an address or username is taken. Failures are only logged.
+#[derive(Clone)]
+struct RegistrationNotices {
+    email_service: EmailService,
+    public_url: String,
+}
+
+impl RegistrationNotices {
+    async fn account_exists(&self, user: &User) {
+        let body = format!(
+            "Someone tried to create an account with this email address, which already has one.\n\n\
+             If it was you, sign in as {} at {}, or request a sign-in link if you have forgotten \
+             your password. If it was not you, you can ignore this email.",
+            user.username, self.public_url
+        );
+        self.send(&user.email, "Sign-up attempt for your account", &body).await;
+    }
+
+    async fn username_taken(&self, email: &str, username: &str) {
+        let body = format!(
+            "The username {} is already taken, so no account was created for this email address. \
+             Register again at {} with another username.\n\n\
+             If you did not try to register, you can ignore this email.",
+            username, self.public_url
+        );
+        self.send(email, "Finish creating your account", &body).await;
+    }
+
+    async fn welcome(&self, email: &str, username: &str) {
+        let body = format!(
+            "Your account {} has been created. You can now sign in at {}.\n\n\
+             If you did not register, contact us so we can close the account.",
+            username, self.public_url
+        );
+        self.send(email, "Welcome", &body).await;
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: &str) {
+        if let Err(e) = self.email_service.send(to, subject, body).await {
+            error!(error = %e, "Failed to send registration email");
+        }
+    }
+}
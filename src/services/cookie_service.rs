@@ -1,32 +1,64 @@
-use axum::http::{HeaderMap, HeaderValue};
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderValue, request::Parts},
+};
 use time::{Duration, OffsetDateTime};
 use tower_cookies::Cookie;
 use cookie::SameSite;
 use tracing::debug;
 
+use crate::AppState;
+use crate::api::forwarded::Scheme;
+use crate::config::CookieSecure;
+
 pub const ACCESS_TOKEN_COOKIE: &str = "access_token";
 pub const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
-const SECURE: bool = true; // Set to true for HTTPS
+pub const MAGIC_LINK_BINDING_COOKIE: &str = "magic_link_binding";
 const HTTP_ONLY: bool = true;
 const SAME_SITE: SameSite = SameSite::Strict;
 
 
-#[derive(Clone)]
-pub struct CookieService;
+/// Builds auth cookies for the current request. Extract it in a handler to
+/// get the `Secure` attribute that matches how the request was served.
+#[derive(Clone, Copy)]
+pub struct CookieService {
+    secure: bool,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for CookieService {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let secure = match state.cookie_secure {
+            CookieSecure::Always => true,
+            CookieSecure::Never => false,
+            CookieSecure::Auto => Scheme::from_request_parts(parts, state).await?.is_https(),
+        };
+        Ok(Self::new(secure))
+    }
+}
 
 impl CookieService {
-    pub fn set_auth_cookies(access_token: &str, refresh_token: &str) -> HeaderMap {
+    pub fn new(secure: bool) -> Self {
+        Self { secure }
+    }
+
+    pub fn set_auth_cookies(&self, access_token: &str, refresh_token: &str) -> HeaderMap {
         let mut headers = HeaderMap::new();
         
         // Set access token cookie
-        let access_cookie = Self::create_cookie(
+        let access_cookie = self.create_cookie(
             ACCESS_TOKEN_COOKIE,
             access_token,
             Duration::minutes(15), // 15 minutes for access token
         );
         
         // Set refresh token cookie
-        let refresh_cookie = Self::create_cookie(
+        let refresh_cookie = self.create_cookie(
             REFRESH_TOKEN_COOKIE,
             refresh_token,
             Duration::days(7), // 7 days for refresh token
@@ -45,7 +77,7 @@ impl CookieService {
         headers
     }
 
-    pub fn clear_auth_cookies() -> HeaderMap {
+    pub fn clear_auth_cookies(&self) -> HeaderMap {
         debug!("Clearing auth cookies");
         let mut headers = HeaderMap::new();
         
@@ -71,19 +103,64 @@ impl CookieService {
         headers
     }
 
+    /// Replace only the access token, leaving the refresh token untouched.
+    pub fn set_access_cookie(&self, access_token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let access_cookie = self.create_cookie(ACCESS_TOKEN_COOKIE, access_token, Duration::minutes(15));
+        headers.append(
+            "Set-Cookie",
+            HeaderValue::from_str(&access_cookie.to_string()).unwrap(),
+        );
+
+        headers
+    }
+
+    pub fn clear_access_cookie(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let access_cookie = Self::create_removal_cookie(ACCESS_TOKEN_COOKIE);
+        headers.append(
+            "Set-Cookie",
+            HeaderValue::from_str(&access_cookie.to_string()).unwrap(),
+        );
+
+        headers
+    }
+
+    /// Bind a magic link to the browser that requested it. The cookie is `Lax`
+    /// because the link is opened via a cross-site navigation from the email client.
+    pub fn set_magic_link_binding_cookie(&self, nonce: &str, max_age: Duration) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let mut binding_cookie = self.create_cookie(MAGIC_LINK_BINDING_COOKIE, nonce, max_age);
+        binding_cookie.set_same_site(Some(SameSite::Lax));
+
+        headers.append(
+            "Set-Cookie",
+            HeaderValue::from_str(&binding_cookie.to_string()).unwrap(),
+        );
+
+        headers
+    }
+
+    pub fn clear_magic_link_binding_cookie(&self, headers: &mut HeaderMap) {
+        let binding_cookie = Self::create_removal_cookie(MAGIC_LINK_BINDING_COOKIE);
+        headers.append(
+            "Set-Cookie",
+            HeaderValue::from_str(&binding_cookie.to_string()).unwrap(),
+        );
+    }
+
     pub fn extract_token(headers: &HeaderMap, token_name: &str) -> Option<String> {
         debug!("Attempting to extract token: {}", token_name);
-        debug!("All headers: {:?}", headers);
-        
+
         headers
             .get_all("cookie")
             .iter()
             .find_map(|cookie_header| {
                 let cookie_str = match cookie_header.to_str() {
-                    Ok(s) => {
-                        debug!("Processing cookie header: {}", s);
-                        s
-                    },
+                    Ok(s) => s,
                     Err(e) => {
                         debug!("Failed to convert cookie header to string: {}", e);
                         return None;
@@ -93,10 +170,10 @@ impl CookieService {
                 // Split cookie string by semicolon and parse each cookie individually
                 for single_cookie_str in cookie_str.split(';') {
                     let trimmed_cookie_str = single_cookie_str.trim();
-                    debug!("Processing individual cookie: {}", trimmed_cookie_str);
-                    
+
+                    // Only ever log cookie names: the values are live credentials
                     if let Ok(cookie) = Cookie::parse(trimmed_cookie_str) {
-                        debug!("Successfully parsed cookie: {}={}", cookie.name(), cookie.value());
+                        debug!("Successfully parsed cookie: {}", cookie.name());
                         if cookie.name() == token_name {
                             return Some(cookie.value().to_string());
                         }
@@ -106,11 +183,11 @@ impl CookieService {
             })
     }
 
-    fn create_cookie(name: &str, value: &str, max_age: Duration) -> Cookie<'static> {
+    fn create_cookie(&self, name: &str, value: &str, max_age: Duration) -> Cookie<'static> {
         let expires = OffsetDateTime::now_utc() + max_age;
         
         let mut cookie = Cookie::new(name.to_owned(), value.to_owned());
-        cookie.set_secure(SECURE);
+        cookie.set_secure(self.secure);
         cookie.set_http_only(HTTP_ONLY);
         cookie.set_same_site(Some(SAME_SITE));
         cookie.set_path("/");
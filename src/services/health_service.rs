@@ -0,0 +1,146 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::db::{Database, RedisStore};
+
+/// Longest a single dependency check may take before it counts as down
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Every dependency is up
+    Ok,
+    /// An optional dependency is down; requests are still served
+    Degraded,
+    /// A required dependency is down
+    Unavailable,
+    /// The server is draining connections before exiting
+    ShuttingDown,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyCheck {
+    pub status: DependencyStatus,
+    pub required: bool,
+    pub latency_ms: f64,
+    /// `unavailable` or `timed out`; the underlying error is only logged, as
+    /// readiness is served without authentication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyChecks {
+    pub database: DependencyCheck,
+    pub redis: DependencyCheck,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    /// Not run once shutdown has started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<DependencyChecks>,
+}
+
+/// Checks the service's dependencies for readiness probes.
+#[derive(Clone)]
+pub struct HealthService {
+    db: Database,
+    redis_store: RedisStore,
+    redis_required: bool,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl HealthService {
+    pub fn new(db: Database, redis_store: RedisStore, redis_required: bool) -> Self {
+        Self {
+            db,
+            redis_store,
+            redis_required,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Report not-ready from now on, so load balancers stop routing new
+    /// requests here while in-flight ones drain.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait for `signal`, report not-ready, then keep serving for
+    /// `readiness_delay` so load balancers see it while new connections are
+    /// still accepted. Pass this as `serve`'s shutdown future.
+    pub async fn shutdown_after(&self, signal: impl Future<Output = ()>, readiness_delay: Duration) {
+        signal.await;
+        self.begin_shutdown();
+        info!(delay_secs = readiness_delay.as_secs(), "Readiness failing, waiting before closing the listener");
+        tokio::time::sleep(readiness_delay).await;
+    }
+
+    /// Check the database and Redis concurrently. The service is unavailable if the
+    /// database or a required Redis is down, and degraded if an optional Redis is.
+    pub async fn readiness(&self) -> ReadinessReport {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return ReadinessReport {
+                status: HealthStatus::ShuttingDown,
+                checks: None,
+            };
+        }
+
+        let (database, redis) = tokio::join!(
+            check(true, self.db.ping()),
+            check(self.redis_required, self.redis_store.ping()),
+        );
+
+        let status = match (database.status, redis.status) {
+            (DependencyStatus::Up, DependencyStatus::Up) => HealthStatus::Ok,
+            (DependencyStatus::Up, DependencyStatus::Down) if !redis.required => HealthStatus::Degraded,
+            _ => HealthStatus::Unavailable,
+        };
+
+        ReadinessReport {
+            status,
+            checks: Some(DependencyChecks { database, redis }),
+        }
+    }
+}
+
+async fn check<E: std::fmt::Display>(
+    required: bool,
+    probe: impl Future<Output = Result<(), E>>,
+) -> DependencyCheck {
+    let started = Instant::now();
+    let error = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => {
+            warn!(error = %e, required, "Health check failed");
+            Some("unavailable".to_string())
+        }
+        Err(_) => {
+            warn!(timeout_secs = CHECK_TIMEOUT.as_secs(), required, "Health check timed out");
+            Some("timed out".to_string())
+        }
+    };
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    DependencyCheck {
+        status: if error.is_none() { DependencyStatus::Up } else { DependencyStatus::Down },
+        required,
+        latency_ms,
+        error,
+    }
+}
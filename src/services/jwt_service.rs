@@ -1,28 +1,34 @@
 // src/services/jwt_service.rs
 use crate::db::RedisStore;
-use crate::models::jwt::{AccessClaims, RefreshClaims, TokenPair};
+use crate::metrics::metrics;
+use crate::models::jwt::{AccessClaims, MagicLinkClaims, RefreshClaims, TokenPair};
+use crate::repositories::UserRepository;
+use crate::services::auth_service::AuthError;
 
 use chrono::Utc;
 use jsonwebtoken::{
     decode, encode, errors::Error as JwtError, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header,
     Validation,
 };
-use tracing::{error, instrument};
+use std::sync::Arc;
+use tracing::{error, warn, instrument};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct JwtService {
+    users: Arc<dyn UserRepository>,
     redis_store: RedisStore,
     enc_key: EncodingKey,
     dec_key: DecodingKey,
 }
 
 impl JwtService {
-    pub fn new(redis_store: RedisStore, secret_key: String) -> Self {
+    pub fn new(users: Arc<dyn UserRepository>, redis_store: RedisStore, secret_key: String) -> Self {
         let enc_key = EncodingKey::from_secret(secret_key.as_bytes());
         let dec_key = DecodingKey::from_secret(secret_key.as_bytes());
 
         Self {
+            users,
             redis_store,
             enc_key,
             dec_key,
@@ -54,37 +60,137 @@ impl JwtService {
         Ok(TokenPair {
             access_token,
             refresh_token,
+            user_id,
         })
     }
 
+    /// Issue a lone access token for `user_id` carrying `actor_id` in its `act`
+    /// claim. No refresh token is issued, so impersonation ends when it expires.
+    #[instrument(skip(self))]
+    pub fn create_impersonation_token(&self, user_id: i64, actor_id: i64) -> Result<String, JwtError> {
+        self.create_jwt(&AccessClaims::impersonating(user_id, actor_id))
+    }
+
     /// Validate an access token and return its claims.
     /// Fails if expired or black-listed.
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     pub async fn verify_access_token(&self, token: &str) -> Result<AccessClaims, JwtError> {
         if self.redis_store.is_blacklisted(token).await.unwrap_or(false) {
             return Err(ErrorKind::ExpiredSignature.into());
         }
         let data = self.decode_jwt::<AccessClaims>(token)?;
+        if data.token_type != "access" {
+            return Err(ErrorKind::InvalidToken.into());
+        }
+
+        // reject tokens issued before the user's sessions were revoked, including
+        // in the same millisecond
+        if let Ok(Some(revoked_at_ms)) = self.redis_store.user_tokens_revoked_at_ms(data.sub).await
+            && data.iat_ms <= revoked_at_ms
+        {
+            return Err(ErrorKind::ExpiredSignature.into());
+        }
 
         Ok(data)
     }
 
+    /// Exchange a valid refresh token for a brand-new pair.
+    #[instrument(skip(self, refresh_token))]
+    pub async fn refresh_tokens(&self, refresh_token: &str) -> Result<TokenPair, AuthError> {
+        let result = self.rotate_refresh_token(refresh_token).await;
+        metrics().record_token_refresh(result.is_ok());
+        result
+    }
+
+    /// Revoke refresh token immediately, returning the id of its user.
+    pub async fn revoke_token(&self, refresh_token: &str) -> Result<i64, JwtError> {
+        let claims = self.decode_jwt::<RefreshClaims>(refresh_token)?;
+
+        let ttl = (claims.exp - Utc::now().timestamp()) as u64;
+        self.redis_store
+            .blacklist_token(refresh_token, ttl)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to blacklist token");
+                JwtError::from(ErrorKind::InvalidToken)
+            })?;
+
+        metrics().record_token_revocation("token");
+        Ok(claims.sub)
+    }
+
+    /// Revoke an access token for the rest of its lifetime, e.g. when an
+    /// impersonation ends.
+    pub async fn revoke_access_token(&self, access_token: &str) -> Result<(), JwtError> {
+        let claims = self.decode_jwt::<AccessClaims>(access_token)?;
+
+        let ttl = (claims.exp - Utc::now().timestamp()).max(1) as u64;
+        self.redis_store
+            .blacklist_token(access_token, ttl)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to blacklist access token");
+                JwtError::from(ErrorKind::InvalidToken)
+            })?;
+
+        metrics().record_token_revocation("access_token");
+        Ok(())
+    }
+
+    /// Revoke every session of `user_id`: all refresh tokens stop working
+    /// immediately, and access tokens issued up to now are rejected.
+    #[instrument(skip(self))]
+    pub async fn revoke_all_sessions(&self, user_id: i64) -> Result<usize, JwtError> {
+        let access_ttl = (AccessClaims::new(user_id).exp - Utc::now().timestamp()) as u64;
+        let revoked = self.redis_store
+            .revoke_user_sessions(user_id, Utc::now().timestamp_millis(), access_ttl)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to revoke user sessions");
+                JwtError::from(ErrorKind::InvalidToken)
+            })?;
+
+        metrics().record_token_revocation("all_sessions");
+        Ok(revoked)
+    }
+
+    /// Sign a single-use magic login link token bound to a browser nonce hash.
+    pub fn create_magic_link_token(&self, claims: &MagicLinkClaims) -> Result<String, JwtError> {
+        self.create_jwt(claims)
+    }
+
+    /// Validate a magic link token's signature, expiry and type.
+    /// Single-use enforcement is left to the caller.
+    pub fn verify_magic_link_token(&self, token: &str) -> Result<MagicLinkClaims, JwtError> {
+        let claims = self.decode_jwt::<MagicLinkClaims>(token)?;
+        if claims.token_type != "magic_link" {
+            return Err(ErrorKind::InvalidToken.into());
+        }
+
+        Ok(claims)
+    }
+
+    /* ---------- PRIVATE HELPERS ---------- */
+
     /// Exchange a valid refresh token for a brand-new pair.
     ///  1. Must still be allow-listed
     ///  2. Not black-listed / expired
-    ///  3. Old refresh token is revoked
-    #[instrument(skip(self))]
-    pub async fn refresh_tokens(&self, refresh_token: &str) -> Result<TokenPair, JwtError> {
+    ///  3. The account must still be active
+    ///  4. Old refresh token is revoked
+    async fn rotate_refresh_token(&self, refresh_token: &str) -> Result<TokenPair, AuthError> {
         if self
             .redis_store
             .is_blacklisted(refresh_token)
             .await
             .unwrap_or(false)
         {
-            return Err(ErrorKind::InvalidToken.into());
+            return Err(AuthError::InvalidToken);
         }
 
         let claims = self.decode_jwt::<RefreshClaims>(refresh_token)?;
+        if claims.token_type != "refresh" {
+            return Err(AuthError::InvalidToken);
+        }
 
         // ensure still allow-listed
         if !self
@@ -93,7 +199,18 @@ impl JwtService {
             .await
             .unwrap_or(false)
         {
-            return Err(ErrorKind::InvalidToken.into());
+            return Err(AuthError::InvalidToken);
+        }
+
+        // ensure the account may still sign in
+        let user = self
+            .users
+            .find_by_id(claims.sub)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+        if !user.is_active() {
+            warn!(user_id = %user.id, status = ?user.status, "Refresh refused - account not active");
+            return Err(AuthError::AccountInactive(user.status));
         }
 
         // everything checks out ⇒ revoke old refresh & build new pair
@@ -105,25 +222,9 @@ impl JwtService {
             .await
             .ok();
 
-        self.create_tokens(claims.sub).await
+        Ok(self.create_tokens(claims.sub).await?)
     }
 
-    /// Revoke refresh token immediately.
-    pub async fn revoke_token(&self, refresh_token: &str) -> Result<(), JwtError> {
-        let claims = self.decode_jwt::<RefreshClaims>(refresh_token)?;
-
-        let ttl = (claims.exp - Utc::now().timestamp()) as u64;
-        self.redis_store
-            .blacklist_token(refresh_token, ttl)
-            .await
-            .map_err(|e| {
-                error!(error = %e, "Failed to blacklist token");
-                JwtError::from(ErrorKind::InvalidToken)
-            })
-    }
-
-    /* ---------- PRIVATE HELPERS ---------- */
-
     fn create_jwt<T: serde::Serialize>(&self, claims: &T) -> Result<String, JwtError> {
         encode(&Header::default(), claims, &self.enc_key)
     }
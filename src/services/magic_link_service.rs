@@ -0,0 +1,157 @@
+use chrono::Duration;
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tracing::{info, warn, error, instrument};
+use uuid::Uuid;
+
+use crate::db::RedisStore;
+use crate::models::jwt::{MagicLinkClaims, TokenPair};
+use crate::repositories::UserRepository;
+use crate::services::auth_service::AuthError;
+use crate::services::email_service::EmailService;
+use crate::services::jwt_service::JwtService;
+
+const SINGLE_USE_KEY_PREFIX: &str = "magic_link:";
+const BINDING_NONCE_LEN: usize = 32;
+
+#[derive(Clone)]
+pub struct MagicLinkService {
+    users: Arc<dyn UserRepository>,
+    redis_store: RedisStore,
+    jwt_service: JwtService,
+    email_service: EmailService,
+    public_url: String,
+    ttl: Duration,
+}
+
+impl MagicLinkService {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        redis_store: RedisStore,
+        jwt_service: JwtService,
+        email_service: EmailService,
+        public_url: String,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            users,
+            redis_store,
+            jwt_service,
+            email_service,
+            public_url,
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Generate the nonce stored in the requesting browser's binding cookie.
+    pub fn new_binding_nonce() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(BINDING_NONCE_LEN)
+            .map(char::from)
+            .collect()
+    }
+
+    /// Email a single-use login link for `email`, bound to `binding_nonce`.
+    /// Returns `UserNotFound` for unknown addresses; callers must not reveal this.
+    #[instrument(skip(self, email, binding_nonce))]
+    pub async fn send_link(&self, email: &str, binding_nonce: &str) -> Result<(), AuthError> {
+        let user = match self.users.find_by_email(email.trim()).await? {
+            Some(user) => user,
+            None => {
+                warn!("Magic link requested for non-existent email");
+                return Err(AuthError::UserNotFound);
+            }
+        };
+
+        let jti = Uuid::new_v4().to_string();
+        let claims = MagicLinkClaims::new(user.id, jti.clone(), Self::hash_nonce(binding_nonce), self.ttl);
+        let token = self.jwt_service.create_magic_link_token(&claims)?;
+
+        self.redis_store
+            .store_single_use(
+                &format!("{}{}", SINGLE_USE_KEY_PREFIX, jti),
+                &user.id.to_string(),
+                self.ttl.num_seconds() as u64,
+            )
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to store magic link");
+                AuthError::TokenError
+            })?;
+
+        let link = format!("{}/login/magic-link/verify?token={}", self.public_url, token);
+        let body = format!(
+            "Click the link below to sign in. It expires in {} minutes and can only be used once, \
+             from the browser you requested it in.\n\n{}\n\n\
+             If you did not request this, you can ignore this email.",
+            self.ttl.num_minutes(),
+            link
+        );
+
+        if let Err(e) = self.email_service.send(&user.email, "Your sign-in link", &body).await {
+            error!(error = %e, user_id = %user.id, "Failed to send magic link email");
+            return Err(AuthError::TokenError);
+        }
+
+        info!(user_id = %user.id, "Magic link sent");
+        Ok(())
+    }
+
+    /// Consume a magic link and issue a token pair. The link must be presented
+    /// from the browser holding the matching binding nonce, and only once.
+    #[instrument(skip(self, token, binding_nonce))]
+    pub async fn verify_link(&self, token: &str, binding_nonce: Option<&str>) -> Result<TokenPair, AuthError> {
+        let claims = self.jwt_service.verify_magic_link_token(token).map_err(|e| {
+            warn!(error = %e, "Invalid magic link token");
+            AuthError::InvalidToken
+        })?;
+
+        // Check the binding before consuming, so an intercepted link opened
+        // elsewhere does not burn the legitimate user's link.
+        let binding_matches = binding_nonce.is_some_and(|nonce| {
+            bool::from(Self::hash_nonce(nonce).as_bytes().ct_eq(claims.bnd.as_bytes()))
+        });
+        if !binding_matches {
+            warn!(user_id = %claims.sub, "Magic link opened from a different browser");
+            return Err(AuthError::InvalidToken);
+        }
+
+        let stored_user_id = self
+            .redis_store
+            .consume_single_use(&format!("{}{}", SINGLE_USE_KEY_PREFIX, claims.jti))
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to consume magic link");
+                AuthError::TokenError
+            })?;
+        if stored_user_id != Some(claims.sub.to_string()) {
+            warn!(user_id = %claims.sub, "Magic link already used or expired");
+            return Err(AuthError::InvalidToken);
+        }
+
+        let user = self
+            .users
+            .find_by_id(claims.sub)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        if !user.is_active() {
+            warn!(user_id = %user.id, status = ?user.status, "Magic link refused - account not active");
+            return Err(AuthError::AccountInactive(user.status));
+        }
+
+        let token_pair = self.jwt_service.create_tokens(user.id).await?;
+        info!(user_id = %user.id, "User logged in via magic link");
+        Ok(token_pair)
+    }
+
+    fn hash_nonce(nonce: &str) -> String {
+        hex::encode(Sha256::digest(nonce.as_bytes()))
+    }
+}
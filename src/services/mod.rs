@@ -1,3 +1,12 @@
 pub mod auth_service;
+pub mod password_hasher;
+pub mod password_policy;
 pub mod jwt_service; 
-pub mod cookie_service;
\ No newline at end of file
+pub mod cookie_service;
+pub mod api_key_service;
+pub mod email_service;
+pub mod magic_link_service;
+pub mod password_reset_service;
+pub mod admin_service;
+pub mod audit_service;
+pub mod health_service;
\ No newline at end of file
@@ -0,0 +1,308 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use bcrypt::{DEFAULT_COST, HashParts};
+use rand::RngCore;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use tokio::sync::{OnceCell, Semaphore};
+use tracing::{error, warn};
+
+use crate::config::Config;
+use crate::metrics::metrics;
+use crate::services::auth_service::AuthError;
+
+/// How many hashes may wait for a free thread before new ones are refused.
+pub const DEFAULT_MAX_QUEUED: usize = 64;
+
+/// One hash per CPU: more would only make each of them slower.
+pub fn default_max_concurrent() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// The algorithm new password hashes are made with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordHashAlgorithm {
+    Argon2id,
+    Bcrypt,
+}
+
+impl PasswordHashAlgorithm {
+    /// Label for metrics and logs
+    pub fn name(self) -> &'static str {
+        match self {
+            PasswordHashAlgorithm::Argon2id => "argon2id",
+            PasswordHashAlgorithm::Bcrypt => "bcrypt",
+        }
+    }
+
+    /// The algorithm a stored hash was made with, if it is one we can verify.
+    pub fn of(hash: &str) -> Option<Self> {
+        if hash.starts_with("$argon2id$") {
+            Some(PasswordHashAlgorithm::Argon2id)
+        } else if hash.parse::<HashParts>().is_ok() {
+            Some(PasswordHashAlgorithm::Bcrypt)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::str::FromStr for PasswordHashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "argon2id" | "argon2" => Ok(PasswordHashAlgorithm::Argon2id),
+            "bcrypt" => Ok(PasswordHashAlgorithm::Bcrypt),
+            other => Err(format!("unknown password hash algorithm: {}", other)),
+        }
+    }
+}
+
+/// Hashes new passwords with the configured algorithm and parameters, and
+/// verifies hashes made with any supported one.
+///
+/// Argon2id hashes are stored as PHC strings
+/// (`$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`), bcrypt hashes in their
+/// usual `$2b$<cost>$...` form, so both carry the parameters they were made with.
+///
+/// The work runs on Tokio's blocking threads rather than the async workers,
+/// a bounded number at a time. Clones share those bounds.
+#[derive(Clone, Debug)]
+pub struct PasswordHasher {
+    algorithm: PasswordHashAlgorithm,
+    argon2: Params,
+    bcrypt_cost: u32,
+    pool: Arc<HashingPool>,
+    dummy_hash: Arc<OnceCell<String>>,
+}
+
+/// Caps the hashes running at once, which also bounds Argon2's memory use,
+/// and how many may wait for a turn.
+#[derive(Debug)]
+struct HashingPool {
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+    max_queued: usize,
+    running: AtomicUsize,
+}
+
+impl HashingPool {
+    fn new(max_concurrent: usize, max_queued: usize) -> Arc<Self> {
+        Arc::new(Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            queued: AtomicUsize::new(0),
+            max_queued,
+            running: AtomicUsize::new(0),
+        })
+    }
+
+    /// Take a place in the queue, or refuse if it is full.
+    fn enqueue(&self) -> Result<Queued<'_>, AuthError> {
+        self.queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| (queued < self.max_queued).then_some(queued + 1))
+            .map_err(|_| {
+                metrics().password_hash_rejected_total.inc();
+                warn!(max_queued = self.max_queued, "Password hashing queue is full");
+                AuthError::Overloaded
+            })?;
+        metrics().password_hash_queue_depth.inc();
+        Ok(Queued(self))
+    }
+}
+
+/// A place in the queue, given up when dropped, including when the request is cancelled.
+struct Queued<'a>(&'a HashingPool);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.queued.fetch_sub(1, Ordering::SeqCst);
+        metrics().password_hash_queue_depth.dec();
+    }
+}
+
+impl Default for PasswordHasher {
+    /// Argon2id with the OWASP-recommended 19 MiB, 2 iterations, 1 lane
+    fn default() -> Self {
+        Self {
+            algorithm: PasswordHashAlgorithm::Argon2id,
+            argon2: Params::DEFAULT,
+            bcrypt_cost: DEFAULT_COST,
+            pool: HashingPool::new(default_max_concurrent(), DEFAULT_MAX_QUEUED),
+            dummy_hash: Arc::default(),
+        }
+    }
+}
+
+impl PasswordHasher {
+    pub fn argon2id(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, argon2::Error> {
+        let argon2 = Params::new(memory_kib, iterations, parallelism, None)?;
+        Ok(Self { algorithm: PasswordHashAlgorithm::Argon2id, argon2, ..Self::default() })
+    }
+
+    pub fn bcrypt(cost: u32) -> Self {
+        Self { algorithm: PasswordHashAlgorithm::Bcrypt, bcrypt_cost: cost, ..Self::default() }
+    }
+
+    /// Run at most `max_concurrent` hashes at once, with up to `max_queued`
+    /// more waiting; beyond that, callers get `AuthError::Overloaded`.
+    pub fn with_limits(mut self, max_concurrent: usize, max_queued: usize) -> Self {
+        self.pool = HashingPool::new(max_concurrent, max_queued);
+        self
+    }
+
+    /// Falls back to the defaults, with a warning, when the Argon2 parameters are out of range.
+    pub fn from_config(config: &Config) -> Self {
+        let hasher = match config.password_hash_algorithm {
+            PasswordHashAlgorithm::Argon2id => {
+                Self::argon2id(config.argon2_memory_kib, config.argon2_iterations, config.argon2_parallelism)
+                    .unwrap_or_else(|e| {
+                        warn!(error = %e, "Invalid Argon2 parameters, using the defaults");
+                        Self::default()
+                    })
+            }
+            PasswordHashAlgorithm::Bcrypt => Self::bcrypt(config.bcrypt_cost.clamp(4, 31)),
+        };
+        hasher.with_limits(config.password_hash_max_concurrent, config.password_hash_max_queued)
+    }
+
+    pub fn algorithm(&self) -> PasswordHashAlgorithm {
+        self.algorithm
+    }
+
+    /// Hashes running right now on this hasher and its clones, unlike the
+    /// `auth_password_hash_in_flight` gauge, which counts every hasher.
+    #[cfg(test)]
+    pub fn in_flight(&self) -> usize {
+        self.pool.running.load(Ordering::SeqCst)
+    }
+
+    /// Hash a password for storage.
+    pub async fn hash(&self, password: &str) -> Result<String, AuthError> {
+        let hasher = self.clone();
+        let password = password.to_string();
+        self.run(move || hasher.hash_blocking(&password)).await?
+    }
+
+    /// Check a password against a stored hash of either algorithm, using the
+    /// parameters recorded in the hash rather than the configured ones.
+    pub async fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+        let hasher = self.clone();
+        let (password, hash) = (password.to_string(), hash.to_string());
+        self.run(move || {
+            let started = Instant::now();
+            let verified = hasher.verify_blocking(&password, &hash);
+            let algorithm = PasswordHashAlgorithm::of(&hash).map_or("unknown", PasswordHashAlgorithm::name);
+            metrics().observe_password_verify(algorithm, started);
+            verified
+        })
+        .await?
+    }
+
+    /// Verify against a throwaway hash made with the current settings, for
+    /// logins naming no account, so they take as long as a wrong password.
+    /// Only `Overloaded` is passed on, as it would be for a real account.
+    pub async fn verify_dummy(&self, password: &str) -> Result<(), AuthError> {
+        let dummy_hash = self.dummy_hash.get_or_try_init(|| self.hash("not a real password")).await;
+        match dummy_hash {
+            Ok(dummy_hash) => match self.verify(password, dummy_hash).await {
+                Err(AuthError::Overloaded) => Err(AuthError::Overloaded),
+                _ => Ok(()),
+            },
+            Err(AuthError::Overloaded) => Err(AuthError::Overloaded),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Run `job` on a blocking thread once a slot is free. The slot is held
+    /// until the job finishes, even if the caller stops waiting for it.
+    async fn run<T: Send + 'static>(&self, job: impl FnOnce() -> T + Send + 'static) -> Result<T, AuthError> {
+        let permit = match self.pool.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let _queued = self.pool.enqueue()?;
+                self.pool.permits.clone().acquire_owned().await.map_err(|_| AuthError::PasswordHashError)?
+            }
+        };
+
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            pool.running.fetch_add(1, Ordering::SeqCst);
+            metrics().password_hash_in_flight.inc();
+            let result = job();
+            metrics().password_hash_in_flight.dec();
+            pool.running.fetch_sub(1, Ordering::SeqCst);
+            result
+        })
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Password hashing task failed");
+            AuthError::PasswordHashError
+        })
+    }
+
+    fn hash_blocking(&self, password: &str) -> Result<String, AuthError> {
+        let hashed = match self.algorithm {
+            PasswordHashAlgorithm::Argon2id => {
+                let mut salt = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut salt);
+                SaltString::encode_b64(&salt)
+                    .and_then(|salt| Ok(self.argon2().hash_password(password.as_bytes(), &salt)?.to_string()))
+                    .map_err(|e| e.to_string())
+            }
+            PasswordHashAlgorithm::Bcrypt => {
+                bcrypt::hash(password.as_bytes(), self.bcrypt_cost).map_err(|e| e.to_string())
+            }
+        };
+        hashed.map_err(|e| {
+            error!(error = %e, "Failed to hash password");
+            AuthError::PasswordHashError
+        })
+    }
+
+    fn verify_blocking(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+        let verified = match PasswordHashAlgorithm::of(hash) {
+            Some(PasswordHashAlgorithm::Argon2id) => PasswordHash::new(hash)
+                .map_err(|e| e.to_string())
+                .and_then(|parsed| match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+                    Ok(()) => Ok(true),
+                    Err(argon2::password_hash::Error::Password) => Ok(false),
+                    Err(e) => Err(e.to_string()),
+                }),
+            Some(PasswordHashAlgorithm::Bcrypt) => bcrypt::verify(password, hash).map_err(|e| e.to_string()),
+            None => Err("unrecognised hash format".to_string()),
+        };
+        verified.map_err(|e| {
+            error!(error = %e, "Password verification error");
+            AuthError::PasswordHashError
+        })
+    }
+
+    /// Whether a stored hash was made with another algorithm or other
+    /// parameters than new ones would be, and should be replaced.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match self.algorithm {
+            PasswordHashAlgorithm::Argon2id => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let current = parsed.algorithm == Algorithm::Argon2id.ident()
+                    && parsed.version == Some(Version::V0x13.into())
+                    && Params::try_from(&parsed).is_ok_and(|params| {
+                        (params.m_cost(), params.t_cost(), params.p_cost())
+                            == (self.argon2.m_cost(), self.argon2.t_cost(), self.argon2.p_cost())
+                    });
+                !current
+            }
+            PasswordHashAlgorithm::Bcrypt => hash
+                .parse::<HashParts>()
+                .map_or(true, |parts| parts.get_cost() != self.bcrypt_cost),
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.argon2.clone())
+    }
+}
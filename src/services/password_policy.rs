@@ -0,0 +1,256 @@
+use async_trait::async_trait;
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::services::auth_service::AuthError;
+
+/// Hex digits of the SHA-1 sent to a range lookup; the rest never leaves the process.
+const PREFIX_LEN: usize = 5;
+
+/// A breached password hash sharing the looked-up prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeEntry {
+    /// The 35 hex digits after the prefix, upper case
+    pub suffix: String,
+    /// How many times the password was seen in breaches
+    pub count: u64,
+}
+
+#[derive(Debug)]
+pub enum BreachCheckError {
+    Io(std::io::Error),
+    Http(String),
+}
+
+impl std::fmt::Display for BreachCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreachCheckError::Io(e) => write!(f, "failed to read breached passwords: {}", e),
+            BreachCheckError::Http(e) => write!(f, "breached password lookup failed: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for BreachCheckError {
+    fn from(err: std::io::Error) -> Self {
+        BreachCheckError::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for BreachCheckError {
+    fn from(err: reqwest::Error) -> Self {
+        BreachCheckError::Http(err.to_string())
+    }
+}
+
+/// A k-anonymity range lookup, as served by Have I Been Pwned's Pwned
+/// Passwords: given the first five hex digits of a SHA-1, every breached hash
+/// starting with them.
+#[async_trait]
+pub trait BreachedPasswordClient: Send + Sync {
+    async fn range(&self, prefix: &str) -> Result<Vec<RangeEntry>, BreachCheckError>;
+}
+
+/// Queries a Pwned Passwords compatible API, such as
+/// `https://api.pwnedpasswords.com/range`.
+pub struct PwnedPasswordsApi {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl PwnedPasswordsApi {
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+            .timeout(Self::TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { client, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+#[async_trait]
+impl BreachedPasswordClient for PwnedPasswordsApi {
+    async fn range(&self, prefix: &str) -> Result<Vec<RangeEntry>, BreachCheckError> {
+        let body = self
+            .client
+            .get(format!("{}/{}", self.base_url, prefix))
+            // Pads every response to a similar size, with zero counts, so its
+            // length does not hint at the prefix either
+            .header("Add-Padding", "true")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(body.lines().filter_map(parse_entry).collect())
+    }
+}
+
+/// Looks ranges up in a local file of `HASH:COUNT` lines sorted by hash, the
+/// format of the Pwned Passwords "ordered by hash" download, so the check works
+/// without network access. Lines without a count count once.
+///
+/// Only the lines for the requested prefix are read, found by binary search,
+/// so the full corpus can be used without loading it into memory.
+pub struct PwnedPasswordsFile {
+    path: PathBuf,
+}
+
+impl PwnedPasswordsFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn range_blocking(path: &Path, prefix: &str) -> std::io::Result<Vec<RangeEntry>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let len = file.get_ref().metadata()?.len();
+
+        // The first offset whose next line is at or past the prefix
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match next_line(&mut file, mid)? {
+                Some(line) if hash_prefix(&line) < prefix => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+
+        let mut entries = Vec::new();
+        let mut line = next_line(&mut file, low)?;
+        while let Some(current) = line.filter(|line| hash_prefix(line) == prefix) {
+            entries.extend(current.get(PREFIX_LEN..).and_then(parse_entry));
+            line = read_line(&mut file)?;
+        }
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl BreachedPasswordClient for PwnedPasswordsFile {
+    async fn range(&self, prefix: &str) -> Result<Vec<RangeEntry>, BreachCheckError> {
+        let (path, prefix) = (self.path.clone(), prefix.to_ascii_uppercase());
+        tokio::task::spawn_blocking(move || Self::range_blocking(&path, &prefix))
+            .await
+            .map_err(std::io::Error::other)?
+            .map_err(BreachCheckError::from)
+    }
+}
+
+/// The first line starting at or after `offset`, skipping the rest of the line `offset` falls in.
+fn next_line(file: &mut BufReader<File>, offset: u64) -> std::io::Result<Option<String>> {
+    if offset == 0 {
+        file.seek(SeekFrom::Start(0))?;
+    } else {
+        file.seek(SeekFrom::Start(offset - 1))?;
+        file.read_until(b'\n', &mut Vec::new())?;
+    }
+    read_line(file)
+}
+
+/// The next non-blank line, without its line ending.
+fn read_line(file: &mut BufReader<File>) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if file.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            return Ok(Some(trimmed.to_ascii_uppercase()));
+        }
+    }
+}
+
+fn hash_prefix(line: &str) -> &str {
+    line.get(..PREFIX_LEN).unwrap_or(line)
+}
+
+/// A `SUFFIX:COUNT` line of a range response; a bare suffix counts once.
+fn parse_entry(line: &str) -> Option<RangeEntry> {
+    let (suffix, count) = line.trim().split_once(':').unwrap_or((line.trim(), "1"));
+    Some(RangeEntry { suffix: suffix.to_ascii_uppercase(), count: count.trim().parse().ok()? })
+}
+
+/// Rules new passwords must meet on registration, password changes and resets.
+///
+/// With a breach check configured, passwords seen in at least `min_breach_count`
+/// breaches are refused. Only the first five hex digits of the password's SHA-1
+/// are looked up. A lookup that fails lets the password through, with a warning,
+/// so an unreachable API does not stop sign-ups.
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    breached_passwords: Option<Arc<dyn BreachedPasswordClient>>,
+    min_breach_count: u64,
+}
+
+impl Default for PasswordPolicy {
+    /// Accepts any password
+    fn default() -> Self {
+        Self { breached_passwords: None, min_breach_count: 1 }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn with_breach_check(mut self, client: Arc<dyn BreachedPasswordClient>) -> Self {
+        self.breached_passwords = Some(client);
+        self
+    }
+
+    /// Refuse only passwords seen at least this many times.
+    pub fn with_min_breach_count(mut self, min_breach_count: u64) -> Self {
+        self.min_breach_count = min_breach_count.max(1);
+        self
+    }
+
+    /// Checks against `BREACHED_PASSWORDS_FILE` when set, otherwise
+    /// `BREACHED_PASSWORDS_API_URL`; without either, accepts any password.
+    pub fn from_config(config: &Config) -> Self {
+        let policy = Self::default().with_min_breach_count(config.breached_password_min_count);
+        if let Some(path) = &config.breached_passwords_file {
+            if !path.is_file() {
+                warn!(path = %path.display(), "Breached passwords file not found, lookups will fail until it exists");
+            }
+            info!(path = %path.display(), "Checking new passwords against a local breach corpus");
+            policy.with_breach_check(Arc::new(PwnedPasswordsFile::new(path)))
+        } else if let Some(url) = &config.breached_passwords_api_url {
+            info!(url = %url, "Checking new passwords against a breached passwords API");
+            policy.with_breach_check(Arc::new(PwnedPasswordsApi::new(url)))
+        } else {
+            policy
+        }
+    }
+
+    /// Refuse `password` with `AuthError::BreachedPassword` if it breaks the policy.
+    pub async fn check(&self, password: &str) -> Result<(), AuthError> {
+        let Some(client) = &self.breached_passwords else {
+            return Ok(());
+        };
+
+        let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        match client.range(prefix).await {
+            Ok(entries) => {
+                let count = entries.iter().find(|entry| entry.suffix == suffix).map_or(0, |entry| entry.count);
+                if count >= self.min_breach_count {
+                    warn!(count, "Refused a password found in known breaches");
+                    return Err(AuthError::BreachedPassword);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                warn!(error = %e, "Breached password check failed, accepting the password");
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::Duration;
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{info, warn, error, instrument};
+
+use crate::db::RedisStore;
+use crate::models::user::User;
+use crate::repositories::UserRepository;
+use crate::services::auth_service::AuthError;
+use crate::services::email_service::EmailService;
+use crate::services::jwt_service::JwtService;
+use crate::services::password_hasher::PasswordHasher;
+use crate::services::password_policy::PasswordPolicy;
+
+const SINGLE_USE_KEY_PREFIX: &str = "password_reset:";
+const TOKEN_LEN: usize = 48;
+
+#[derive(Clone)]
+pub struct PasswordResetService {
+    users: Arc<dyn UserRepository>,
+    redis_store: RedisStore,
+    jwt_service: JwtService,
+    email_service: EmailService,
+    password_hasher: PasswordHasher,
+    password_policy: PasswordPolicy,
+    reset_url: String,
+    ttl: Duration,
+}
+
+impl PasswordResetService {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        redis_store: RedisStore,
+        jwt_service: JwtService,
+        email_service: EmailService,
+        reset_url: String,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            users,
+            redis_store,
+            jwt_service,
+            email_service,
+            password_hasher: PasswordHasher::default(),
+            password_policy: PasswordPolicy::default(),
+            reset_url,
+            ttl,
+        }
+    }
+
+    pub fn with_password_hasher(mut self, password_hasher: PasswordHasher) -> Self {
+        self.password_hasher = password_hasher;
+        self
+    }
+
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    /// Email `user` a single-use link to choose a new password.
+    #[instrument(skip(self, user), fields(user_id = %user.id))]
+    pub async fn send_reset_link(&self, user: &User) -> Result<(), AuthError> {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .map(char::from)
+            .collect();
+
+        // Only a hash of the token is kept server-side
+        self.redis_store
+            .store_single_use(&Self::key_for(&token), &user.id.to_string(), self.ttl.num_seconds() as u64)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to store password reset token");
+                AuthError::TokenError
+            })?;
+
+        let separator = if self.reset_url.contains('?') { '&' } else { '?' };
+        let link = format!("{}{}token={}", self.reset_url, separator, token);
+        let body = format!(
+            "A password reset is required for your account. Use the link below to choose a new \
+             password. It expires in {} minutes and can only be used once.\n\n{}",
+            self.ttl.num_minutes(),
+            link
+        );
+
+        if let Err(e) = self.email_service.send(&user.email, "Reset your password", &body).await {
+            error!(error = %e, "Failed to send password reset email");
+            return Err(AuthError::TokenError);
+        }
+
+        info!("Password reset link sent");
+        Ok(())
+    }
+
+    /// The user a reset token belongs to, without consuming it, so a reset
+    /// page can tell an expired link apart before asking for a password.
+    pub async fn check_token(&self, token: &str) -> Result<i64, AuthError> {
+        let user_id = self
+            .redis_store
+            .peek_single_use(&Self::key_for(token))
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to look up password reset token");
+                AuthError::TokenError
+            })?;
+        Self::parse_user_id(user_id)
+    }
+
+    /// Consume a reset token, set the new password and sign the user out everywhere.
+    #[instrument(skip(self, token, new_password))]
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<i64, AuthError> {
+        // The token is checked first, so without a valid link the endpoint says
+        // nothing about which passwords are breached. It is only consumed once
+        // the password is accepted and hashed, so a refused password or a busy
+        // hasher does not use up the link.
+        let user_id = self.check_token(token).await?;
+        self.password_policy.check(new_password).await?;
+
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        let password_hash = self.password_hasher.hash(new_password).await?;
+
+        // Of concurrent resets with the same link, only one consumes it
+        let consumed = self
+            .redis_store
+            .consume_single_use(&Self::key_for(token))
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to consume password reset token");
+                AuthError::TokenError
+            })?;
+        if Self::parse_user_id(consumed)? != user.id {
+            return Err(AuthError::InvalidToken);
+        }
+
+        self.users.update_password(user.id, &password_hash).await?;
+        self.jwt_service.revoke_all_sessions(user.id).await?;
+
+        info!(user_id = %user.id, "Password reset completed");
+        Ok(user.id)
+    }
+
+    fn parse_user_id(user_id: Option<String>) -> Result<i64, AuthError> {
+        user_id.and_then(|user_id| user_id.parse::<i64>().ok()).ok_or_else(|| {
+            warn!("Invalid or already used password reset token");
+            AuthError::InvalidToken
+        })
+    }
+
+    fn key_for(token: &str) -> String {
+        format!("{}{}", SINGLE_USE_KEY_PREFIX, hex::encode(Sha256::digest(token.as_bytes())))
+    }
+}
@@ -0,0 +1,113 @@
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource, runtime,
+    trace::{Sampler, TracerProvider},
+};
+use tracing::{Subscriber, error};
+use tracing_subscriber::{
+    EnvFilter, Layer,
+    fmt::MakeWriter,
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+};
+
+use crate::config::{Config, LogFormat};
+
+pub mod redact;
+
+use redact::{RedactedFields, RedactedJson};
+
+/// Keeps the trace exporter alive; dropping it flushes any buffered spans.
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+/// Install the global subscriber: redacted logs in the configured format,
+/// filtered by `RUST_LOG` or `config.log_level`, plus OTLP trace export when
+/// `config.otlp_endpoint` is set.
+pub fn init(config: &Config) -> Telemetry {
+    let provider = config.otlp_endpoint.as_deref().and_then(|endpoint| {
+        tracer_provider(endpoint, config.trace_sample_ratio, &config.service_name)
+            .inspect_err(|e| eprintln!("Failed to configure OTLP exporter, traces will not be exported: {}", e))
+            .ok()
+    });
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME"))));
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::try_new(&config.log_level).unwrap_or_else(|e| {
+            eprintln!("Invalid LOG_LEVEL {:?}, falling back to info: {}", config.log_level, e);
+            EnvFilter::new("info")
+        })
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(log_layer(config.log_format, std::io::stdout))
+        .with(otel_layer)
+        .init();
+
+    if let Some(endpoint) = &config.otlp_endpoint
+        && provider.is_none()
+    {
+        error!(endpoint = %endpoint, "OTLP trace export disabled");
+    }
+
+    Telemetry { provider }
+}
+
+/// Log formatter writing to `writer`, with sensitive fields and values masked
+/// in both formats.
+pub fn log_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .fmt_fields(RedactedFields::pretty())
+            .with_target(false)
+            .with_thread_ids(true)
+            .with_level(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_names(true)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .fmt_fields(RedactedFields::json())
+            .event_format(RedactedJson)
+            .boxed(),
+    }
+}
+
+/// Build a tracer provider that batches spans to an OTLP/HTTP collector at
+/// `endpoint`. Incoming sampling decisions are honoured; new traces are kept
+/// with probability `sample_ratio`.
+pub fn tracer_provider(endpoint: &str, sample_ratio: f64, service_name: &str) -> Result<TracerProvider, TraceError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sample_ratio))))
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name.to_string())]))
+        .build())
+}
@@ -0,0 +1,221 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use serde_json::{Map, Value};
+use tracing::{Event, Subscriber, field::{Field, Visit}, span};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields, format::Writer},
+    registry::LookupSpan,
+};
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Field name segments whose values are never logged, e.g. `email`,
+/// `refresh_token` or `http.authorization`.
+const SENSITIVE_SEGMENTS: &[&str] = &["password", "token", "secret", "cookie", "authorization", "email", "jwt"];
+
+/// Whole field names that are sensitive but too generic to match as segments
+const SENSITIVE_NAMES: &[&str] = &["key", "api_key", "headers", "nonce", "binding_nonce"];
+
+/// Patterns masked inside any logged string, including messages, paired
+/// with their replacement.
+static SCRUBBERS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    vec![
+        // Auth cookies in Cookie / Set-Cookie values
+        (
+            Regex::new(r"(access_token|refresh_token|magic_link_binding)=[^;,\s]+").unwrap(),
+            "$1=[REDACTED]",
+        ),
+        // One-time tokens in links, e.g. `/password-reset?token=...`
+        (Regex::new(r"([?&](?:token|code))=[^&\s]+").unwrap(), "$1=[REDACTED]"),
+        (Regex::new(r"(?i)\b(bearer|basic)\s+[A-Za-z0-9._~+/=-]+").unwrap(), "$1 [REDACTED]"),
+        // JWTs always start with a base64url-encoded `{"`
+        (Regex::new(r"eyJ[A-Za-z0-9_-]*\.[A-Za-z0-9_-]*\.[A-Za-z0-9_-]*").unwrap(), REDACTED),
+        // API keys keep their public prefix so they can still be identified
+        (Regex::new(r"\b(ak_[A-Za-z0-9]{8})_[A-Za-z0-9]{32}\b").unwrap(), "${1}_[REDACTED]"),
+        (Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(), REDACTED),
+    ]
+});
+
+pub fn is_sensitive_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_NAMES.contains(&name.as_str())
+        || name
+            .split(['_', '.', '-'])
+            .any(|segment| SENSITIVE_SEGMENTS.contains(&segment))
+}
+
+/// Mask credentials and email addresses appearing anywhere in `value`.
+pub fn scrub(value: &str) -> Cow<'_, str> {
+    let mut scrubbed = Cow::Borrowed(value);
+    for (pattern, replacement) in SCRUBBERS.iter() {
+        if pattern.is_match(&scrubbed) {
+            scrubbed = Cow::Owned(pattern.replace_all(&scrubbed, *replacement).into_owned());
+        }
+    }
+    scrubbed
+}
+
+/// Collects an event's or span's fields with sensitive values masked.
+#[derive(Default)]
+struct RedactingVisitor {
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl RedactingVisitor {
+    fn push(&mut self, field: &Field, value: Value) {
+        let value = if is_sensitive_field(field.name()) {
+            Value::String(REDACTED.to_string())
+        } else {
+            match value {
+                Value::String(s) => Value::String(scrub(&s).into_owned()),
+                other => other,
+            }
+        };
+        self.fields.push((field.name(), value));
+    }
+
+    fn into_map(self) -> Map<String, Value> {
+        self.fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+}
+
+impl Visit for RedactingVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.push(field, Value::String(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, Value::String(format!("{:?}", value)));
+    }
+}
+
+/// Field formatter that masks sensitive values, writing either `key=value`
+/// pairs or a JSON object.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RedactedFields {
+    json: bool,
+}
+
+impl RedactedFields {
+    pub fn pretty() -> Self {
+        Self { json: false }
+    }
+
+    pub fn json() -> Self {
+        Self { json: true }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactedFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = RedactingVisitor::default();
+        fields.record(&mut visitor);
+
+        if self.json {
+            return write!(writer, "{}", Value::Object(visitor.into_map()));
+        }
+
+        let mut separator = "";
+        for (name, value) in visitor.fields {
+            match (name, value) {
+                ("message", Value::String(message)) => write!(writer, "{}{}", separator, message)?,
+                (name, Value::String(value)) => write!(writer, "{}{}={}", separator, name, value)?,
+                (name, value) => write!(writer, "{}{}={}", separator, name, value)?,
+            }
+            separator = " ";
+        }
+        Ok(())
+    }
+
+    fn add_fields(&self, current: &'writer mut FormattedFields<Self>, fields: &span::Record<'_>) -> fmt::Result {
+        if !self.json {
+            if !current.fields.is_empty() {
+                current.fields.push(' ');
+            }
+            return self.format_fields(current.as_writer(), fields);
+        }
+
+        // Merge into the existing JSON object rather than appending a second one
+        let mut visitor = RedactingVisitor::default();
+        fields.record(&mut visitor);
+        let mut merged: Map<String, Value> = serde_json::from_str(&current.fields).unwrap_or_default();
+        merged.extend(visitor.into_map());
+        current.fields = Value::Object(merged).to_string();
+        Ok(())
+    }
+}
+
+/// Writes each event as one JSON object with its redacted fields and the
+/// fields of every enclosing span. The innermost `request_id` is lifted to
+/// the top level so lines can be grouped by request.
+pub struct RedactedJson;
+
+impl<S, N> FormatEvent<S, N> for RedactedJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut visitor = RedactingVisitor::default();
+        event.record(&mut visitor);
+
+        let mut line = Map::new();
+        line.insert("timestamp".into(), Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true).into());
+        line.insert("level".into(), metadata.level().as_str().into());
+        line.insert("target".into(), metadata.target().into());
+
+        let mut spans = Vec::new();
+        let mut request_id = None;
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let mut entry = Map::new();
+                entry.insert("name".into(), span.name().into());
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>()
+                    && let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(&fields.fields)
+                {
+                    if let Some(id) = fields.get("request_id") {
+                        request_id = Some(id.clone());
+                    }
+                    entry.extend(fields);
+                }
+                spans.push(Value::Object(entry));
+            }
+        }
+        if let Some(request_id) = request_id {
+            line.insert("request_id".into(), request_id);
+        }
+        line.insert("fields".into(), Value::Object(visitor.into_map()));
+        if !spans.is_empty() {
+            line.insert("spans".into(), Value::Array(spans));
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
@@ -0,0 +1,95 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use super::helpers::{setup_test_db, create_test_app, test_request, register_and_login, users};
+use crate::models::user::{Role, UserStatus};
+use crate::repositories::UserRepository;
+use crate::services::cookie_service::{ACCESS_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE};
+
+#[tokio::test]
+async fn test_inactive_account_is_refused_at_login() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    for (status, code) in [
+        (UserStatus::Locked, "account_locked"),
+        (UserStatus::Disabled, "account_disabled"),
+        (UserStatus::PendingVerification, "account_pending_verification"),
+    ] {
+        users(&pool).set_status(alice.id, status, Some("test")).await.unwrap();
+        let (status, body, _) = test_request(app.clone(), "POST", "/login", Some(login.clone()), None, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"], code);
+    }
+
+    // A wrong password still looks like any other failed login
+    let wrong = json!({ "email": "alice@example.com", "password": "wrongpassword" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/login", Some(wrong), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    users(&pool).set_status(alice.id, UserStatus::Active, None).await.unwrap();
+    let (status, _, _) = test_request(app, "POST", "/login", Some(login), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_existing_tokens_stop_working_for_inactive_account() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+    let (access_token, refresh_token) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+
+    // Change the status directly so no sessions are revoked
+    users(&pool).set_status(alice.id, UserStatus::Locked, Some("too many attempts")).await.unwrap();
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str()), (REFRESH_TOKEN_COOKIE, refresh_token.as_str())];
+    let (status, body, _) = test_request(app.clone(), "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["error"], "account_locked");
+
+    let (status, body, _) = test_request(app, "POST", "/refresh", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["error"], "account_locked");
+}
+
+#[tokio::test]
+async fn test_admin_sets_status_with_reason() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+    let (admin_token, _) = register_and_login(&app, "admin", "admin@example.com", "adminpass").await;
+    let admin = users(&pool).find_by_email("admin@example.com").await.unwrap().unwrap();
+    users(&pool).set_role(admin.id, Role::Admin).await.unwrap();
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, admin_token.as_str())];
+    let (status, body, _) = test_request(
+        app.clone(),
+        "PUT",
+        &format!("/admin/users/{}/status", alice.id),
+        Some(json!({ "status": "locked", "reason": "suspicious activity" })),
+        None,
+        Some(&cookies),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    let user: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(user["status"], "locked");
+    assert_eq!(user["status_reason"], "suspicious activity");
+    assert!(user["status_changed_at"].is_string());
+
+    let (status, body, _) = test_request(
+        app,
+        "POST",
+        &format!("/admin/users/{}/disable", alice.id),
+        Some(json!({ "reason": "account closed" })),
+        None,
+        Some(&cookies),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    let user: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(user["status"], "disabled");
+    assert_eq!(user["status_reason"], "account closed");
+}
@@ -0,0 +1,280 @@
+use axum::{Router, http::StatusCode};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use super::helpers::{setup_test_db, setup_test_redis, create_test_app_with_email, test_request, register_and_login, extract_response_cookie, users};
+use crate::config::Config;
+use crate::models::user::Role;
+use crate::repositories::UserRepository;
+use crate::services::cookie_service::{ACCESS_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE};
+
+/// Register an admin and return their access token
+async fn login_admin(app: &Router, pool: &SqlitePool) -> String {
+    let (access_token, _) = register_and_login(app, "admin", "admin@example.com", "adminpass").await;
+    let admin = users(pool).find_by_email("admin@example.com").await.unwrap().unwrap();
+    users(pool).set_role(admin.id, Role::Admin).await.unwrap();
+    access_token
+}
+
+async fn user_id(pool: &SqlitePool, email: &str) -> i64 {
+    users(pool).find_by_email(email).await.unwrap().unwrap().id
+}
+
+async fn admin_request(app: &Router, token: &str, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, token)];
+    let (status, body, _) = test_request(app.clone(), method, uri, body, None, Some(&cookies)).await;
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_admin_routes_require_admin_role() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool);
+
+    let (status, _, _) = test_request(app.clone(), "GET", "/admin/users", None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (access_token, _) = register_and_login(&app, "testuser", "test@example.com", "password123").await;
+    let (status, _) = admin_request(&app, &access_token, "GET", "/admin/users", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_admin_lists_users_with_pagination_and_filters() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    register_and_login(&app, "bob", "bob@example.com", "password123").await;
+
+    let (status, page) = admin_request(&app, &admin_token, "GET", "/admin/users?per_page=2&page=2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["users"].as_array().unwrap().len(), 1);
+
+    let (_, page) = admin_request(&app, &admin_token, "GET", "/admin/users?search=ali", None).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["users"][0]["username"], "alice");
+    assert!(page["users"][0].get("password_hash").is_none());
+
+    let (_, page) = admin_request(&app, &admin_token, "GET", "/admin/users?role=admin", None).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["users"][0]["role"], "admin");
+
+    let bob_id = user_id(&pool, "bob@example.com").await;
+    let (status, user) = admin_request(&app, &admin_token, "GET", &format!("/admin/users/{}", bob_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["email"], "bob@example.com");
+
+    let (status, _) = admin_request(&app, &admin_token, "GET", "/admin/users/9999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_admin_search_matches_wildcards_literally() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    for (username, email) in [("a_b", "a_b@example.com"), ("axb", "axb@example.com"), ("c\\d", "cd@example.com")] {
+        users(&pool).create(username, "hash", email).await.unwrap();
+    }
+
+    let (_, page) = admin_request(&app, &admin_token, "GET", "/admin/users?search=a_b", None).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["users"][0]["username"], "a_b");
+
+    let (_, page) = admin_request(&app, &admin_token, "GET", "/admin/users?search=%25", None).await;
+    assert_eq!(page["total"], 0);
+    assert_eq!(page["users"].as_array().unwrap().len(), 0);
+
+    let (_, page) = admin_request(&app, &admin_token, "GET", "/admin/users?search=c%5Cd", None).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["users"][0]["username"], "c\\d");
+}
+
+#[tokio::test]
+async fn test_admin_disable_enable_and_role_assignment() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    let admin_id = user_id(&pool, "admin@example.com").await;
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice_id = user_id(&pool, "alice@example.com").await;
+
+    let (status, user) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/disable", alice_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["status"], "disabled");
+
+    let (status, user) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/enable", alice_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["status"], "active");
+
+    let (status, user) = admin_request(
+        &app,
+        &admin_token,
+        "PUT",
+        &format!("/admin/users/{}/role", alice_id),
+        Some(json!({ "role": "admin" })),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(user["role"], "admin");
+
+    // Admins cannot lock themselves out
+    let (status, _) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/disable", admin_id), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = admin_request(
+        &app,
+        &admin_token,
+        "PUT",
+        &format!("/admin/users/{}/role", admin_id),
+        Some(json!({ "role": "user" })),
+    ).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_admin_revoke_all_sessions() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    let (access_token, refresh_token) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice_id = user_id(&pool, "alice@example.com").await;
+
+    let (status, response) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/revoke-sessions", alice_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["revoked"], 1);
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str()), (REFRESH_TOKEN_COOKIE, refresh_token.as_str())];
+    let (status, _, _) = test_request(app.clone(), "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = test_request(app.clone(), "POST", "/refresh", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Signing in again right away is unaffected
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    let (status, _, headers) = test_request(app.clone(), "POST", "/login", Some(login), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = extract_response_cookie(&headers, ACCESS_TOKEN_COOKIE).unwrap();
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+    let (status, _, _) = test_request(app, "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_force_password_reset() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice_id = user_id(&pool, "alice@example.com").await;
+
+    let (status, _) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/force-password-reset", alice_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/login", Some(login), None, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let email = email_service.sent_emails().pop().unwrap();
+    assert_eq!(email.to, "alice@example.com");
+    let token = email.body.split("token=").nth(1).unwrap().trim().to_string();
+
+    let reset = json!({ "token": token, "new_password": "newpassword456" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset.clone()), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "reset link is single-use");
+
+    let login = json!({ "email": "alice@example.com", "password": "newpassword456" });
+    let (status, _, _) = test_request(app, "POST", "/login", Some(login), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_password_reset_link_opens() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice_id = user_id(&pool, "alice@example.com").await;
+    admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/force-password-reset", alice_id), None).await;
+
+    // The emailed link itself is routed, and only checks the token
+    let body = email_service.sent_emails().pop().unwrap().body;
+    let link = body.lines().last().unwrap().strip_prefix(&Config::from_env().public_url).unwrap().to_string();
+    assert!(link.starts_with("/password-reset?token="), "{}", link);
+    for _ in 0..2 {
+        let (status, _, _) = test_request(app.clone(), "GET", &link, None, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let token = link.split("token=").nth(1).unwrap();
+    let reset = json!({ "token": token, "new_password": "newpassword456" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = test_request(app, "GET", &link, None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Or it points at a reset page elsewhere
+    let config = Config { password_reset_url: Some("https://app.example.com/reset?from=email".to_string()), ..Config::from_env() };
+    let state = crate::build_state(pool.clone(), setup_test_redis(), config, email_service.clone());
+    let alice = users(&pool).find_by_id(alice_id).await.unwrap().unwrap();
+    state.password_reset_service.send_reset_link(&alice).await.unwrap();
+    let body = email_service.sent_emails().pop().unwrap().body;
+    assert!(body.contains("\n\nhttps://app.example.com/reset?from=email&token="), "{}", body);
+}
+
+#[tokio::test]
+async fn test_admin_impersonation() {
+    let pool = setup_test_db().await;
+    let (app, _) = create_test_app_with_email(pool.clone());
+    let admin_token = login_admin(&app, &pool).await;
+    let admin_id = user_id(&pool, "admin@example.com").await;
+    register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice_id = user_id(&pool, "alice@example.com").await;
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, admin_token.as_str())];
+    let (status, _, headers) = test_request(
+        app.clone(), "POST", &format!("/admin/users/{}/impersonate", alice_id), None, None, Some(&cookies),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(extract_response_cookie(&headers, REFRESH_TOKEN_COOKIE).is_none(), "admin keeps their refresh token");
+    let impersonation_token = extract_response_cookie(&headers, ACCESS_TOKEN_COOKIE).unwrap();
+
+    // /me shows the customer, with the acting admin visible
+    let (status, me) = admin_request(&app, &impersonation_token, "GET", "/me", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["id"], alice_id);
+    assert_eq!(me["impersonated_by"], admin_id);
+    let (_, me) = admin_request(&app, &admin_token, "GET", "/me", None).await;
+    assert!(me.get("impersonated_by").is_none());
+
+    // Sensitive endpoints are off limits while impersonating
+    let (status, _) = admin_request(&app, &impersonation_token, "POST", "/me/api-keys", Some(json!({ "name": "x", "scopes": [] }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Admins cannot be impersonated, nor can admins impersonate themselves
+    let (status, _) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/impersonate", admin_id), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    users(&pool).set_role(alice_id, Role::Admin).await.unwrap();
+    let (status, _) = admin_request(&app, &admin_token, "POST", &format!("/admin/users/{}/impersonate", alice_id), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    users(&pool).set_role(alice_id, Role::User).await.unwrap();
+
+    let (status, _) = admin_request(&app, &admin_token, "POST", "/impersonation/stop", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "not impersonating");
+    let (status, _) = admin_request(&app, &impersonation_token, "POST", "/impersonation/stop", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = admin_request(&app, &impersonation_token, "GET", "/me", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "a copy of the token stops working too");
+
+    // Losing the admin role ends any impersonation in progress
+    let (_, _, headers) = test_request(
+        app.clone(), "POST", &format!("/admin/users/{}/impersonate", alice_id), None, None, Some(&cookies),
+    ).await;
+    let impersonation_token = extract_response_cookie(&headers, ACCESS_TOKEN_COOKIE).unwrap();
+    let (status, _) = admin_request(&app, &impersonation_token, "GET", "/me", None).await;
+    assert_eq!(status, StatusCode::OK);
+    users(&pool).set_role(admin_id, Role::User).await.unwrap();
+    let (status, _) = admin_request(&app, &impersonation_token, "GET", "/me", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,142 @@
+use axum::{Router, http::StatusCode};
+use clap::Parser;
+use serde_json::json;
+use super::helpers::{setup_test_db, setup_test_redis, test_request, register_and_login, extract_response_cookie};
+use crate::cli::user::{UserRef, run_sessions, run_user};
+use crate::cli::{Cli, Command, SessionsCommand, UserCommand};
+use crate::config::Config;
+use crate::models::audit_event::{AuditEventFilter, AuditEventType};
+use crate::models::user::{Role, UserStatus};
+use crate::services::admin_service::AdminError;
+use crate::services::cookie_service::{ACCESS_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE};
+use crate::services::email_service::EmailService;
+use crate::AppState;
+
+/// The state the CLI runs against, plus an API over the same database
+async fn setup() -> (AppState, Router) {
+    let pool = setup_test_db().await;
+    let state = crate::build_state(pool, setup_test_redis(), Config::from_env(), EmailService::in_memory());
+    (state.clone(), crate::router(state))
+}
+
+fn create(username: &str, email: &str, password: &str, role: Role) -> UserCommand {
+    UserCommand::Create {
+        username: username.to_string(),
+        email: email.to_string(),
+        password: Some(password.to_string()),
+        role,
+    }
+}
+
+async fn login(app: &Router, email: &str, password: &str) -> StatusCode {
+    let body = json!({ "email": email, "password": password });
+    test_request(app.clone(), "POST", "/login", Some(body), None, None).await.0
+}
+
+#[test]
+fn test_cli_parsing() {
+    let parse = |args: &[&str]| Cli::try_parse_from(["app"].iter().chain(args)).unwrap().command;
+
+    assert_eq!(
+        parse(&["user", "create", "--username", "root", "--email", "root@example.com", "--role", "admin"]),
+        Some(Command::User(UserCommand::Create {
+            username: "root".to_string(),
+            email: "root@example.com".to_string(),
+            password: None,
+            role: Role::Admin,
+        }))
+    );
+    assert_eq!(
+        parse(&["user", "grant-role", "7", "admin"]),
+        Some(Command::User(UserCommand::GrantRole { user: UserRef::Id(7), role: Role::Admin }))
+    );
+    assert_eq!(
+        parse(&["sessions", "revoke", "--user", "alice@example.com"]),
+        Some(Command::Sessions(SessionsCommand::Revoke { user: UserRef::Email("alice@example.com".to_string()) }))
+    );
+    assert!(matches!(
+        parse(&["user", "list", "--status", "pending_verification"]),
+        Some(Command::User(UserCommand::List { status: Some(UserStatus::PendingVerification), page: 1, per_page: 20, .. }))
+    ));
+
+    assert!(Cli::try_parse_from(["app", "user", "grant-role", "7", "superuser"]).is_err());
+    assert!(Cli::try_parse_from(["app", "user", "create", "--username", "root"]).is_err());
+}
+
+#[tokio::test]
+async fn test_create_first_admin() {
+    let (state, app) = setup().await;
+
+    run_user(&state, create("root", "root@example.com", "rootpass", Role::Admin)).await.unwrap();
+
+    // The new admin can sign in and use the admin API straight away
+    let body = json!({ "email": "root@example.com", "password": "rootpass" });
+    let (status, _, headers) = test_request(app.clone(), "POST", "/login", Some(body), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = extract_response_cookie(&headers, ACCESS_TOKEN_COOKIE).unwrap();
+    let cookies = [(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+    let (status, _, _) = test_request(app, "GET", "/admin/users", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+    let root = state.users.find_by_email("root@example.com").await.unwrap().unwrap();
+    assert!(root.is_admin());
+    assert_ne!(root.password_hash, "rootpass");
+
+    // Duplicate emails are refused, as on /register
+    let result = run_user(&state, create("root2", "root@example.com", "rootpass", Role::User)).await;
+    assert!(matches!(result, Err(AdminError::Auth(_))), "{:?}", result);
+
+    // Audited without an actor, and marked as coming from the command line
+    let filter = AuditEventFilter { user_id: Some(root.id), ..Default::default() };
+    let page = state.audit_log.list(&filter, 1, 10).await.unwrap();
+    let events: Vec<_> = page.events.iter().map(|e| e.event_type).collect();
+    assert_eq!(events, [AuditEventType::Login, AuditEventType::RoleChanged, AuditEventType::Registration]);
+    let role_changed = &page.events[1];
+    assert_eq!(role_changed.actor_id, None);
+    assert_eq!(role_changed.metadata.as_deref(), Some(r#"{"role":"admin","source":"cli"}"#));
+}
+
+#[tokio::test]
+async fn test_fix_accounts() {
+    let (state, app) = setup().await;
+    run_user(&state, create("alice", "alice@example.com", "password123", Role::User)).await.unwrap();
+    let alice = UserRef::Email("alice@example.com".to_string());
+
+    let disable = UserCommand::Disable { user: alice.clone(), reason: Some("abuse".to_string()) };
+    run_user(&state, disable).await.unwrap();
+    assert_eq!(login(&app, "alice@example.com", "password123").await, StatusCode::FORBIDDEN);
+
+    run_user(&state, UserCommand::Enable { user: alice.clone() }).await.unwrap();
+    assert_eq!(login(&app, "alice@example.com", "password123").await, StatusCode::OK);
+
+    let set_password = UserCommand::SetPassword { user: alice.clone(), password: Some("newpass456".to_string()) };
+    run_user(&state, set_password).await.unwrap();
+    assert_eq!(login(&app, "alice@example.com", "password123").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(login(&app, "alice@example.com", "newpass456").await, StatusCode::OK);
+
+    let id = state.users.find_by_email("alice@example.com").await.unwrap().unwrap().id;
+    run_user(&state, UserCommand::GrantRole { user: UserRef::Id(id), role: Role::Admin }).await.unwrap();
+    assert!(state.admin_service.get_user(id).await.unwrap().is_admin());
+
+    let list = UserCommand::List { search: None, role: Some(Role::Admin), status: None, page: 1, per_page: 20 };
+    run_user(&state, list).await.unwrap();
+
+    let missing = UserCommand::Enable { user: UserRef::Email("nobody@example.com".to_string()) };
+    assert!(matches!(run_user(&state, missing).await, Err(AdminError::UserNotFound)));
+}
+
+#[tokio::test]
+async fn test_revoke_sessions() {
+    let (state, app) = setup().await;
+    let (_, refresh_token) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+
+    let revoke = SessionsCommand::Revoke { user: UserRef::Email("alice@example.com".to_string()) };
+    run_sessions(&state, revoke).await.unwrap();
+
+    let cookies = [(REFRESH_TOKEN_COOKIE, refresh_token.as_str())];
+    let (status, _, _) = test_request(app, "POST", "/refresh", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let filter = AuditEventFilter { event_type: Some(AuditEventType::SessionsRevoked), ..Default::default() };
+    let page = state.audit_log.list(&filter, 1, 10).await.unwrap();
+    assert_eq!(page.events[0].metadata.as_deref(), Some(r#"{"revoked":1,"source":"cli"}"#));
+}
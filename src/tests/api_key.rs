@@ -0,0 +1,173 @@
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::helpers::{setup_test_db, create_test_app, test_request, register_and_login, users};
+use crate::repositories::UserRepository;
+use crate::repositories::sqlite::SqliteApiKeyRepository;
+use crate::services::api_key_service::{ApiKeyError, ApiKeyService};
+use crate::services::cookie_service::ACCESS_TOKEN_COOKIE;
+
+fn api_key_headers(name: &'static str, value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(name, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+async fn create_key(app: &axum::Router, access_token: &str, scopes: Value) -> (StatusCode, Value) {
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token)];
+    let (status, body, _) = test_request(
+        app.clone(),
+        "POST",
+        "/me/api-keys",
+        Some(json!({ "name": "ci", "scopes": scopes })),
+        None,
+        Some(&cookies),
+    ).await;
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_api_key_authenticates_requests() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (access_token, _) = register_and_login(&app, "testuser", "test@example.com", "password123").await;
+
+    let (status, response) = create_key(&app, &access_token, json!(["user:read", "api_keys:read"])).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let key = response["key"].as_str().unwrap().to_string();
+    let prefix = response["api_key"]["prefix"].as_str().unwrap();
+    assert!(key.starts_with(&format!("ak_{}_", prefix)));
+
+    // Authorization: ApiKey <key>
+    let (status, body, _) = test_request(
+        app.clone(),
+        "GET",
+        "/me",
+        None,
+        Some(api_key_headers("authorization", &format!("ApiKey {}", key))),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    let user: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(user["username"], "testuser");
+
+    // X-API-Key: <key>
+    let (status, body, _) = test_request(
+        app,
+        "GET",
+        "/me/api-keys",
+        None,
+        Some(api_key_headers("x-api-key", &key)),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+    let list: Value = serde_json::from_str(&body).unwrap();
+    let keys = list["api_keys"].as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0]["last_used_at"].is_string(), "usage should be recorded");
+    assert!(keys[0].get("key").is_none() && keys[0].get("key_hash").is_none());
+}
+
+#[tokio::test]
+async fn test_api_key_scopes_are_enforced() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (access_token, _) = register_and_login(&app, "testuser", "test@example.com", "password123").await;
+
+    let (status, _) = create_key(&app, &access_token, json!(["admin:everything"])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, response) = create_key(&app, &access_token, json!(["api_keys:read"])).await;
+    let key = response["key"].as_str().unwrap();
+
+    let (status, _, _) = test_request(
+        app.clone(),
+        "GET",
+        "/me",
+        None,
+        Some(api_key_headers("x-api-key", key)),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Keys can never mint further keys
+    let (status, _, _) = test_request(
+        app,
+        "POST",
+        "/me/api-keys",
+        Some(json!({ "name": "escalate", "scopes": ["user:read"] })),
+        Some(api_key_headers("x-api-key", key)),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_deleted_or_unknown_api_key_is_rejected() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (access_token, _) = register_and_login(&app, "testuser", "test@example.com", "password123").await;
+
+    let (_, response) = create_key(&app, &access_token, json!(["user:read"])).await;
+    let key = response["key"].as_str().unwrap();
+    let id = response["api_key"]["id"].as_i64().unwrap();
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+    let (status, _, _) = test_request(
+        app.clone(),
+        "DELETE",
+        &format!("/me/api-keys/{}", id),
+        None,
+        None,
+        Some(&cookies),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = test_request(
+        app.clone(),
+        "GET",
+        "/me",
+        None,
+        Some(api_key_headers("x-api-key", key)),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _, _) = test_request(
+        app,
+        "GET",
+        "/me",
+        None,
+        Some(api_key_headers("authorization", "ApiKey ak_notakey_x")),
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_key_expiry_is_bounded() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (access_token, _) = register_and_login(&app, "testuser", "test@example.com", "password123").await;
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+
+    for (days, expected) in [
+        (json!(0), StatusCode::BAD_REQUEST),
+        (json!(3651), StatusCode::BAD_REQUEST),
+        (json!(i64::MAX), StatusCode::BAD_REQUEST),
+        (json!(3650), StatusCode::CREATED),
+    ] {
+        let body = json!({ "name": "ci", "scopes": ["user:read"], "expires_in_days": days });
+        let (status, _, _) = test_request(app.clone(), "POST", "/me/api-keys", Some(body), None, Some(&cookies)).await;
+        assert_eq!(status, expected, "{} days", days);
+    }
+
+    // The service refuses rather than overflowing, whatever its caller checked
+    let pool = setup_test_db().await;
+    let user = users(&pool).create("alice", "hash", "alice@example.com").await.unwrap();
+    let service = ApiKeyService::new(Arc::new(SqliteApiKeyRepository::new(pool)));
+    let scopes = ["user:read".to_string()];
+    let result = service.create_key(user.id, "ci", &scopes, Some(i64::MAX)).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidExpiry(i64::MAX))), "{:?}", result);
+}
@@ -0,0 +1,84 @@
+use axum::{Router, http::StatusCode};
+use serde_json::{json, Value};
+use super::helpers::{setup_test_db, create_test_app, test_request, register_and_login, users};
+use crate::models::user::Role;
+use crate::repositories::UserRepository;
+use crate::services::cookie_service::ACCESS_TOKEN_COOKIE;
+
+async fn get_json(app: &Router, token: &str, uri: &str) -> (StatusCode, Value) {
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, token)];
+    let (status, body, _) = test_request(app.clone(), "GET", uri, None, None, Some(&cookies)).await;
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_auth_events_are_recorded_for_the_user() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+    let (access_token, _) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+
+    let wrong = json!({ "email": "alice@example.com", "password": "wrongpassword" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/login", Some(wrong), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let unknown = json!({ "email": "nobody@example.com", "password": "wrongpassword" });
+    test_request(app.clone(), "POST", "/login", Some(unknown), None, None).await;
+
+    // Failed logins against the account show up for its owner
+    let (status, page) = get_json(&app, &access_token, "/me/security-events").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["events"][0]["event_type"], "login");
+    assert_eq!(page["events"][0]["outcome"], "failure");
+    assert_eq!(page["events"][0]["metadata"]["email"], "alice@example.com");
+    assert_eq!(page["events"][1]["event_type"], "login");
+    assert_eq!(page["events"][1]["outcome"], "success");
+    assert_eq!(page["events"][2]["event_type"], "registration");
+
+    let (other, _) = register_and_login(&app, "bob", "bob@example.com", "password123").await;
+    let (_, page) = get_json(&app, &other, "/me/security-events").await;
+    assert_eq!(page["total"], 2, "users only see their own events");
+}
+
+#[tokio::test]
+async fn test_admin_queries_audit_log_with_filters() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+    let (admin_token, _) = register_and_login(&app, "admin", "admin@example.com", "adminpass").await;
+    let admin = users(&pool).find_by_email("admin@example.com").await.unwrap().unwrap();
+    users(&pool).set_role(admin.id, Role::Admin).await.unwrap();
+    let (user_token, _) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+
+    let (status, _) = get_json(&app, &user_token, "/admin/audit-events").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let wrong = json!({ "email": "alice@example.com", "password": "wrongpassword" });
+    test_request(app.clone(), "POST", "/login", Some(wrong), None, None).await;
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, admin_token.as_str())];
+    let (status, _, _) = test_request(
+        app.clone(), "POST", &format!("/admin/users/{}/disable", alice.id),
+        Some(json!({ "reason": "fraud" })), None, Some(&cookies),
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+
+
+    let (status, page) = get_json(&app, &admin_token, "/admin/audit-events?event_type=login&outcome=failure").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["events"][0]["metadata"]["email"], "alice@example.com");
+
+    let (_, page) = get_json(&app, &admin_token, &format!("/admin/audit-events?actor_id={}", admin.id)).await;
+    assert_eq!(page["total"], 1);
+    let event = &page["events"][0];
+    assert_eq!(event["event_type"], "status_changed");
+    assert_eq!(event["user_id"], alice.id);
+    assert_eq!(event["metadata"]["status"], "disabled");
+    assert_eq!(event["metadata"]["reason"], "fraud");
+
+    let (_, page) = get_json(&app, &admin_token, &format!("/admin/audit-events?user_id={}", alice.id)).await;
+    assert_eq!(page["total"], 4);
+
+    let (_, page) = get_json(&app, &admin_token, "/admin/audit-events?since=2100-01-01T00:00:00Z").await;
+    assert_eq!(page["total"], 0);
+}
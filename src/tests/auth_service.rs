@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use super::helpers::{init_tracing, setup_test_redis};
+use crate::models::user::{UserFilter, UserStatus};
+use crate::repositories::UserRepository;
+use crate::repositories::memory::InMemoryUserRepository;
+use crate::services::auth_service::{AuthError, AuthService};
+use crate::services::jwt_service::JwtService;
+use crate::services::password_hasher::PasswordHasher;
+
+/// An `AuthService` over an in-memory user store, plus a handle on that store
+fn auth_service() -> (AuthService, InMemoryUserRepository) {
+    init_tracing();
+    let users = InMemoryUserRepository::new();
+    let repository: Arc<dyn UserRepository> = Arc::new(users.clone());
+    let jwt_service = JwtService::new(repository.clone(), setup_test_redis(), "test-secret".to_string());
+    (AuthService::new(repository, jwt_service), users)
+}
+
+#[tokio::test]
+async fn test_register_stores_hashed_password() {
+    let (auth, users) = auth_service();
+
+    let id = auth.register("alice", "password123", "alice@example.com").await.unwrap();
+
+    let user = users.find_by_id(id).await.unwrap().unwrap();
+    assert_eq!(user.username, "alice");
+    assert_eq!(user.email, "alice@example.com");
+    assert_ne!(user.password_hash, "password123");
+    assert!(user.password_hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+    assert!(PasswordHasher::default().verify("password123", &user.password_hash).await.unwrap());
+    assert!(user.is_active() && !user.is_admin());
+}
+
+#[tokio::test]
+async fn test_register_rejects_existing_email() {
+    let (auth, users) = auth_service();
+    auth.register("alice", "password123", "alice@example.com").await.unwrap();
+
+    let result = auth.register("alice2", "password456", "alice@example.com").await;
+    assert!(matches!(result, Err(AuthError::AccountExists)), "{:?}", result);
+
+    // A taken username gives the same error, so neither can be told apart
+    let result = auth.register("alice", "password456", "other@example.com").await;
+    assert!(matches!(result, Err(AuthError::AccountExists)), "{:?}", result);
+    assert_eq!(users.count(&UserFilter::default()).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_login_checks_password_and_account_state() {
+    let (auth, users) = auth_service();
+    let id = auth.register("alice", "password123", "alice@example.com").await.unwrap();
+
+    let tokens = auth.login("alice@example.com", "password123").await.result.unwrap();
+    assert_eq!(tokens.user_id, id);
+
+    // Failures name the account they were against, when there is one
+    for (email, password, user_id) in [("alice@example.com", "wrong-password", Some(id)), ("nobody@example.com", "password123", None)] {
+        let attempt = auth.login(email, password).await;
+        assert!(matches!(attempt.result, Err(AuthError::InvalidCredentials)), "{:?}", attempt.result);
+        assert_eq!(attempt.user_id, user_id);
+    }
+
+    users.set_password_reset_required(id, true).await.unwrap();
+    let result = auth.login("alice@example.com", "password123").await.result;
+    assert!(matches!(result, Err(AuthError::PasswordResetRequired)), "{:?}", result);
+
+    // The account state is only revealed to someone who knows the password
+    users.set_status(id, UserStatus::Locked, None).await.unwrap();
+    let result = auth.login("alice@example.com", "password123").await.result;
+    assert!(matches!(result, Err(AuthError::AccountInactive(UserStatus::Locked))), "{:?}", result);
+    let result = auth.login("alice@example.com", "wrong-password").await.result;
+    assert!(matches!(result, Err(AuthError::InvalidCredentials)), "{:?}", result);
+}
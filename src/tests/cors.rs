@@ -0,0 +1,128 @@
+use axum::{
+    Router,
+    http::{HeaderMap, HeaderValue, StatusCode},
+};
+use super::helpers::{setup_test_db, setup_test_redis, test_request};
+use crate::config::{AllowedOrigins, Config, CookieSecure, Environment, parse_setting};
+use crate::services::email_service::EmailService;
+
+async fn create_app(origins: &str) -> Router {
+    let pool = setup_test_db().await;
+    let config = Config {
+        cors_allowed_origins: origins.parse().unwrap(),
+        cors_exposed_headers: vec!["x-request-id".to_string(), "retry-after".to_string()],
+        cors_max_age_secs: 600,
+        ..Config::from_env()
+    };
+    crate::build_router(pool, setup_test_redis(), config, EmailService::in_memory())
+}
+
+async fn preflight(app: &Router, origin: &str, method: &str) -> (StatusCode, HeaderMap) {
+    let mut headers = HeaderMap::new();
+    headers.insert("origin", HeaderValue::from_str(origin).unwrap());
+    headers.insert("access-control-request-method", HeaderValue::from_str(method).unwrap());
+    headers.insert("access-control-request-headers", HeaderValue::from_static("content-type,x-api-key"));
+    let (status, _, headers) = test_request(app.clone(), "OPTIONS", "/me", None, Some(headers), None).await;
+    (status, headers)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+#[tokio::test]
+async fn test_preflight_from_allowed_origin() {
+    let app = create_app("https://app.example.com, https://admin.example.com").await;
+
+    let (status, headers) = preflight(&app, "https://admin.example.com", "PATCH").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(header(&headers, "access-control-allow-origin"), Some("https://admin.example.com"));
+    assert_eq!(header(&headers, "access-control-allow-credentials"), Some("true"));
+    assert_eq!(header(&headers, "access-control-max-age"), Some("600"));
+    let methods = header(&headers, "access-control-allow-methods").unwrap();
+    for method in ["GET", "POST", "PUT", "PATCH", "DELETE"] {
+        assert!(methods.contains(method), "{} missing from {}", method, methods);
+    }
+    let allowed_headers = header(&headers, "access-control-allow-headers").unwrap();
+    assert!(allowed_headers.contains("content-type") && allowed_headers.contains("x-api-key"));
+    assert!(header(&headers, "vary").unwrap().contains("origin"));
+}
+
+#[tokio::test]
+async fn test_preflight_from_unknown_origin_is_not_allowed() {
+    let app = create_app("https://app.example.com").await;
+
+    // Without an allowed origin the browser rejects the preflight
+    let (_, headers) = preflight(&app, "https://evil.example.net", "POST").await;
+    assert!(headers.get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn test_wildcard_subdomain_origins() {
+    let app = create_app("https://*.example.com").await;
+
+    for origin in ["https://app.example.com", "https://pr-42.preview.example.com"] {
+        let (_, headers) = preflight(&app, origin, "GET").await;
+        assert_eq!(header(&headers, "access-control-allow-origin"), Some(origin));
+    }
+
+    for origin in [
+        "https://example.com",
+        "http://app.example.com",
+        "https://evilexample.com",
+        "https://app.example.com.evil.io",
+        "https://app.example.com:8443",
+    ] {
+        let (_, headers) = preflight(&app, origin, "GET").await;
+        assert!(headers.get("access-control-allow-origin").is_none(), "{} should be refused", origin);
+    }
+}
+
+#[tokio::test]
+async fn test_exposed_headers_on_cross_origin_response() {
+    let app = create_app("https://app.example.com").await;
+
+    let mut request_headers = HeaderMap::new();
+    request_headers.insert("origin", HeaderValue::from_static("https://app.example.com"));
+    let (status, _, headers) = test_request(app, "GET", "/", None, Some(request_headers), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(header(&headers, "access-control-allow-origin"), Some("https://app.example.com"));
+    let exposed = header(&headers, "access-control-expose-headers").unwrap();
+    assert!(exposed.contains("x-request-id") && exposed.contains("retry-after"));
+}
+
+#[test]
+fn test_parse_allowed_origins() {
+    let origins: AllowedOrigins = "https://App.Example.com/, http://localhost:3000".parse().unwrap();
+    assert!(origins.matches("https://app.example.com"));
+    assert!(origins.matches("http://localhost:3000"));
+    assert!(!origins.matches("http://localhost:3001"));
+
+    for invalid in ["*", "app.example.com", "https://*", "https://app.*.com", "https://a.com/path", "null"] {
+        assert!(invalid.parse::<AllowedOrigins>().is_err(), "{} should be rejected", invalid);
+    }
+}
+
+#[test]
+fn test_invalid_settings_stop_startup() {
+    // Rather than quietly falling back to the environment's default origins
+    let result = parse_setting("CORS_ALLOWED_ORIGINS", Some("*"), Environment::Development.default_cors_origins());
+    let error = result.unwrap_err();
+    assert!(error.starts_with("CORS_ALLOWED_ORIGINS=\"*\""), "{}", error);
+
+    assert!(parse_setting("COOKIE_SECURE", Some("yes"), CookieSecure::Auto).is_err());
+    assert!(parse_setting("APP_ENV", Some("staging"), Environment::Development).is_err());
+
+    let origins = parse_setting("CORS_ALLOWED_ORIGINS", Some("https://app.example.com"), AllowedOrigins::default());
+    assert!(origins.unwrap().matches("https://app.example.com"));
+    assert_eq!(parse_setting("APP_ENV", None, Environment::Production), Ok(Environment::Production));
+}
+
+#[test]
+fn test_environment_defaults() {
+    let development = Environment::Development.default_cors_origins();
+    assert!(development.matches("http://localhost:3000"));
+    assert!(!development.matches("https://app.example.com"));
+
+    assert!(Environment::Production.default_cors_origins().is_empty());
+}
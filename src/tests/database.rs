@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use super::helpers::{init_tracing, register_and_login, setup_postgres_schema, setup_test_redis, test_request};
+use crate::config::Config;
+use crate::db::{Backend, Database, SqliteSettings};
+use crate::models::audit_event::{AuditEventFilter, AuditEventType, AuditOutcome, NewAuditEvent};
+use crate::models::user::{Role, UserFilter, UserStatus};
+use crate::repositories::Repositories;
+use crate::services::cookie_service::ACCESS_TOKEN_COOKIE;
+
+/// A migrated pool confined to a fresh schema, or `None` when no test
+/// server is configured.
+async fn setup_postgres() -> Option<PgPool> {
+    let pool = setup_postgres_schema().await?;
+    Database::from(pool.clone()).migrate().await.expect("Failed to run migrations");
+    Some(pool)
+}
+
+/// A database file that is removed, with its WAL files, when dropped
+struct TempDatabase(PathBuf);
+
+impl TempDatabase {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("axum-boilerplate-{}.sqlite", uuid::Uuid::new_v4())))
+    }
+
+    fn url(&self) -> String {
+        format!("sqlite:{}", self.0.display())
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_defaults() {
+    init_tracing();
+    let file = TempDatabase::new();
+    let config = Config { database_url: file.url(), ..Config::from_env() };
+
+    // The file is created on first start
+    let db = Database::connect(&config).await.unwrap();
+    assert!(file.0.exists());
+    assert_eq!(db.max_connections(), 10);
+    assert_eq!(
+        db.sqlite_settings().await.unwrap(),
+        Some(SqliteSettings {
+            journal_mode: "wal".to_string(),
+            synchronous: "normal".to_string(),
+            busy_timeout_ms: 5000,
+            foreign_keys: true,
+        })
+    );
+    db.close().await;
+}
+
+#[tokio::test]
+async fn test_sqlite_settings_from_config() {
+    init_tracing();
+    let file = TempDatabase::new();
+    let config = Config {
+        database_url: file.url(),
+        db_max_connections: 3,
+        db_min_connections: 1,
+        sqlite_journal_mode: SqliteJournalMode::Delete,
+        sqlite_synchronous: SqliteSynchronous::Full,
+        sqlite_busy_timeout_ms: 250,
+        sqlite_foreign_keys: false,
+        ..Config::from_env()
+    };
+
+    let db = Database::connect(&config).await.unwrap();
+    assert_eq!(db.max_connections(), 3);
+    let settings = db.sqlite_settings().await.unwrap().unwrap();
+    assert_eq!(settings.journal_mode, "delete");
+    assert_eq!(settings.synchronous, "full");
+    assert_eq!(settings.busy_timeout_ms, 250);
+    assert!(!settings.foreign_keys);
+    db.close().await;
+
+    let missing = TempDatabase::new();
+    let config = Config { database_url: missing.url(), sqlite_create_if_missing: false, ..config };
+    assert!(Database::connect(&config).await.is_err());
+    assert!(!missing.0.exists());
+}
+
+#[tokio::test]
+async fn test_sqlite_concurrent_writes() {
+    init_tracing();
+    let file = TempDatabase::new();
+    let config = Config { database_url: file.url(), ..Config::from_env() };
+    let db = Database::connect(&config).await.unwrap();
+    db.migrate().await.unwrap();
+    let repositories = Repositories::new(&db);
+
+    // Writers queue on the busy timeout instead of failing with SQLITE_BUSY
+    let writes = (0..50).map(|i| {
+        let users = repositories.users.clone();
+        tokio::spawn(async move {
+            let user = users.create(&format!("user{}", i), "hash", &format!("user{}@example.com", i)).await?;
+            users.set_role(user.id, Role::Admin).await
+        })
+    });
+    for write in writes.collect::<Vec<_>>() {
+        write.await.unwrap().unwrap();
+    }
+    let filter = UserFilter { role: Some(Role::Admin), ..Default::default() };
+    assert_eq!(repositories.users.count(&filter).await.unwrap(), 50);
+
+    // Foreign keys are enforced
+    let orphan = repositories.api_keys.create(9999, "ci", "prefix", "hash", "user:read", None).await;
+    assert!(orphan.is_err());
+    db.close().await;
+}
+
+#[test]
+fn test_backend_from_url() {
+    assert_eq!(Backend::from_url("sqlite::memory:").unwrap(), Backend::Sqlite);
+    assert_eq!(Backend::from_url("sqlite:data/app.db?mode=rwc").unwrap(), Backend::Sqlite);
+    assert_eq!(Backend::from_url("postgres://app:secret@db/app").unwrap(), Backend::Postgres);
+    assert_eq!(Backend::from_url("postgresql://db/app").unwrap(), Backend::Postgres);
+
+    let error = Backend::from_url("mysql://db/app").unwrap_err().to_string();
+    assert!(error.contains("MySQL is not supported"), "{}", error);
+    assert!(Backend::from_url("redis://localhost").is_err());
+}
+
+#[tokio::test]
+async fn test_postgres_api_flow() {
+    let Some(pool) = setup_postgres().await else {
+        return;
+    };
+    let app = crate::create_router(pool, setup_test_redis());
+    let (access_token, _) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+    let cookies = [(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+
+    let (status, body, _) = test_request(app.clone(), "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+    let user: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(user["username"], "alice");
+
+    let api_key = json!({ "name": "ci", "scopes": ["user:read"], "expires_in_days": 30 });
+    let (status, body, _) = test_request(app.clone(), "POST", "/me/api-keys", Some(api_key), None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: Value = serde_json::from_str(&body).unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert("x-api-key", HeaderValue::from_str(created["key"].as_str().unwrap()).unwrap());
+    let (status, _, _) = test_request(app.clone(), "GET", "/me", None, Some(headers), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body, _) = test_request(app, "GET", "/me/security-events", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+    let page: Value = serde_json::from_str(&body).unwrap();
+    let events: Vec<&str> = page["events"].as_array().unwrap().iter().map(|e| e["event_type"].as_str().unwrap()).collect();
+    assert_eq!(events, ["api_key_created", "login", "registration"]);
+}
+
+#[tokio::test]
+async fn test_postgres_repositories() {
+    let Some(pool) = setup_postgres().await else {
+        return;
+    };
+    let repositories = Repositories::new(&Database::from(pool));
+    let users = &repositories.users;
+
+    let alice = users.create("alice", "hash-a", "alice@example.com").await.unwrap();
+    let bob = users.create("bob", "hash-b", "bob@example.com").await.unwrap();
+    assert_eq!(alice.role, Role::User);
+    assert_eq!(alice.status, UserStatus::Active);
+    assert!(users.create("alice", "hash", "other@example.com").await.is_err(), "usernames are unique");
+
+    users.set_role(bob.id, Role::Admin).await.unwrap();
+    users.set_status(alice.id, UserStatus::Locked, Some("too many attempts")).await.unwrap();
+    users.set_password_reset_required(alice.id, true).await.unwrap();
+    let alice = users.find_by_email("alice@example.com").await.unwrap().unwrap();
+    assert_eq!(alice.status, UserStatus::Locked);
+    assert_eq!(alice.status_reason.as_deref(), Some("too many attempts"));
+    assert!(alice.status_changed_at.is_some() && alice.password_reset_required);
+
+    users.update_password(alice.id, "hash-c").await.unwrap();
+    let alice = users.find_by_id(alice.id).await.unwrap().unwrap();
+    assert_eq!(alice.password_hash, "hash-c");
+    assert!(!alice.password_reset_required);
+
+    // Search ignores case, as it does on SQLite
+    let filter = UserFilter { search: Some("ALI".to_string()), ..Default::default() };
+    assert_eq!(users.list(&filter, 10, 0).await.unwrap().len(), 1);
+    let filter = UserFilter { role: Some(Role::Admin), ..Default::default() };
+    let admins = users.list(&filter, 10, 0).await.unwrap();
+    assert_eq!(admins.iter().map(|u| u.id).collect::<Vec<_>>(), [bob.id]);
+    assert_eq!(users.count(&UserFilter::default()).await.unwrap(), 2);
+
+    // LIKE wildcards in the search match literally
+    users.create("a_b", "hash", "a_b@example.com").await.unwrap();
+    users.create("axb", "hash", "axb@example.com").await.unwrap();
+    let filter = UserFilter { search: Some("A_B".to_string()), ..Default::default() };
+    assert_eq!(users.count(&filter).await.unwrap(), 1);
+    let filter = UserFilter { search: Some("%".to_string()), ..Default::default() };
+    assert!(users.list(&filter, 10, 0).await.unwrap().is_empty());
+
+    let audit_events = &repositories.audit_events;
+    audit_events
+        .create(&NewAuditEvent::failure(AuditEventType::Login).user(alice.id).metadata(json!({ "reason": "locked" })))
+        .await
+        .unwrap();
+    audit_events.create(&NewAuditEvent::success(AuditEventType::RoleChanged).user(bob.id).actor(alice.id)).await.unwrap();
+    let filter = AuditEventFilter { outcome: Some(AuditOutcome::Failure), ..Default::default() };
+    let failures = audit_events.list(&filter, 10, 0).await.unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].event_type, AuditEventType::Login);
+    assert_eq!(failures[0].metadata.as_deref(), Some(r#"{"reason":"locked"}"#));
+    let filter = AuditEventFilter { actor_id: Some(alice.id), ..Default::default() };
+    assert_eq!(audit_events.count(&filter).await.unwrap(), 1);
+}
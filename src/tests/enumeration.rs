@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::http::StatusCode;
+use serde_json::json;
+use super::helpers::{init_tracing, setup_test_db, setup_test_redis, create_test_app, test_request, users};
+use crate::config::Config;
+use crate::models::user::UserFilter;
+use crate::repositories::UserRepository;
+use crate::repositories::memory::InMemoryUserRepository;
+use crate::services::auth_service::AuthService;
+use crate::services::email_service::EmailService;
+use crate::services::jwt_service::JwtService;
+use crate::services::password_hasher::PasswordHasher;
+
+async fn register(app: &Router, username: &str, email: &str) -> (StatusCode, String) {
+    let body = json!({ "username": username, "email": email, "password": "password123" });
+    let (status, body, _) = test_request(app.clone(), "POST", "/register", Some(body), None, None).await;
+    (status, body)
+}
+
+async fn login(app: &Router, login: &str, password: &str) -> (StatusCode, String) {
+    let body = json!({ "login": login, "password": password });
+    let (status, body, _) = test_request(app.clone(), "POST", "/login", Some(body), None, None).await;
+    (status, body)
+}
+
+async fn timed<T>(future: impl std::future::Future<Output = T>) -> Duration {
+    let started = Instant::now();
+    future.await;
+    started.elapsed()
+}
+
+#[tokio::test]
+async fn test_unknown_accounts_take_as_long_as_wrong_passwords() {
+    init_tracing();
+    let users = InMemoryUserRepository::new();
+    let repository: Arc<dyn UserRepository> = Arc::new(users);
+    let jwt_service = JwtService::new(repository.clone(), setup_test_redis(), "test-secret".to_string());
+    // Slow enough that skipping the hash would stand out
+    let hasher = PasswordHasher::argon2id(32 * 1024, 3, 1).unwrap();
+    let auth = AuthService::new(repository, jwt_service).with_password_hasher(hasher);
+    auth.register("alice", "password123", "alice@example.com").await.unwrap();
+    let _ = auth.login("nobody@example.com", "password123").await.result;
+
+    let wrong_password = timed(auth.login("alice@example.com", "wrong-password")).await;
+    for login in ["nobody@example.com", "nobody"] {
+        let unknown = timed(auth.login(login, "password123")).await;
+        assert!(unknown > wrong_password / 2, "{}: {:?}, a wrong password {:?}", login, unknown, wrong_password);
+    }
+}
+
+#[tokio::test]
+async fn test_failed_logins_look_the_same() {
+    let app = create_test_app(setup_test_db().await);
+    register(&app, "alice", "alice@example.com").await;
+
+    let wrong_password = login(&app, "alice@example.com", "wrong-password").await;
+    assert_eq!(wrong_password.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(login(&app, "nobody@example.com", "password123").await, wrong_password);
+    assert_eq!(login(&app, "nobody", "password123").await, wrong_password);
+    assert_eq!(login(&app, "alice", "wrong-password").await, wrong_password);
+}
+
+#[tokio::test]
+async fn test_taken_email_and_username_look_the_same() {
+    let app = create_test_app(setup_test_db().await);
+    register(&app, "alice", "alice@example.com").await;
+
+    let taken_email = register(&app, "alice2", "alice@example.com").await;
+    assert_eq!(taken_email.0, StatusCode::CONFLICT);
+    assert_eq!(register(&app, "alice", "other@example.com").await, taken_email);
+}
+
+#[tokio::test]
+async fn test_enumeration_resistant_registration() {
+    let pool = setup_test_db().await;
+    let email_service = EmailService::in_memory();
+    let config = Config { enumeration_resistant_registration: true, ..Config::from_env() };
+    let app = crate::build_router(pool.clone(), setup_test_redis(), config, email_service.clone());
+
+    let created = register(&app, "alice", "alice@example.com").await;
+    assert_eq!(created.0, StatusCode::OK);
+    assert!(!created.1.contains("\"id\""), "{}", created.1);
+    assert_eq!(register(&app, "alice2", "ALICE@example.com").await, created);
+    assert_eq!(register(&app, "Alice", "bob@example.com").await, created);
+    assert_eq!(users(&pool).count(&UserFilter::default()).await.unwrap(), 1);
+    assert_eq!(login(&app, "alice", "password123").await.0, StatusCode::OK);
+
+    // Each address hears what happened instead
+    let sent = email_service.sent_emails();
+    let sent: Vec<_> = sent.iter().map(|email| (email.to.as_str(), email.subject.as_str())).collect();
+    assert_eq!(
+        sent,
+        [
+            ("alice@example.com", "Welcome"),
+            ("alice@example.com", "Sign-up attempt for your account"),
+            ("bob@example.com", "Finish creating your account"),
+        ]
+    );
+
+    // Requests that say nothing about existing accounts still fail normally
+    assert_eq!(register(&app, "bob@example.com", "bob@example.com").await.0, StatusCode::BAD_REQUEST);
+}
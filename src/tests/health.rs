@@ -0,0 +1,70 @@
+use axum::{Router, http::StatusCode};
+use serde_json::Value;
+use super::helpers::{setup_test_db, create_test_app, test_request};
+use crate::config::Config;
+use crate::db::RedisStore;
+use crate::services::email_service::EmailService;
+
+/// An app whose Redis is unreachable (nothing listens on port 1)
+async fn create_app_without_redis(redis_required: bool) -> Router {
+    let pool = setup_test_db().await;
+    let redis = RedisStore::open("redis://127.0.0.1:1").unwrap();
+    let config = Config { redis_required, ..Config::from_env() };
+    crate::build_router(pool, redis, config, EmailService::in_memory())
+}
+
+#[tokio::test]
+async fn test_liveness() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    let (status, body, _) = test_request(app, "GET", "/health/live", None, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn test_readiness_with_all_dependencies_up() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    let (status, body, _) = test_request(app, "GET", "/health/ready", None, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "ok");
+    for dependency in ["database", "redis"] {
+        assert_eq!(body["checks"][dependency]["status"], "up");
+        assert!(body["checks"][dependency]["latency_ms"].is_number());
+        assert!(body["checks"][dependency].get("error").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_readiness_fails_when_required_redis_is_down() {
+    let app = create_app_without_redis(true).await;
+
+    let (status, body, _) = test_request(app, "GET", "/health/ready", None, None, None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "unavailable");
+    assert_eq!(body["checks"]["database"]["status"], "up");
+    assert_eq!(body["checks"]["redis"]["status"], "down");
+    assert_eq!(body["checks"]["redis"]["required"], true);
+    // Driver errors are only logged
+    let error = &body["checks"]["redis"]["error"];
+    assert!(error == "unavailable" || error == "timed out", "{}", error);
+}
+
+#[tokio::test]
+async fn test_readiness_degraded_when_optional_redis_is_down() {
+    let app = create_app_without_redis(false).await;
+
+    let (status, body, _) = test_request(app, "GET", "/health/ready", None, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["checks"]["database"]["status"], "up");
+    assert_eq!(body["checks"]["redis"]["status"], "down");
+    assert_eq!(body["checks"]["redis"]["required"], false);
+}
@@ -1,11 +1,16 @@
+use crate::config::Config;
 use crate::db::RedisStore;
+use crate::repositories::sqlite::SqliteUserRepository;
+use crate::services::email_service::EmailService;
 use axum::{
     Router,
     body::Body,
     http::{HeaderMap, Request, StatusCode},
 };
 use serde_json::Value;
-use sqlx::SqlitePool;
+use sqlx::{PgPool, SqlitePool};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::str::FromStr;
 use std::sync::Once;
 use tower::ServiceExt;
 use tower_cookies::Cookie;
@@ -41,7 +46,7 @@ pub async fn setup_test_db() -> SqlitePool {
         .expect("Failed to create test database");
 
     // Run migrations
-    sqlx::migrate!("./migrations")
+    sqlx::migrate!("./migrations/sqlite")
         .run(&pool)
         .await
         .expect("Failed to run migrations");
@@ -50,9 +55,36 @@ pub async fn setup_test_db() -> SqlitePool {
     pool
 }
 
+/// Postgres tests only run when this points at a server we may create
+/// schemas on, e.g. `postgres://postgres@localhost/postgres`.
+const TEST_POSTGRES_URL: &str = "TEST_POSTGRES_URL";
+
+/// An unmigrated pool confined to a fresh schema, or `None` when no test
+/// Postgres server is configured.
+pub async fn setup_postgres_schema() -> Option<PgPool> {
+    init_tracing();
+    let url = std::env::var(TEST_POSTGRES_URL).ok()?;
+
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+    let admin = PgPool::connect(&url).await.expect("Failed to connect to test Postgres");
+    sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&admin).await.unwrap();
+    admin.close().await;
+
+    let options = PgConnectOptions::from_str(&url).unwrap().options([("search_path", schema.as_str())]);
+    Some(PgPoolOptions::new().connect_with(options).await.unwrap())
+}
+
+/// Direct access to the users table, for arranging state the API cannot
+pub fn users(pool: &SqlitePool) -> SqliteUserRepository {
+    SqliteUserRepository::new(pool.clone())
+}
+
 pub fn setup_test_redis() -> RedisStore {
     info!("Setting up test Redis store");
-    let store = RedisStore::new().expect("Failed to create test Redis store");
+    // Namespace keys per test: in-memory databases reuse user ids across tests
+    let store = RedisStore::new()
+        .expect("Failed to create test Redis store")
+        .with_namespace(format!("test:{}:", uuid::Uuid::new_v4()));
     info!("Test Redis setup complete");
     store
 }
@@ -65,6 +97,14 @@ pub fn create_test_app(pool: SqlitePool) -> Router {
     app
 }
 
+/// Create a test application whose outgoing emails are captured in memory
+pub fn create_test_app_with_email(pool: SqlitePool) -> (Router, EmailService) {
+    info!("Creating test application with in-memory email");
+    let email_service = EmailService::in_memory();
+    let app = super::super::build_router(pool, setup_test_redis(), Config::from_env(), email_service.clone());
+    (app, email_service)
+}
+
 pub async fn test_request(
     app: Router,
     method: &str,
@@ -88,16 +128,16 @@ pub async fn test_request(
         .header("content-type", "application/json");
 
     // Add cookies if provided
-    if let Some(cookies) = cookies {
-        if !cookies.is_empty() {
-            let cookie_header = cookies
-                .iter()
-                .map(|(name, value)| format!("{}={}", name, value))
-                .collect::<Vec<_>>()
-                .join("; ");
-            debug!("Setting cookie header: {}", cookie_header);
-            request = request.header("cookie", cookie_header);
-        }
+    if let Some(cookies) = cookies
+        && !cookies.is_empty()
+    {
+        let cookie_header = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        debug!("Setting cookie header: {}", cookie_header);
+        request = request.header("cookie", cookie_header);
     }
 
     // Add custom headers if provided
@@ -124,6 +164,13 @@ pub async fn test_request(
     (status, body, headers)
 }
 
+/// `/metrics` as Prometheus scrapes it, with a bearer token
+pub async fn scrape(app: Router, token: &str) -> (StatusCode, String, HeaderMap) {
+    let mut headers = HeaderMap::new();
+    headers.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+    test_request(app, "GET", "/metrics", None, Some(headers), None).await
+}
+
 /// Helper function to extract cookie values from Set-Cookie headers in test responses
 pub fn extract_response_cookie(headers: &HeaderMap, cookie_name: &str) -> Option<String> {
     headers
@@ -140,3 +187,25 @@ pub fn extract_response_cookie(headers: &HeaderMap, cookie_name: &str) -> Option
             })
         })
 }
+
+/// Register a user and log them in, returning the `(access_token, refresh_token)` cookie values
+pub async fn register_and_login(app: &Router, username: &str, email: &str, password: &str) -> (String, String) {
+    let register_data = serde_json::json!({
+        "username": username,
+        "email": email,
+        "password": password
+    });
+    let (status, _, _) = test_request(app.clone(), "POST", "/register", Some(register_data), None, None).await;
+    assert_eq!(status, StatusCode::OK, "registration should succeed");
+
+    let login_data = serde_json::json!({
+        "email": email,
+        "password": password,
+    });
+    let (status, _, headers) = test_request(app.clone(), "POST", "/login", Some(login_data), None, None).await;
+    assert_eq!(status, StatusCode::OK, "login should succeed");
+
+    let access_token = extract_response_cookie(&headers, crate::services::cookie_service::ACCESS_TOKEN_COOKIE).unwrap();
+    let refresh_token = extract_response_cookie(&headers, crate::services::cookie_service::REFRESH_TOKEN_COOKIE).unwrap();
+    (access_token, refresh_token)
+}
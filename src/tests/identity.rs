@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::SqlitePool;
+use sqlx::postgres::PgPoolOptions;
+use super::helpers::{init_tracing, setup_postgres_schema, setup_test_db, setup_test_redis, create_test_app, test_request, users};
+use crate::db::Database;
+use crate::models::user::{normalize_email, normalize_username};
+use crate::repositories::UserRepository;
+use crate::repositories::memory::InMemoryUserRepository;
+use crate::services::auth_service::AuthService;
+use crate::services::jwt_service::JwtService;
+
+async fn register(app: &axum::Router, username: &str, email: &str) -> StatusCode {
+    let body = json!({ "username": username, "email": email, "password": "password123" });
+    test_request(app.clone(), "POST", "/register", Some(body), None, None).await.0
+}
+
+async fn login(app: &axum::Router, body: serde_json::Value) -> StatusCode {
+    test_request(app.clone(), "POST", "/login", Some(body), None, None).await.0
+}
+
+/// Insert users as they might predate normalisation
+async fn insert_raw(pool: &SqlitePool, username: &str, email: &str) {
+    sqlx::query("INSERT INTO users (username, email, password_hash) VALUES (?, ?, 'hash')")
+        .bind(username)
+        .bind(email)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[test]
+fn test_normalize_email_and_username() {
+    assert_eq!(normalize_email("  Alice@Example.COM \n", true), "alice@example.com");
+    assert_eq!(normalize_email("Alice@Example.COM", false), "Alice@example.com");
+    // Only the last `@` separates the domain
+    assert_eq!(normalize_email("\"a@b\"@Example.com", false), "\"a@b\"@example.com");
+    assert_eq!(normalize_email(" not-an-email ", true), "not-an-email");
+
+    assert_eq!(normalize_username("  Alice "), "Alice");
+    // Full-width letters and ligatures fold to their plain forms
+    assert_eq!(normalize_username("ＡＬＩＣＥ"), "ALICE");
+    assert_eq!(normalize_username("ﬁnn"), "finn");
+    assert_eq!(normalize_username("\u{3000}bob\u{3000}"), "bob");
+}
+
+#[tokio::test]
+async fn test_email_and_username_ignore_case() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+
+    assert_eq!(register(&app, " Alice ", "  Alice@Example.COM ").await, StatusCode::OK);
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+    assert_eq!((alice.username.as_str(), alice.email.as_str()), ("Alice", "alice@example.com"));
+
+    // One account per address or name, whatever the case
+    assert_ne!(register(&app, "alice2", "ALICE@example.com").await, StatusCode::OK);
+    assert_ne!(register(&app, "ALICE", "other@example.com").await, StatusCode::OK);
+    assert_ne!(register(&app, "ＡＬＩＣＥ", "other@example.com").await, StatusCode::OK);
+
+    for body in [
+        json!({ "email": "ALICE@EXAMPLE.COM", "password": "password123" }),
+        json!({ "email": " alice@example.com ", "password": "password123" }),
+        json!({ "email": "alice", "password": "password123" }),
+        json!({ "username": "aLiCe", "password": "password123" }),
+        json!({ "login": "Alice", "password": "password123" }),
+    ] {
+        assert_eq!(login(&app, body.clone()).await, StatusCode::OK, "{}", body);
+    }
+    assert_eq!(login(&app, json!({ "username": "alice", "password": "wrong" })).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_non_ascii_case_is_ignored() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool.clone());
+
+    assert_eq!(register(&app, "Ölaf", "Ölaf@Exämple.com").await, StatusCode::OK);
+    assert_ne!(register(&app, "ölaf", "other@example.com").await, StatusCode::OK);
+    // A decomposed Ö, composed by NFKC before folding
+    assert_ne!(register(&app, "O\u{308}LAF", "other@example.com").await, StatusCode::OK);
+    assert_ne!(register(&app, "olaf2", "ÖLAF@EXÄMPLE.COM").await, StatusCode::OK);
+
+    assert_eq!(login(&app, json!({ "username": "ÖLAF", "password": "password123" })).await, StatusCode::OK);
+    assert_eq!(login(&app, json!({ "email": "ölaf@exämple.com", "password": "password123" })).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_migration_fills_in_identity_keys() {
+    let pool = setup_test_db().await;
+    let db = Database::from(pool.clone());
+    // Back to before the identity keys
+    db.revert(1).await.unwrap();
+
+    insert_raw(&pool, "Ölaf", "olaf@example.com").await;
+    insert_raw(&pool, "ölaf", "olaf2@example.com").await;
+    let error = db.migrate().await.unwrap_err().to_string();
+    assert!(error.contains("resolve them before migrating"), "{}", error);
+
+    // Once resolved, existing usernames are normalised and found by their keys
+    sqlx::query("DELETE FROM users WHERE email = 'olaf2@example.com'").execute(&pool).await.unwrap();
+    insert_raw(&pool, "ｃａｒｏｌ", "Carol@Example.com").await;
+    db.migrate().await.unwrap();
+    assert_eq!(users(&pool).find_by_username("ÖLAF").await.unwrap().unwrap().email, "olaf@example.com");
+    let carol = users(&pool).find_by_username("CAROL").await.unwrap().unwrap();
+    assert_eq!(carol.username, "carol");
+    assert!(users(&pool).find_by_email("carol@example.com").await.unwrap().is_some());
+    assert!(users(&pool).create("ölaf", "hash", "other@example.com").await.is_err());
+}
+
+#[tokio::test]
+async fn test_usernames_must_not_look_like_emails() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    assert_eq!(register(&app, "alice@example.com", "alice@example.com").await, StatusCode::BAD_REQUEST);
+    assert_eq!(register(&app, "   ", "alice@example.com").await, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_email_local_part_case_kept_when_configured() {
+    init_tracing();
+    let users = InMemoryUserRepository::new();
+    let repository: Arc<dyn UserRepository> = Arc::new(users.clone());
+    let jwt_service = JwtService::new(repository.clone(), setup_test_redis(), "test-secret".to_string());
+    let auth = AuthService::new(repository, jwt_service).with_lowercase_email_local_part(false);
+
+    let id = auth.register("alice", "password123", "Alice.Smith@Example.COM").await.unwrap();
+    assert_eq!(users.find_by_id(id).await.unwrap().unwrap().email, "Alice.Smith@example.com");
+
+    // Still one account per address
+    assert!(auth.register("alice2", "password123", "alice.smith@example.com").await.is_err());
+    assert!(auth.login("alice.smith@EXAMPLE.com", "password123").await.result.is_ok());
+}
+
+#[tokio::test]
+async fn test_migration_refuses_conflicting_users() {
+    let pool = setup_test_db().await;
+    let db = Database::from(pool.clone());
+    // Back to before the case-insensitive indexes
+    db.revert(2).await.unwrap();
+
+    insert_raw(&pool, "alice", "alice@example.com").await;
+    insert_raw(&pool, "Alice2", " ALICE@example.com").await;
+    let error = db.migrate().await.unwrap_err().to_string();
+    assert!(error.contains("differ only by case or whitespace"), "{}", error);
+
+    // Once resolved by hand, existing values are trimmed and matched without case
+    sqlx::query("DELETE FROM users WHERE username = 'Alice2'").execute(&pool).await.unwrap();
+    insert_raw(&pool, " bob ", "Bob@Example.com").await;
+    db.migrate().await.unwrap();
+    let bob = users(&pool).find_by_username("BOB").await.unwrap().unwrap();
+    assert_eq!(bob.username, "bob");
+    assert!(users(&pool).find_by_email("bob@example.com").await.unwrap().is_some());
+    assert!(users(&pool).create("BOB", "hash", "other@example.com").await.is_err());
+}
+
+#[tokio::test]
+async fn test_postgres_migration_refuses_conflicting_users() {
+    let Some(pool) = setup_postgres_schema().await else {
+        return;
+    };
+    let db = Database::from(pool.clone());
+    db.migrate().await.unwrap();
+    db.revert(2).await.unwrap();
+
+    for (username, email) in [("alice", "alice@example.com"), ("Alice2", " ALICE@example.com")] {
+        sqlx::query("INSERT INTO users (username, email, password_hash) VALUES ($1, $2, 'hash')")
+            .bind(username)
+            .bind(email)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    // sqlx keeps its advisory lock when a migration fails, so fail on a
+    // connection of our own and close it
+    let options = (*pool.connect_options()).clone();
+    let failing = PgPoolOptions::new().max_connections(1).connect_with(options).await.unwrap();
+    let error = Database::from(failing.clone()).migrate().await.unwrap_err().to_string();
+    assert!(error.contains("alice@example.com"), "{}", error);
+    failing.close().await;
+
+    sqlx::query("DELETE FROM users WHERE username = 'Alice2'").execute(&pool).await.unwrap();
+    db.migrate().await.unwrap();
+    let repositories = crate::repositories::Repositories::new(&db);
+    assert!(repositories.users.find_by_email("ALICE@EXAMPLE.COM").await.unwrap().is_some());
+    assert!(repositories.users.find_by_username("ALICE").await.unwrap().is_some());
+    assert!(repositories.users.create("ALICE", "hash", "other@example.com").await.is_err());
+
+    // Case is folded with Unicode rules, as on SQLite
+    repositories.users.create("Ölaf", "hash", "ölaf@example.com").await.unwrap();
+    assert!(repositories.users.find_by_username("ÖLAF").await.unwrap().is_some());
+    assert!(repositories.users.find_by_email("ÖLAF@EXAMPLE.COM").await.unwrap().is_some());
+    assert!(repositories.users.create("ölaf", "hash", "other2@example.com").await.is_err());
+}
@@ -0,0 +1,142 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::{info, info_span};
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt};
+use super::helpers::setup_test_redis;
+use crate::config::LogFormat;
+use crate::repositories::UserRepository;
+use crate::repositories::memory::InMemoryUserRepository;
+use crate::services::email_service::EmailService;
+use crate::services::jwt_service::JwtService;
+use crate::services::password_reset_service::PasswordResetService;
+use crate::telemetry::log_layer;
+use crate::telemetry::redact::scrub;
+
+const JWT: &str = "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOjF9.c2lnbmF0dXJl";
+const API_KEY: &str = "ak_ABCDEFGH_0123456789abcdef0123456789abcdef";
+
+/// In-memory log sink
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn output(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Capture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn log_sensitive_event() {
+    let span = info_span!("request", request_id = "req-123");
+    let _entered = span.enter();
+    info!(
+        email = "alice@example.com",
+        refresh_token = "opaque-refresh",
+        user_id = 7,
+        "Headers: cookie: access_token={}; theme=dark, authorization: Bearer {}, x-api-key: {}, from bob@example.com",
+        JWT,
+        JWT,
+        API_KEY
+    );
+}
+
+fn assert_no_secrets(output: &str) {
+    for secret in [JWT, API_KEY, "alice@example.com", "bob@example.com", "opaque-refresh"] {
+        assert!(!output.contains(secret), "log output leaked {}: {}", secret, output);
+    }
+}
+
+#[test]
+fn test_json_logs_redact_sensitive_data() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(log_layer(LogFormat::Json, capture.clone()));
+    tracing::subscriber::with_default(subscriber, log_sensitive_event);
+
+    let output = capture.output();
+    assert_no_secrets(&output);
+
+    let line: Value = serde_json::from_str(output.trim()).expect("each event is one JSON object");
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["request_id"], "req-123");
+    assert_eq!(line["spans"][0]["name"], "request");
+    assert_eq!(line["fields"]["email"], "[REDACTED]");
+    assert_eq!(line["fields"]["refresh_token"], "[REDACTED]");
+    assert_eq!(line["fields"]["user_id"], 7);
+
+    // Values inside the message are masked but the rest is kept
+    let message = line["fields"]["message"].as_str().unwrap();
+    assert!(message.contains("access_token=[REDACTED]; theme=dark"));
+    assert!(message.contains("Bearer [REDACTED]"));
+    assert!(message.contains("ak_ABCDEFGH_[REDACTED]"));
+}
+
+#[test]
+fn test_pretty_logs_redact_sensitive_data() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(log_layer(LogFormat::Pretty, capture.clone()));
+    tracing::subscriber::with_default(subscriber, log_sensitive_event);
+
+    let output = capture.output();
+    assert_no_secrets(&output);
+    assert!(output.contains("email=[REDACTED]"));
+    assert!(output.contains("user_id=7"));
+    assert!(output.contains("request_id=req-123"));
+}
+
+#[test]
+fn test_link_tokens_are_scrubbed() {
+    assert_eq!(
+        scrub("https://app.example.com/password-reset?token=abc123&next=/home"),
+        "https://app.example.com/password-reset?token=[REDACTED]&next=/home"
+    );
+    assert_eq!(scrub("GET /callback?state=1&code=xyz"), "GET /callback?state=1&code=[REDACTED]");
+}
+
+#[tokio::test]
+async fn test_logged_emails_leave_out_their_links() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(log_layer(LogFormat::Json, capture.clone()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let users = InMemoryUserRepository::new();
+    let alice = users.create("alice", "hash", "alice@example.com").await.unwrap();
+    let repository: Arc<dyn UserRepository> = Arc::new(users);
+    let redis_store = setup_test_redis();
+    let jwt_service = JwtService::new(repository.clone(), redis_store.clone(), "test-secret".to_string());
+    let resets = PasswordResetService::new(
+        repository,
+        redis_store,
+        jwt_service,
+        EmailService::log_only(),
+        "http://localhost:3000/password-reset".to_string(),
+        chrono::Duration::minutes(60),
+    );
+    resets.send_reset_link(&alice).await.unwrap();
+
+    let output = capture.output();
+    assert!(output.contains("Email (not sent, SMTP not configured)"), "{}", output);
+    assert!(output.contains("Reset your password"), "{}", output);
+    assert!(!Regex::new(r"token=[A-Za-z0-9]{48}").unwrap().is_match(&output), "log output leaked the reset token: {}", output);
+    assert_no_secrets(&output);
+}
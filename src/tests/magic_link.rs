@@ -0,0 +1,128 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use super::helpers::{setup_test_db, create_test_app_with_email, test_request, extract_response_cookie};
+use crate::services::cookie_service::{ACCESS_TOKEN_COOKIE, MAGIC_LINK_BINDING_COOKIE};
+use crate::services::email_service::{EmailService, OutgoingEmail};
+
+/// Register a user and request a magic link, returning `(token, binding_nonce)`
+async fn request_link(app: &axum::Router, email_service: &EmailService, email: &str) -> (String, String) {
+    let register_data = json!({
+        "username": "testuser",
+        "email": email,
+        "password": "password123"
+    });
+    let (status, _, _) = test_request(app.clone(), "POST", "/register", Some(register_data), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, headers) = test_request(
+        app.clone(),
+        "POST",
+        "/login/magic-link",
+        Some(json!({ "email": email })),
+        None,
+        None,
+    ).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let nonce = extract_response_cookie(&headers, MAGIC_LINK_BINDING_COOKIE).unwrap();
+    let sent = wait_for_emails(email_service, 1).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, email);
+
+    let token = sent[0]
+        .body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap()
+        .to_string();
+    (token, nonce)
+}
+
+/// Links are sent in the background, after the response
+async fn wait_for_emails(email_service: &EmailService, count: usize) -> Vec<OutgoingEmail> {
+    for _ in 0..200 {
+        let sent = email_service.sent_emails();
+        if sent.len() >= count {
+            return sent;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("expected {} emails, got {:?}", count, email_service.sent_emails());
+}
+
+#[tokio::test]
+async fn test_magic_link_login_is_single_use() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool);
+    let (token, nonce) = request_link(&app, &email_service, "test@example.com").await;
+
+    let cookies = vec![(MAGIC_LINK_BINDING_COOKIE, nonce.as_str())];
+    let uri = format!("/login/magic-link/verify?token={}", token);
+    let (status, _, headers) = test_request(app.clone(), "GET", &uri, None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let access_token = extract_response_cookie(&headers, ACCESS_TOKEN_COOKIE).unwrap();
+    let me_cookies = vec![(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+    let (status, _, _) = test_request(app.clone(), "GET", "/me", None, None, Some(&me_cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = test_request(app, "GET", &uri, None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "link must not be reusable");
+}
+
+#[tokio::test]
+async fn test_magic_link_is_bound_to_requesting_browser() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool);
+    let (token, nonce) = request_link(&app, &email_service, "test@example.com").await;
+    let uri = format!("/login/magic-link/verify?token={}", token);
+
+    let (status, _, _) = test_request(app.clone(), "GET", &uri, None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let wrong_cookies = vec![(MAGIC_LINK_BINDING_COOKIE, "someoneelse")];
+    let (status, _, _) = test_request(app.clone(), "GET", &uri, None, None, Some(&wrong_cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Rejected attempts from other browsers do not consume the link
+    let cookies = vec![(MAGIC_LINK_BINDING_COOKIE, nonce.as_str())];
+    let (status, _, _) = test_request(app, "GET", &uri, None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_magic_link_unknown_email_is_indistinguishable() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool);
+
+    let (status, body, headers) = test_request(
+        app.clone(),
+        "POST",
+        "/login/magic-link",
+        Some(json!({ "email": "nobody@example.com" })),
+        None,
+        None,
+    ).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("If an account exists"));
+    assert!(extract_response_cookie(&headers, MAGIC_LINK_BINDING_COOKIE).is_some());
+
+    // Once a later request's link has gone out, the unknown address still has none
+    request_link(&app, &email_service, "test@example.com").await;
+    let sent = email_service.sent_emails();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "test@example.com");
+}
+
+#[tokio::test]
+async fn test_magic_link_token_is_not_an_access_token() {
+    let pool = setup_test_db().await;
+    let (app, email_service) = create_test_app_with_email(pool);
+    let (token, _) = request_link(&app, &email_service, "test@example.com").await;
+
+    let cookies = vec![(ACCESS_TOKEN_COOKIE, token.as_str())];
+    let (status, _, _) = test_request(app, "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,69 @@
+use axum::Router;
+use axum::http::StatusCode;
+use serde_json::json;
+use super::helpers::{setup_test_db, setup_test_redis, test_request, register_and_login, scrape};
+use crate::config::Config;
+use crate::services::email_service::EmailService;
+
+const METRICS_TOKEN: &str = "scrape-token";
+
+async fn create_app() -> Router {
+    let config = Config { metrics_token: Some(METRICS_TOKEN.to_string()), ..Config::from_env() };
+    crate::build_router(setup_test_db().await, setup_test_redis(), config, EmailService::in_memory())
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_exposes_http_and_auth_metrics() {
+    let app = create_app().await;
+    register_and_login(&app, "testuser", "test@example.com", "password123").await;
+
+    let wrong = json!({ "email": "test@example.com", "password": "wrongpassword" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/login", Some(wrong), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body, headers) = scrape(app, METRICS_TOKEN).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers["content-type"].to_str().unwrap().starts_with("text/plain"));
+
+    // Metrics are process-wide, so other tests may have added to the counts
+    assert!(body.contains(r#"http_requests_total{method="POST",route="/login",status="200"}"#));
+    assert!(body.contains(r#"http_requests_total{method="POST",route="/login",status="401"}"#));
+    assert!(body.contains(r#"auth_logins_total{outcome="success"}"#));
+    assert!(body.contains(r#"auth_logins_total{outcome="failure"}"#));
+    assert!(body.contains(r#"auth_password_verify_duration_seconds_count{algorithm="argon2id"}"#));
+    assert!(body.contains(r#"redis_command_duration_seconds_count{operation="add_to_allowlist"}"#));
+    assert!(body.contains(r#"db_pool_connections{state="idle"}"#));
+    assert!(body.contains("db_pool_max_connections"));
+}
+
+#[tokio::test]
+async fn test_metrics_use_route_templates() {
+    let app = create_app().await;
+
+    test_request(app.clone(), "DELETE", "/me/api-keys/42", None, None, None).await;
+    test_request(app.clone(), "GET", "/no-such-page", None, None, None).await;
+    test_request(app.clone(), "FOO1", "/health/live", None, None, None).await;
+
+    let (_, body, _) = scrape(app, METRICS_TOKEN).await;
+    assert!(body.contains(r#"route="/me/api-keys/:id",status="401""#));
+    assert!(!body.contains("/me/api-keys/42"));
+    assert!(body.contains(r#"route="unmatched",status="404""#));
+    assert!(body.contains(r#"method="other",route="/health/live""#));
+    assert!(!body.contains("FOO1"));
+}
+
+#[tokio::test]
+async fn test_metrics_require_the_scrape_token() {
+    let app = create_app().await;
+    let (status, _, headers) = test_request(app.clone(), "GET", "/metrics", None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(headers["www-authenticate"], "Bearer");
+    let (status, body, _) = scrape(app.clone(), "wrong-token").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!body.contains("http_requests_total"));
+    assert_eq!(scrape(app, METRICS_TOKEN).await.0, StatusCode::OK);
+
+    // Not served at all without a token configured
+    let app = crate::build_router(setup_test_db().await, setup_test_redis(), Config::from_env(), EmailService::in_memory());
+    assert_eq!(scrape(app, METRICS_TOKEN).await.0, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,127 @@
+use clap::Parser;
+use sqlx::SqlitePool;
+
+use super::helpers::{init_tracing, setup_postgres_schema};
+use crate::cli::migrate::run_migrate;
+use crate::cli::{Cli, Command, MigrateCommand};
+use crate::db::{Database, MigrationState};
+use crate::repositories::Repositories;
+
+async fn unmigrated_db() -> Database {
+    init_tracing();
+    Database::from(SqlitePool::connect("sqlite::memory:").await.unwrap())
+}
+
+async fn states(db: &Database) -> Vec<MigrationState> {
+    db.migration_status().await.unwrap().into_iter().map(|m| m.state).collect()
+}
+
+/// Reverting everything and migrating again must leave a working schema
+async fn assert_round_trip(db: &Database) {
+    db.migrate().await.unwrap();
+    let count = db.migrator().iter().filter(|m| !m.migration_type.is_down_migration()).count();
+
+    let newest: Vec<_> = db
+        .migrator()
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .rev()
+        .take(2)
+        .map(|m| m.description.to_string())
+        .collect();
+    let reverted = db.revert(2).await.unwrap();
+    assert_eq!(reverted.into_iter().map(|m| m.description).collect::<Vec<_>>(), newest);
+    let mut expected = vec![MigrationState::Applied; count - 2];
+    expected.extend([MigrationState::Pending; 2]);
+    assert_eq!(states(db).await, expected);
+
+    assert_eq!(db.revert(usize::MAX).await.unwrap().len(), count - 2);
+    assert_eq!(states(db).await, vec![MigrationState::Pending; count]);
+    assert!(db.revert(1).await.unwrap().is_empty());
+
+    db.migrate().await.unwrap();
+    assert_eq!(states(db).await, vec![MigrationState::Applied; count]);
+    let repositories = Repositories::new(db);
+    let user = repositories.users.create("alice", "hash", "alice@example.com").await.unwrap();
+    repositories.api_keys.create(user.id, "ci", "prefix", "hash", "user:read", None).await.unwrap();
+}
+
+#[test]
+fn test_cli_parsing() {
+    assert_eq!(Cli::try_parse_from(["app"]).unwrap().command, None);
+    assert_eq!(Cli::try_parse_from(["app", "serve"]).unwrap().command, Some(Command::Serve));
+
+    let parse = |args: &[&str]| Cli::try_parse_from(["app", "migrate"].iter().chain(args)).unwrap().command;
+    assert_eq!(parse(&["up"]), Some(Command::Migrate(MigrateCommand::Up)));
+    assert_eq!(parse(&["status"]), Some(Command::Migrate(MigrateCommand::Status)));
+    assert_eq!(parse(&["check"]), Some(Command::Migrate(MigrateCommand::Check)));
+    assert_eq!(parse(&["down"]), Some(Command::Migrate(MigrateCommand::Down { steps: 1 })));
+    assert_eq!(parse(&["revert", "--steps", "3"]), Some(Command::Migrate(MigrateCommand::Down { steps: 3 })));
+
+    assert!(Cli::try_parse_from(["app", "migrate"]).is_err());
+    assert!(Cli::try_parse_from(["app", "migrate", "sideways"]).is_err());
+}
+
+#[tokio::test]
+async fn test_sqlite_migrate_and_revert() {
+    let db = unmigrated_db().await;
+    assert!(states(&db).await.iter().all(|state| *state == MigrationState::Pending));
+    assert_round_trip(&db).await;
+
+    // Reverted tables are really gone
+    db.revert(usize::MAX).await.unwrap();
+    let Database::Sqlite(pool) = &db else { unreachable!() };
+    assert!(sqlx::query("SELECT * FROM users").execute(pool).await.is_err());
+}
+
+#[tokio::test]
+async fn test_check_fails_until_schema_is_current() {
+    let db = unmigrated_db().await;
+    assert!(!run_migrate(&db, MigrateCommand::Check).await.unwrap());
+
+    assert!(run_migrate(&db, MigrateCommand::Up).await.unwrap());
+    assert!(run_migrate(&db, MigrateCommand::Check).await.unwrap());
+
+    assert!(run_migrate(&db, MigrateCommand::Down { steps: 1 }).await.unwrap());
+    assert!(!run_migrate(&db, MigrateCommand::Check).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_status_reports_drift() {
+    let db = unmigrated_db().await;
+    db.migrate().await.unwrap();
+    let Database::Sqlite(pool) = &db else { unreachable!() };
+    let first = db.migrator().iter().next().unwrap().version;
+
+    // An edited migration, and one only a newer build knows about
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = ?")
+        .bind(first)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES (99990101000000, 'from the future', TRUE, x'00', 0)",
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+
+    let status = db.migration_status().await.unwrap();
+    assert_eq!(status.first().unwrap().state, MigrationState::Modified);
+    let unknown = status.last().unwrap();
+    assert_eq!((unknown.version, unknown.state), (99990101000000, MigrationState::Unknown));
+    assert!(!run_migrate(&db, MigrateCommand::Check).await.unwrap());
+
+    // sqlx refuses to run or revert over drift rather than guessing
+    assert!(db.migrate().await.is_err());
+    assert!(db.revert(1).await.is_err());
+}
+
+#[tokio::test]
+async fn test_postgres_migrate_and_revert() {
+    let Some(pool) = setup_postgres_schema().await else {
+        return;
+    };
+    assert_round_trip(&Database::from(pool)).await;
+}
@@ -1,2 +1,24 @@
 pub mod helpers;
-pub mod auth; 
\ No newline at end of file
+pub mod auth;
+pub mod auth_service;
+pub mod identity;
+pub mod password_hashing;
+pub mod enumeration;
+pub mod password_policy;
+pub mod api_key;
+pub mod magic_link;
+pub mod admin;
+pub mod admin_cli;
+pub mod account_status;
+pub mod audit;
+pub mod metrics;
+pub mod telemetry;
+pub mod logging;
+pub mod health;
+pub mod shutdown;
+pub mod server;
+pub mod proxy;
+pub mod cors;
+pub mod security_headers;
+pub mod database;
+pub mod migrations;
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::json;
+use super::helpers::{init_tracing, setup_postgres_schema, setup_test_db, setup_test_redis, test_request, users, scrape};
+use crate::config::Config;
+use crate::db::Database;
+use crate::repositories::{Repositories, UserRepository};
+use crate::repositories::memory::InMemoryUserRepository;
+use crate::services::auth_service::{AuthError, AuthService};
+use crate::services::email_service::EmailService;
+use crate::services::jwt_service::JwtService;
+use crate::services::password_hasher::{PasswordHashAlgorithm, PasswordHasher};
+use crate::services::password_reset_service::PasswordResetService;
+
+/// Cheap parameters, so the tests stay fast
+fn argon2id(memory_kib: u32) -> PasswordHasher {
+    PasswordHasher::argon2id(memory_kib, 1, 1).unwrap()
+}
+
+fn auth_service(users: &InMemoryUserRepository, password_hasher: PasswordHasher) -> AuthService {
+    init_tracing();
+    let repository: Arc<dyn UserRepository> = Arc::new(users.clone());
+    let jwt_service = JwtService::new(repository.clone(), setup_test_redis(), "test-secret".to_string());
+    AuthService::new(repository, jwt_service).with_password_hasher(password_hasher)
+}
+
+/// An app whose password checks are slow and share a few blocking threads,
+/// with its hasher, so tests can watch this app's hashing alone
+async fn app_with_hashing_limits(max_concurrent: usize, max_queued: usize) -> (Router, PasswordHasher) {
+    let config = Config {
+        argon2_memory_kib: 32 * 1024,
+        argon2_iterations: 3,
+        password_hash_max_concurrent: max_concurrent,
+        password_hash_max_queued: max_queued,
+        metrics_token: Some("scrape-token".to_string()),
+        ..Config::from_env()
+    };
+    let state = crate::build_state(setup_test_db().await, setup_test_redis(), config, EmailService::in_memory());
+    let hasher = state.auth_service.password_hasher().clone();
+    let app = crate::router(state);
+
+    let body = json!({ "username": "alice", "email": "alice@example.com", "password": "password123" });
+    assert_eq!(test_request(app.clone(), "POST", "/register", Some(body), None, None).await.0, StatusCode::OK);
+    (app, hasher)
+}
+
+async fn login(app: Router) -> (StatusCode, HeaderMap) {
+    let body = json!({ "email": "alice@example.com", "password": "password123" });
+    let (status, _, headers) = test_request(app, "POST", "/login", Some(body), None, None).await;
+    (status, headers)
+}
+
+async fn stored_hash(users: &InMemoryUserRepository, id: i64) -> String {
+    users.find_by_id(id).await.unwrap().unwrap().password_hash
+}
+
+/// A rehash must not undo a password change or a forced reset made since the hash was read
+async fn assert_replace_password_hash(users: &dyn UserRepository) {
+    let alice = users.create("alice", "hash-a", "alice@example.com").await.unwrap();
+    users.set_password_reset_required(alice.id, true).await.unwrap();
+
+    assert!(!users.replace_password_hash(alice.id, "stale", "hash-b").await.unwrap());
+    assert!(users.replace_password_hash(alice.id, "hash-a", "hash-b").await.unwrap());
+    let alice = users.find_by_id(alice.id).await.unwrap().unwrap();
+    assert_eq!(alice.password_hash, "hash-b");
+    assert!(alice.password_reset_required);
+}
+
+#[tokio::test]
+async fn test_hashes_are_phc_strings() {
+    let hasher = argon2id(8192);
+    let hash = hasher.hash("password123").await.unwrap();
+    assert!(hash.starts_with("$argon2id$v=19$m=8192,t=1,p=1$"), "{}", hash);
+    assert_ne!(hasher.hash("password123").await.unwrap(), hash, "each hash gets its own salt");
+    assert!(hasher.verify("password123", &hash).await.unwrap());
+    assert!(!hasher.verify("password124", &hash).await.unwrap());
+
+    // Either algorithm verifies, whichever is configured for new hashes
+    let bcrypt_hash = PasswordHasher::bcrypt(4).hash("password123").await.unwrap();
+    assert!(bcrypt_hash.starts_with("$2b$04$"), "{}", bcrypt_hash);
+    assert!(hasher.verify("password123", &bcrypt_hash).await.unwrap());
+    assert!(PasswordHasher::bcrypt(4).verify("password123", &hash).await.unwrap());
+    assert_eq!(PasswordHashAlgorithm::of(&bcrypt_hash), Some(PasswordHashAlgorithm::Bcrypt));
+
+    assert!(matches!(hasher.verify("password123", "password123").await, Err(AuthError::PasswordHashError)));
+}
+
+#[tokio::test]
+async fn test_needs_rehash() {
+    let argon2_hash = argon2id(8192).hash("password123").await.unwrap();
+    let bcrypt_hash = PasswordHasher::bcrypt(4).hash("password123").await.unwrap();
+
+    assert!(!argon2id(8192).needs_rehash(&argon2_hash));
+    assert!(argon2id(8192).needs_rehash(&bcrypt_hash));
+    assert!(argon2id(16384).needs_rehash(&argon2_hash));
+    assert!(PasswordHasher::argon2id(8192, 2, 1).unwrap().needs_rehash(&argon2_hash));
+    assert!(argon2id(8192).needs_rehash("not a hash"));
+
+    assert!(!PasswordHasher::bcrypt(4).needs_rehash(&bcrypt_hash));
+    assert!(PasswordHasher::bcrypt(5).needs_rehash(&bcrypt_hash));
+    assert!(PasswordHasher::bcrypt(4).needs_rehash(&argon2_hash));
+}
+
+#[tokio::test]
+async fn test_hasher_from_config() {
+    assert_eq!("Argon2id".parse(), Ok(PasswordHashAlgorithm::Argon2id));
+    assert_eq!("bcrypt".parse(), Ok(PasswordHashAlgorithm::Bcrypt));
+    assert!("scrypt".parse::<PasswordHashAlgorithm>().is_err());
+
+    let config = Config { password_hash_algorithm: PasswordHashAlgorithm::Bcrypt, bcrypt_cost: 4, ..Config::from_env() };
+    let hasher = PasswordHasher::from_config(&config);
+    assert_eq!(hasher.algorithm(), PasswordHashAlgorithm::Bcrypt);
+    assert!(hasher.hash("password123").await.unwrap().starts_with("$2b$04$"));
+
+    // Parameters Argon2 rejects fall back to the defaults rather than failing logins
+    let config = Config { argon2_memory_kib: 1, ..Config::from_env() };
+    let hash = PasswordHasher::from_config(&config).hash("password123").await.unwrap();
+    assert!(hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"), "{}", hash);
+}
+
+#[tokio::test]
+async fn test_login_upgrades_outdated_hash() {
+    let users = InMemoryUserRepository::new();
+    let auth = auth_service(&users, argon2id(8192));
+    let bcrypt_hash = PasswordHasher::bcrypt(4).hash("password123").await.unwrap();
+    let id = users.create("alice", &bcrypt_hash, "alice@example.com").await.unwrap().id;
+
+    // Only a correct password can be rehashed
+    assert!(auth.login("alice@example.com", "wrong-password").await.result.is_err());
+    assert_eq!(stored_hash(&users, id).await, bcrypt_hash);
+
+    auth.login("alice@example.com", "password123").await.result.unwrap();
+    let upgraded = stored_hash(&users, id).await;
+    assert!(upgraded.starts_with("$argon2id$v=19$m=8192,t=1,p=1$"), "{}", upgraded);
+    auth.login("alice@example.com", "password123").await.result.unwrap();
+    assert_eq!(stored_hash(&users, id).await, upgraded, "current hashes are left alone");
+
+    // Raising the cost upgrades Argon2id hashes too
+    let auth = auth_service(&users, argon2id(16384));
+    auth.login("alice", "password123").await.result.unwrap();
+    assert!(stored_hash(&users, id).await.starts_with("$argon2id$v=19$m=16384,t=1,p=1$"));
+}
+
+#[tokio::test]
+async fn test_blocked_login_keeps_outdated_hash() {
+    let users = InMemoryUserRepository::new();
+    let auth = auth_service(&users, argon2id(8192));
+    let bcrypt_hash = PasswordHasher::bcrypt(4).hash("password123").await.unwrap();
+    let id = users.create("alice", &bcrypt_hash, "alice@example.com").await.unwrap().id;
+    users.set_password_reset_required(id, true).await.unwrap();
+
+    let result = auth.login("alice@example.com", "password123").await.result;
+    assert!(matches!(result, Err(AuthError::PasswordResetRequired)), "{:?}", result);
+    assert_eq!(stored_hash(&users, id).await, bcrypt_hash);
+}
+
+#[tokio::test]
+async fn test_replace_password_hash_compares_first() {
+    assert_replace_password_hash(&InMemoryUserRepository::new()).await;
+    assert_replace_password_hash(&users(&setup_test_db().await)).await;
+
+    if let Some(pool) = setup_postgres_schema().await {
+        let db = Database::from(pool);
+        db.migrate().await.unwrap();
+        assert_replace_password_hash(Repositories::new(&db).users.as_ref()).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_other_routes_stay_responsive_during_logins() {
+    // As many hashes at once as there are async workers, which would all be
+    // stuck if hashing ran on them
+    let (app, hasher) = app_with_hashing_limits(2, 100).await;
+    let started = Instant::now();
+    assert_eq!(login(app.clone()).await.0, StatusCode::OK);
+    let one_login = started.elapsed();
+
+    let logins: Vec<_> = (0..16).map(|_| tokio::spawn(login(app.clone()))).collect();
+    let hashing = async {
+        while hasher.in_flight() < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), hashing).await.expect("logins never started hashing");
+
+    // Spawned, so they compete for the workers; the test body has a thread of its own
+    for _ in 0..5 {
+        let started = Instant::now();
+        let request = test_request(app.clone(), "GET", "/health/live", None, None, None);
+        let (status, _, _) = tokio::spawn(request).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let elapsed = started.elapsed();
+        assert!(elapsed < one_login / 2, "liveness took {:?}, a login {:?}", elapsed, one_login);
+    }
+    assert!(logins.iter().any(|login| !login.is_finished()), "the logins should still be queued");
+
+    for login in logins {
+        assert_eq!(login.await.unwrap().0, StatusCode::OK);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_saturated_hashing_returns_503() {
+    let (app, _) = app_with_hashing_limits(1, 1).await;
+
+    let logins: Vec<_> = (0..8).map(|_| tokio::spawn(login(app.clone()))).collect();
+    let mut statuses = Vec::new();
+    for login in logins {
+        let (status, headers) = login.await.unwrap();
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            assert_eq!(headers["retry-after"], "1");
+        }
+        statuses.push(status);
+    }
+
+    // One login runs and one waits; the rest are turned away rather than piling up
+    let ok = statuses.iter().filter(|status| **status == StatusCode::OK).count();
+    let refused = statuses.iter().filter(|status| **status == StatusCode::SERVICE_UNAVAILABLE).count();
+    assert!(ok >= 2 && refused >= 1 && ok + refused == 8, "{:?}", statuses);
+    assert_eq!(login(app.clone()).await.0, StatusCode::OK, "the queue drains again");
+
+    let (_, body, _) = scrape(app, "scrape-token").await;
+    assert!(body.contains("auth_password_hash_rejected_total"));
+    assert!(body.contains("auth_password_hash_queue_depth"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_saturated_hashing_keeps_reset_links_usable() {
+    init_tracing();
+    let users = InMemoryUserRepository::new();
+    let alice = users.create("alice", "hash", "alice@example.com").await.unwrap();
+    let repository: Arc<dyn UserRepository> = Arc::new(users.clone());
+    let redis_store = setup_test_redis();
+    let jwt_service = JwtService::new(repository.clone(), redis_store.clone(), "test-secret".to_string());
+    let email_service = EmailService::in_memory();
+    // One slot and no queue, held by a slow hash below
+    let hasher = PasswordHasher::argon2id(64 * 1024, 3, 1).unwrap().with_limits(1, 0);
+    let resets = PasswordResetService::new(
+        repository,
+        redis_store,
+        jwt_service,
+        email_service.clone(),
+        "http://localhost:3000/password-reset".to_string(),
+        chrono::Duration::minutes(60),
+    )
+    .with_password_hasher(hasher.clone());
+
+    resets.send_reset_link(&alice).await.unwrap();
+    let email = email_service.sent_emails().pop().unwrap();
+    let token = email.body.split("token=").nth(1).unwrap().trim().to_string();
+
+    let busy = tokio::spawn({
+        let hasher = hasher.clone();
+        async move { hasher.hash("occupying the only slot").await }
+    });
+    while hasher.in_flight() < 1 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let result = resets.reset_password(&token, "new-password-456").await;
+    assert!(matches!(result, Err(AuthError::Overloaded)), "{:?}", result);
+    busy.await.unwrap().unwrap();
+
+    // The link was not used up by the refused attempt
+    assert_eq!(resets.reset_password(&token, "new-password-456").await.unwrap(), alice.id);
+    let hash = users.find_by_id(alice.id).await.unwrap().unwrap().password_hash;
+    assert!(hasher.verify("new-password-456", &hash).await.unwrap());
+    let result = resets.reset_password(&token, "new-password-789").await;
+    assert!(matches!(result, Err(AuthError::InvalidToken)), "{:?}", result);
+}
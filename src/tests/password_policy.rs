@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::Router;
+use axum::extract::Path;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::get;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use super::helpers::{setup_test_db, setup_test_redis, test_request, users};
+use crate::config::Config;
+use crate::models::user::UserFilter;
+use crate::repositories::UserRepository;
+use crate::services::auth_service::AuthError;
+use crate::services::email_service::EmailService;
+use crate::services::password_hasher::PasswordHasher;
+use crate::services::password_policy::{
+    BreachCheckError, BreachedPasswordClient, PasswordPolicy, PwnedPasswordsApi, PwnedPasswordsFile, RangeEntry,
+};
+
+fn sha1(password: &str) -> String {
+    hex::encode_upper(Sha1::digest(password.as_bytes()))
+}
+
+/// A breach corpus file, removed when dropped
+struct Corpus(PathBuf);
+
+impl Corpus {
+    fn write(contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("axum-boilerplate-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+
+    /// `breached` among a thousand other hashes, sorted as the real download is
+    fn sorted(breached: &[(&str, u64)]) -> (Self, Vec<(String, u64)>) {
+        let mut hashes: Vec<_> = breached
+            .iter()
+            .map(|(password, count)| (sha1(password), *count))
+            .chain((0..1000).map(|i| (sha1(&format!("filler-{}", i)), i + 1)))
+            .collect();
+        hashes.sort();
+        let contents: String = hashes.iter().map(|(hash, count)| format!("{}:{}\r\n", hash, count)).collect();
+        (Self::write(&contents), hashes)
+    }
+
+    fn policy(&self) -> PasswordPolicy {
+        PasswordPolicy::default().with_breach_check(Arc::new(PwnedPasswordsFile::new(&self.0)))
+    }
+}
+
+impl Drop for Corpus {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Records the prefixes it is asked for, and answers with nothing or an error
+#[derive(Default)]
+struct RecordingClient {
+    prefixes: Mutex<Vec<String>>,
+    unavailable: bool,
+}
+
+#[async_trait]
+impl BreachedPasswordClient for RecordingClient {
+    async fn range(&self, prefix: &str) -> Result<Vec<RangeEntry>, BreachCheckError> {
+        self.prefixes.lock().unwrap().push(prefix.to_string());
+        if self.unavailable {
+            return Err(BreachCheckError::Http("connection refused".to_string()));
+        }
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_local_corpus_ranges() {
+    let (corpus, hashes) = Corpus::sorted(&[("password123", 100)]);
+    let file = PwnedPasswordsFile::new(&corpus.0);
+
+    // Including the first and last lines of the file
+    for (hash, count) in &hashes {
+        let (prefix, suffix) = hash.split_at(5);
+        let range = file.range(prefix).await.unwrap();
+        assert!(range.contains(&RangeEntry { suffix: suffix.to_string(), count: *count }), "{}", hash);
+        assert!(range.len() < hashes.len() / 10, "only the prefix's lines are read");
+    }
+
+    let missing = (0..)
+        .map(|i| format!("{:05X}", i))
+        .find(|prefix| !hashes.iter().any(|(hash, _)| hash.starts_with(prefix.as_str())))
+        .unwrap();
+    assert_eq!(file.range(&missing).await.unwrap(), []);
+    assert_eq!(file.range("FFFFF").await.unwrap(), []);
+
+    // Lower-case hashes, blank lines and hashes without a count
+    let hash = sha1("letmein");
+    let corpus = Corpus::write(&format!("\n{}\n\n", hash.to_ascii_lowercase()));
+    let range = PwnedPasswordsFile::new(&corpus.0).range(&hash[..5].to_ascii_lowercase()).await.unwrap();
+    assert_eq!(range, [RangeEntry { suffix: hash[5..].to_string(), count: 1 }]);
+
+    let result = PwnedPasswordsFile::new("/nonexistent/pwned-passwords.txt").range("00000").await;
+    assert!(matches!(result, Err(BreachCheckError::Io(_))), "{:?}", result);
+}
+
+#[tokio::test]
+async fn test_policy_refuses_breached_passwords() {
+    let (corpus, _) = Corpus::sorted(&[("password123", 100), ("letmein", 3)]);
+
+    let policy = corpus.policy();
+    assert!(matches!(policy.check("password123").await, Err(AuthError::BreachedPassword)));
+    assert!(matches!(policy.check("letmein").await, Err(AuthError::BreachedPassword)));
+    assert!(policy.check("correct horse battery staple").await.is_ok());
+    assert!(policy.check("Password123").await.is_ok(), "passwords are compared exactly");
+
+    let policy = corpus.policy().with_min_breach_count(10);
+    assert!(policy.check("password123").await.is_err());
+    assert!(policy.check("letmein").await.is_ok());
+
+    assert!(PasswordPolicy::default().check("password123").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_lookups_send_only_a_hash_prefix() {
+    let client = Arc::new(RecordingClient::default());
+    let policy = PasswordPolicy::default().with_breach_check(client.clone());
+    policy.check("password123").await.unwrap();
+    assert_eq!(*client.prefixes.lock().unwrap(), [sha1("password123")[..5].to_string()]);
+
+    // An unreachable corpus lets passwords through rather than blocking sign-ups
+    let client = Arc::new(RecordingClient { unavailable: true, ..Default::default() });
+    let policy = PasswordPolicy::default().with_breach_check(client.clone());
+    assert!(policy.check("password123").await.is_ok());
+    assert_eq!(client.prefixes.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_range_api_client() {
+    // Padding entries have a count of zero, and do not count as breached
+    let ranges: HashMap<String, String> = [("password123", 100), ("padding-only", 0)]
+        .into_iter()
+        .map(|(password, count)| {
+            let hash = sha1(password);
+            let (prefix, suffix) = hash.split_at(5);
+            (prefix.to_string(), format!("0018A45C4D1DEF81644B54AB7F969B88D65:2\r\n{}:{}\r\n", suffix, count))
+        })
+        .collect();
+    let padded = Arc::new(Mutex::new(Vec::new()));
+    let requests = padded.clone();
+    let api = Router::new().route(
+        "/range/:prefix",
+        get(move |Path(prefix): Path<String>, headers: HeaderMap| async move {
+            requests.lock().unwrap().push(headers.contains_key("add-padding"));
+            ranges.get(&prefix).cloned().ok_or(StatusCode::BAD_REQUEST)
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/range/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, api).await });
+
+    let client = PwnedPasswordsApi::new(&url);
+    let hash = sha1("password123");
+    let entries = client.range(&hash[..5]).await.unwrap();
+    assert_eq!(entries[1], RangeEntry { suffix: hash[5..].to_string(), count: 100 });
+    assert_eq!(entries.len(), 2);
+    assert_eq!(*padded.lock().unwrap(), [true]);
+    assert!(matches!(client.range("ZZZZZ").await, Err(BreachCheckError::Http(_))));
+
+    let policy = PasswordPolicy::default().with_breach_check(Arc::new(client));
+    assert!(matches!(policy.check("password123").await, Err(AuthError::BreachedPassword)));
+    assert!(policy.check("padding-only").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_new_passwords_are_checked() {
+    let (corpus, _) = Corpus::sorted(&[("password123", 100)]);
+    let pool = setup_test_db().await;
+    let email_service = EmailService::in_memory();
+    let config = Config { breached_passwords_file: Some(corpus.0.clone()), ..Config::from_env() };
+    let state = crate::build_state(pool.clone(), setup_test_redis(), config, email_service.clone());
+    let app = crate::router(state.clone());
+
+    // Registration
+    let body = json!({ "username": "alice", "email": "alice@example.com", "password": "password123" });
+    let (status, body, _) = test_request(app.clone(), "POST", "/register", Some(body), None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("\"error\":\"breached_password\""), "{}", body);
+    assert_eq!(users(&pool).count(&UserFilter::default()).await.unwrap(), 0);
+
+    let body = json!({ "username": "alice", "email": "alice@example.com", "password": "unbreached-passphrase" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/register", Some(body), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let alice = users(&pool).find_by_email("alice@example.com").await.unwrap().unwrap();
+
+    // Password changes
+    let result = state.auth_service.set_password(alice.id, "password123").await;
+    assert!(matches!(result, Err(AuthError::BreachedPassword)), "{:?}", result);
+
+    // Resets, which check the link first, so they cannot be used to probe the corpus
+    let reset = json!({ "token": "not-a-real-token", "new_password": "password123" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // and keep the link usable for another try
+    state.password_reset_service.send_reset_link(&alice).await.unwrap();
+    let email = email_service.sent_emails().pop().unwrap();
+    let token = email.body.split("token=").nth(1).unwrap().trim().to_string();
+
+    let reset = json!({ "token": token, "new_password": "password123" });
+    let (status, body, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset), None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("\"error\":\"breached_password\""), "{}", body);
+
+    let reset = json!({ "token": token, "new_password": "another-unbreached-passphrase" });
+    let (status, _, _) = test_request(app.clone(), "POST", "/password-reset", Some(reset), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Existing passwords still sign in; only new ones are checked
+    let hash = PasswordHasher::default().hash("password123").await.unwrap();
+    users(&pool).update_password(alice.id, &hash).await.unwrap();
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    assert_eq!(test_request(app, "POST", "/login", Some(login), None, None).await.0, StatusCode::OK);
+}
@@ -0,0 +1,239 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode, uri},
+    routing::get,
+};
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tower::ServiceExt;
+use super::helpers::{register_and_login, setup_test_db, setup_test_redis};
+use crate::api::forwarded::{ClientIp, Scheme};
+use crate::config::{Config, TrustedProxies, parse_setting};
+use crate::server::{Listener, serve};
+use crate::services::cookie_service::ACCESS_TOKEN_COOKIE;
+use crate::services::email_service::EmailService;
+
+const PROXIES: &str = "10.0.0.0/8, fd00::/8";
+
+/// Echoes the resolved client address and scheme
+fn echo_router(trusted: &str) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(|ClientIp(ip): ClientIp, scheme: Scheme| async move { format!("{} {}", ip, scheme.0) }),
+        )
+        .route("/scheme", get(|scheme: Scheme| async move { scheme.0.to_string() }))
+        .with_state(trusted.parse::<TrustedProxies>().unwrap())
+}
+
+async fn resolve(app: Router, uri: &str, peer: Option<&str>, headers: &[(&str, &str)]) -> (StatusCode, String) {
+    let mut request = Request::get(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    if let Some(peer) = peer {
+        request.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+    }
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_headers_from_untrusted_peer_are_ignored() {
+    let headers = [
+        ("x-forwarded-for", "203.0.113.7"),
+        ("x-forwarded-proto", "https"),
+        ("forwarded", "for=198.51.100.1;proto=https"),
+        ("x-real-ip", "192.0.2.1"),
+    ];
+    let (status, body) = resolve(echo_router(PROXIES), "/", Some("198.51.100.20:5000"), &headers).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "198.51.100.20 http");
+}
+
+#[tokio::test]
+async fn test_x_forwarded_for_from_trusted_proxy() {
+    // Only the nearest untrusted hop counts; the client can prepend anything
+    let headers = [
+        ("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.2"),
+        ("x-forwarded-proto", "http, https, http"),
+    ];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "203.0.113.7 https");
+
+    // Values split across several header lines form one list
+    let headers = [("x-forwarded-for", "1.2.3.4"), ("x-forwarded-for", "203.0.113.7")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "203.0.113.7 http");
+
+    // A single protocol value belongs to the nearest proxy
+    let headers = [("x-forwarded-for", "203.0.113.7"), ("x-forwarded-proto", "https")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "203.0.113.7 https");
+}
+
+#[tokio::test]
+async fn test_forwarded_header_takes_precedence() {
+    let headers = [
+        ("forwarded", "for=\"[2001:db8::1]:4711\";proto=https, for=10.0.0.2;proto=http"),
+        ("x-forwarded-for", "192.0.2.1"),
+    ];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("[fd00::1]:5000"), &headers).await;
+    assert_eq!(body, "2001:db8::1 https");
+
+    // An obfuscated node hides the client, so the last known proxy is reported
+    let headers = [("forwarded", "for=_hidden, for=10.0.0.2")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "10.0.0.2 http");
+}
+
+#[tokio::test]
+async fn test_x_real_ip_and_fallbacks() {
+    let headers = [("x-real-ip", "203.0.113.9")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "203.0.113.9 http");
+
+    // A proxy may forward only the protocol
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &[("x-forwarded-proto", "https")]).await;
+    assert_eq!(body, "10.0.0.1 https");
+
+    // A trusted peer without forwarding headers is the client
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &[]).await;
+    assert_eq!(body, "10.0.0.1 http");
+
+    // IPv4-mapped peers match IPv4 networks
+    let headers = [("x-forwarded-for", "203.0.113.7")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("[::ffff:10.0.0.1]:5000"), &headers).await;
+    assert_eq!(body, "203.0.113.7 http");
+
+    // Every hop trusted: the leftmost one is the client
+    let headers = [("x-forwarded-for", "10.1.1.1, 10.0.0.2")];
+    let (_, body) = resolve(echo_router(PROXIES), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "10.1.1.1 http");
+}
+
+#[tokio::test]
+async fn test_no_trusted_proxies_by_default() {
+    let headers = [("x-forwarded-for", "203.0.113.7"), ("x-forwarded-proto", "https")];
+    let (_, body) = resolve(echo_router(""), "/", Some("10.0.0.1:5000"), &headers).await;
+    assert_eq!(body, "10.0.0.1 http");
+}
+
+#[tokio::test]
+async fn test_without_connect_info() {
+    // Neither a TCP address nor a Unix socket peer, e.g. a bare `oneshot`
+    let (status, _) = resolve(echo_router(PROXIES), "/", None, &[("x-forwarded-for", "203.0.113.7")]).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let (_, body) = resolve(echo_router(PROXIES), "/scheme", None, &[("x-forwarded-proto", "https")]).await;
+    assert_eq!(body, "http");
+}
+
+/// Over a Unix socket, usually from a proxy on the same host
+async fn get_over_unix_socket(path: &std::path::Path, uri: &str, headers: &[(&str, &str)]) -> String {
+    let mut request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n", uri);
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+
+    let mut stream = UnixStream::connect(path).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_unix_socket_peer_is_trusted() {
+    let path = std::env::temp_dir().join(format!("axum-boilerplate-{}.sock", uuid::Uuid::new_v4()));
+    let listener = Listener::Unix(UnixListener::bind(&path).unwrap());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    // No TRUSTED_PROXIES needed for the local peer
+    let server = tokio::spawn(serve(
+        listener,
+        None,
+        echo_router(""),
+        async {
+            shutdown_rx.await.ok();
+        },
+        Duration::from_secs(1),
+    ));
+
+    let headers = [("x-forwarded-for", "203.0.113.7"), ("x-forwarded-proto", "https")];
+    let response = get_over_unix_socket(&path, "/", &headers).await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.ends_with("203.0.113.7 https"), "{}", response);
+
+    let response = get_over_unix_socket(&path, "/", &[]).await;
+    assert!(response.ends_with("127.0.0.1 http"), "{}", response);
+
+    drop(shutdown_tx);
+    server.await.unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_scheme_from_tls_connection() {
+    let mut request = Request::get("/scheme").body(Body::empty()).unwrap();
+    request.extensions_mut().insert(uri::Scheme::HTTPS);
+    let response = echo_router(PROXIES).oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body, "https");
+}
+
+#[test]
+fn test_parse_trusted_proxies() {
+    let proxies: TrustedProxies = "10.0.0.0/8, 192.0.2.1 ,::1".parse().unwrap();
+    assert!(proxies.contains(&"10.20.30.40".parse().unwrap()));
+    assert!(proxies.contains(&"192.0.2.1".parse().unwrap()));
+    assert!(!proxies.contains(&"192.0.2.2".parse().unwrap()));
+    assert!(proxies.contains(&"::1".parse().unwrap()));
+
+    assert!("10.0.0.0/8, not-an-ip".parse::<TrustedProxies>().is_err());
+
+    // A typo stops startup instead of quietly trusting no proxies
+    let result = parse_setting("TRUSTED_PROXIES", Some("10.0.0.0/8, not-an-ip"), TrustedProxies::default());
+    let error = result.unwrap_err();
+    assert!(error.starts_with("TRUSTED_PROXIES=") && error.contains("not-an-ip"), "{}", error);
+    let proxies = parse_setting("TRUSTED_PROXIES", None, TrustedProxies::default()).unwrap();
+    assert!(!proxies.contains(&"10.0.0.1".parse().unwrap()));
+}
+
+#[tokio::test]
+async fn test_audit_log_records_forwarded_client_ip() {
+    let pool = setup_test_db().await;
+    let config = Config { trusted_proxies: PROXIES.parse().unwrap(), ..Config::from_env() };
+    let app = crate::build_router(pool, setup_test_redis(), config, EmailService::in_memory());
+    let (access_token, _) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    let mut request = Request::post("/login")
+        .header("content-type", "application/json")
+        .header("x-forwarded-for", "203.0.113.7")
+        .body(Body::from(login.to_string()))
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo("10.0.0.1:5000".parse::<SocketAddr>().unwrap()));
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::get("/me/security-events")
+        .header("cookie", format!("{}={}", ACCESS_TOKEN_COOKIE, access_token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let page: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["events"][0]["event_type"], "login");
+    assert_eq!(page["events"][0]["ip"], "203.0.113.7");
+}
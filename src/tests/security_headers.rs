@@ -0,0 +1,119 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Request, StatusCode, uri::Scheme},
+};
+use serde_json::json;
+use tower::ServiceExt;
+use super::helpers::{create_test_app, register_and_login, setup_test_db, setup_test_redis, test_request};
+use crate::config::Config;
+use crate::services::cookie_service::ACCESS_TOKEN_COOKIE;
+use crate::services::email_service::EmailService;
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+async fn get_over_tls(app: Router, uri: &str) -> HeaderMap {
+    let mut request = Request::get(uri).body(Body::empty()).unwrap();
+    request.extensions_mut().insert(Scheme::HTTPS);
+    app.oneshot(request).await.unwrap().headers().clone()
+}
+
+#[tokio::test]
+async fn test_default_security_headers() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    for uri in ["/", "/does-not-exist"] {
+        let (_, _, headers) = test_request(app.clone(), "GET", uri, None, None, None).await;
+        assert_eq!(header(&headers, "x-content-type-options"), Some("nosniff"));
+        assert_eq!(header(&headers, "x-frame-options"), Some("DENY"));
+        assert_eq!(
+            header(&headers, "content-security-policy"),
+            Some("default-src 'none'; frame-ancestors 'none'")
+        );
+        assert_eq!(header(&headers, "referrer-policy"), Some("no-referrer"));
+        assert!(header(&headers, "permissions-policy").unwrap().contains("camera=()"));
+        // Browsers ignore HSTS on plain HTTP
+        assert!(headers.get("strict-transport-security").is_none());
+        assert!(headers.get("cache-control").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_hsts_sent_over_https() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    let headers = get_over_tls(app, "/").await;
+    assert_eq!(header(&headers, "strict-transport-security"), Some("max-age=31536000"));
+}
+
+#[tokio::test]
+async fn test_auth_responses_are_not_cached() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (access_token, _) = register_and_login(&app, "alice", "alice@example.com", "password123").await;
+
+    let login = json!({ "email": "alice@example.com", "password": "password123" });
+    let (status, _, headers) = test_request(app.clone(), "POST", "/login", Some(login), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(header(&headers, "cache-control"), Some("no-store"));
+
+    let (status, _, headers) = test_request(app.clone(), "POST", "/refresh", None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(header(&headers, "cache-control"), Some("no-store"));
+
+    let cookies = [(ACCESS_TOKEN_COOKIE, access_token.as_str())];
+    let (status, _, headers) = test_request(app.clone(), "GET", "/me", None, None, Some(&cookies)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(header(&headers, "cache-control"), Some("no-store"));
+
+    // Rejected before reaching the handler, still not cacheable
+    let (status, _, headers) = test_request(app.clone(), "GET", "/me", None, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(header(&headers, "cache-control"), Some("no-store"));
+
+    let (_, _, headers) = test_request(app, "GET", "/health/live", None, None, None).await;
+    assert!(headers.get("cache-control").is_none());
+}
+
+#[tokio::test]
+async fn test_security_headers_are_configurable() {
+    let pool = setup_test_db().await;
+    let config = Config {
+        hsts_max_age_secs: 63_072_000,
+        hsts_include_subdomains: true,
+        hsts_preload: true,
+        frame_options: None,
+        content_security_policy: Some("frame-ancestors 'self'".to_string()),
+        permissions_policy: None,
+        auth_cache_control: Some("no-store, private".to_string()),
+        ..Config::from_env()
+    };
+    let app = crate::build_router(pool, setup_test_redis(), config, EmailService::in_memory());
+
+    let headers = get_over_tls(app.clone(), "/").await;
+    assert_eq!(
+        header(&headers, "strict-transport-security"),
+        Some("max-age=63072000; includeSubDomains; preload")
+    );
+    assert!(headers.get("x-frame-options").is_none());
+    assert!(headers.get("permissions-policy").is_none());
+    assert_eq!(header(&headers, "content-security-policy"), Some("frame-ancestors 'self'"));
+    assert_eq!(header(&headers, "x-content-type-options"), Some("nosniff"));
+
+    let (_, _, headers) = test_request(app, "POST", "/refresh", None, None, None).await;
+    assert_eq!(header(&headers, "cache-control"), Some("no-store, private"));
+}
+
+#[tokio::test]
+async fn test_hsts_can_be_disabled() {
+    let pool = setup_test_db().await;
+    let config = Config { hsts_max_age_secs: 0, ..Config::from_env() };
+    let app = crate::build_router(pool, setup_test_redis(), config, EmailService::in_memory());
+
+    let headers = get_over_tls(app, "/").await;
+    assert!(headers.get("strict-transport-security").is_none());
+}
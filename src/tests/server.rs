@@ -0,0 +1,301 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::ConnectInfo,
+    http::{HeaderMap, HeaderValue, Request, StatusCode, uri::Scheme},
+    routing::get,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rcgen::CertifiedKey;
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UnixStream};
+use tokio::sync::oneshot;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tower::ServiceExt;
+use super::helpers::{setup_test_db, setup_test_redis, test_request};
+use crate::config::{Config, CookieSecure};
+use crate::server::{Listener, TlsAcceptor, serve};
+use crate::services::email_service::EmailService;
+
+/// Echoes how the request reached the server
+fn info_router() -> Router {
+    Router::new().route(
+        "/info",
+        get(|request: Request<Body>| async move {
+            let scheme = request.extensions().get::<Scheme>().map(Scheme::to_string).unwrap_or_default();
+            let has_peer = request.extensions().get::<ConnectInfo<SocketAddr>>().is_some();
+            format!("{} {:?} peer={}", scheme, request.version(), has_peer)
+        }),
+    )
+}
+
+/// Serve `info_router` until the returned sender is dropped
+fn start_server(listener: Listener, tls: Option<TlsAcceptor>) -> oneshot::Sender<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    tokio::spawn(serve(
+        listener,
+        tls,
+        info_router(),
+        async {
+            shutdown_rx.await.ok();
+        },
+        Duration::from_secs(1),
+    ));
+    shutdown_tx
+}
+
+async fn bind_tcp() -> (Listener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    (Listener::Tcp(listener), addr)
+}
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("axum-boilerplate-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_cert(dir: &Path) -> (PathBuf, PathBuf, CertificateDer<'static>) {
+    let CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    std::fs::write(&cert_path, cert.pem()).unwrap();
+    std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+    (cert_path, key_path, cert.der().clone())
+}
+
+async fn connect_tls(addr: &str, trusted: &[&CertificateDer<'static>], alpn: &[&[u8]]) -> TlsStream<TcpStream> {
+    let mut roots = RootCertStore::empty();
+    for cert in trusted {
+        roots.add((*cert).clone()).unwrap();
+    }
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+        .unwrap()
+}
+
+fn peer_certificate(stream: &TlsStream<TcpStream>) -> CertificateDer<'static> {
+    stream.get_ref().1.peer_certificates().unwrap()[0].clone()
+}
+
+/// Send a single HTTP/1.1 request and read until the server closes the connection
+async fn http1_get(mut stream: impl AsyncRead + AsyncWrite + Unpin, path: &str) -> String {
+    stream
+        .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).as_bytes())
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    // A TLS peer may close without close_notify; what was read is still kept
+    let _ = stream.read_to_end(&mut response).await;
+    String::from_utf8(response).unwrap()
+}
+
+#[tokio::test]
+async fn test_serves_plain_http() {
+    let (listener, addr) = bind_tcp().await;
+    let _server = start_server(listener, None);
+
+    let response = http1_get(TcpStream::connect(&addr).await.unwrap(), "/info").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+    assert!(response.ends_with("http HTTP/1.1 peer=true"));
+}
+
+#[tokio::test]
+async fn test_serves_unix_socket() {
+    let path = temp_dir().join("app.sock");
+    let config = Config { unix_socket: Some(path.clone()), ..Config::from_env() };
+
+    // A socket file left behind by a previous run does not block the bind
+    drop(Listener::bind(&config).await.unwrap());
+    let listener = Listener::bind(&config).await.unwrap();
+    assert_eq!(listener.local_addr().unwrap(), format!("unix:{}", path.display()));
+    let _server = start_server(listener, None);
+
+    let response = http1_get(UnixStream::connect(&path).await.unwrap(), "/info").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+    assert!(response.ends_with("http HTTP/1.1 peer=false"));
+}
+
+#[tokio::test]
+async fn test_terminates_tls() {
+    let (cert_path, key_path, cert) = write_cert(&temp_dir());
+    let tls = TlsAcceptor::from_pem_files(cert_path, key_path, Duration::ZERO).unwrap();
+    let (listener, addr) = bind_tcp().await;
+    let _server = start_server(listener, Some(tls));
+
+    let stream = connect_tls(&addr, &[&cert], &[b"http/1.1"]).await;
+    let response = http1_get(stream, "/info").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+    assert!(response.ends_with("https HTTP/1.1 peer=true"));
+}
+
+#[tokio::test]
+async fn test_negotiates_http2_over_tls() {
+    let (cert_path, key_path, cert) = write_cert(&temp_dir());
+    let tls = TlsAcceptor::from_pem_files(cert_path, key_path, Duration::ZERO).unwrap();
+    let (listener, addr) = bind_tcp().await;
+    let _server = start_server(listener, Some(tls));
+
+    let stream = connect_tls(&addr, &[&cert], &[b"h2", b"http/1.1"]).await;
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+
+    let (mut sender, connection) = hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+
+    let request = Request::get("https://localhost/info").body(Body::empty()).unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(Body::new(response.into_body()), usize::MAX).await.unwrap();
+    assert_eq!(body, "https HTTP/2.0 peer=true");
+}
+
+#[tokio::test]
+async fn test_reloads_certificate_when_files_change() {
+    let dir = temp_dir();
+    let (cert_path, key_path, first_cert) = write_cert(&dir);
+    let tls = TlsAcceptor::from_pem_files(&cert_path, &key_path, Duration::from_millis(50)).unwrap();
+    let (listener, addr) = bind_tcp().await;
+    let _server = start_server(listener, Some(tls));
+
+    let stream = connect_tls(&addr, &[&first_cert], &[]).await;
+    assert_eq!(peer_certificate(&stream), first_cert);
+
+    let (_, _, second_cert) = write_cert(&dir);
+    let trusted = [&first_cert, &second_cert];
+    let mut reloaded = false;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        if peer_certificate(&connect_tls(&addr, &trusted, &[]).await) == second_cert {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "new certificate should be served without a restart");
+
+    // A broken certificate is rejected and the last good one kept
+    std::fs::write(&cert_path, "not a certificate").unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(peer_certificate(&connect_tls(&addr, &trusted, &[]).await), second_cert);
+}
+
+#[test]
+fn test_rejects_missing_certificate() {
+    let dir = temp_dir();
+    let result = TlsAcceptor::from_pem_files(dir.join("missing.pem"), dir.join("key.pem"), Duration::ZERO);
+    assert!(result.is_err());
+}
+
+async fn create_app(cookie_secure: CookieSecure) -> Router {
+    let pool = setup_test_db().await;
+    let config = Config {
+        cookie_secure,
+        trusted_proxies: "10.0.0.0/8".parse().unwrap(),
+        ..Config::from_env()
+    };
+    crate::build_router(pool, setup_test_redis(), config, EmailService::in_memory())
+}
+
+/// Register and log in, returning the login response's Set-Cookie values
+async fn login_cookies(app: &Router, peer: &str, headers: Option<HeaderMap>, scheme: Option<Scheme>) -> Vec<String> {
+    let register_data = json!({
+        "username": "cookieuser",
+        "email": "cookie@example.com",
+        "password": "password123"
+    });
+    let (status, _, _) = test_request(app.clone(), "POST", "/register", Some(register_data), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let login_data = json!({ "email": "cookie@example.com", "password": "password123" });
+    let mut request = Request::post("/login")
+        .header("content-type", "application/json")
+        .body(Body::from(login_data.to_string()))
+        .unwrap();
+    request.headers_mut().extend(headers.unwrap_or_default());
+    request.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+    if let Some(scheme) = scheme {
+        request.extensions_mut().insert(scheme);
+    }
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect()
+}
+
+fn forwarded_https() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    headers
+}
+
+fn all_secure(cookies: &[String]) -> bool {
+    assert_eq!(cookies.len(), 2);
+    cookies.iter().all(|cookie| cookie.contains("Secure"))
+}
+
+fn none_secure(cookies: &[String]) -> bool {
+    assert_eq!(cookies.len(), 2);
+    cookies.iter().all(|cookie| !cookie.contains("Secure"))
+}
+
+const CLIENT: &str = "203.0.113.7:5000";
+const PROXY: &str = "10.0.0.1:5000";
+
+#[tokio::test]
+async fn test_cookies_not_secure_over_plain_http() {
+    let app = create_app(CookieSecure::Auto).await;
+    assert!(none_secure(&login_cookies(&app, CLIENT, None, Some(Scheme::HTTP)).await));
+}
+
+#[tokio::test]
+async fn test_cookies_secure_over_tls() {
+    let app = create_app(CookieSecure::Auto).await;
+    assert!(all_secure(&login_cookies(&app, CLIENT, None, Some(Scheme::HTTPS)).await));
+}
+
+#[tokio::test]
+async fn test_cookies_secure_behind_https_proxy() {
+    let app = create_app(CookieSecure::Auto).await;
+    assert!(all_secure(&login_cookies(&app, PROXY, Some(forwarded_https()), Some(Scheme::HTTP)).await));
+}
+
+#[tokio::test]
+async fn test_forwarded_proto_ignored_from_untrusted_peer() {
+    let app = create_app(CookieSecure::Auto).await;
+    assert!(none_secure(&login_cookies(&app, CLIENT, Some(forwarded_https()), Some(Scheme::HTTP)).await));
+}
+
+#[tokio::test]
+async fn test_cookie_secure_override() {
+    let app = create_app(CookieSecure::Always).await;
+    assert!(all_secure(&login_cookies(&app, CLIENT, None, Some(Scheme::HTTP)).await));
+
+    let app = create_app(CookieSecure::Never).await;
+    assert!(none_secure(&login_cookies(&app, PROXY, Some(forwarded_https()), Some(Scheme::HTTPS)).await));
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use axum::{Router, http::StatusCode, routing::get};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use super::helpers::{setup_test_db, setup_test_redis, test_request};
+use crate::config::Config;
+use crate::server::{Listener, serve};
+use crate::services::email_service::EmailService;
+
+/// Serve a router with one slow route until the returned sender fires
+async fn start_server(request_time: Duration, drain_timeout: Duration) -> (String, oneshot::Sender<()>, JoinHandle<()>) {
+    let app = Router::new().route(
+        "/slow",
+        get(move || async move {
+            tokio::time::sleep(request_time).await;
+            "done"
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let handle = tokio::spawn(serve(
+        Listener::Tcp(listener),
+        None,
+        app,
+        async {
+            shutdown_rx.await.ok();
+        },
+        drain_timeout,
+    ));
+    (addr, shutdown_tx, handle)
+}
+
+async fn send_slow_request(addr: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    stream
+}
+
+#[tokio::test]
+async fn test_readiness_reports_shutting_down() {
+    let pool = setup_test_db().await;
+    let state = crate::build_state(pool, setup_test_redis(), Config::from_env(), EmailService::in_memory());
+    let health_service = state.health_service.clone();
+    let app = crate::router(state);
+
+    health_service.begin_shutdown();
+
+    let (status, body, _) = test_request(app.clone(), "GET", "/health/ready", None, None, None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "shutting_down");
+    assert!(body.get("checks").is_none());
+
+    // The process is still alive while draining
+    let (status, _, _) = test_request(app, "GET", "/health/live", None, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_readiness_fails_before_the_listener_closes() {
+    let pool = setup_test_db().await;
+    let state = crate::build_state(pool, setup_test_redis(), Config::from_env(), EmailService::in_memory());
+    let health_service = state.health_service.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (signal_tx, signal_rx) = oneshot::channel::<()>();
+    let shutdown = async move {
+        let signal = async {
+            signal_rx.await.ok();
+        };
+        health_service.shutdown_after(signal, Duration::from_millis(500)).await
+    };
+    let handle = tokio::spawn(serve(
+        Listener::Tcp(listener),
+        None,
+        crate::router(state),
+        shutdown,
+        Duration::from_secs(5),
+    ));
+
+    let ready = || async {
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        stream
+            .write_all(b"GET /health/ready HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+    assert!(ready().await.starts_with("HTTP/1.1 200 OK"));
+
+    // A new connection after the signal is still served, and told to go away
+    signal_tx.send(()).unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let response = ready().await;
+    assert!(response.starts_with("HTTP/1.1 503"), "unexpected response: {}", response);
+    assert!(response.contains("\"shutting_down\""), "{}", response);
+
+    handle.await.unwrap();
+    assert!(TcpStream::connect(&addr).await.is_err(), "no new connections after the delay");
+}
+
+#[tokio::test]
+async fn test_in_flight_requests_complete_during_shutdown() {
+    let (addr, shutdown_tx, handle) = start_server(Duration::from_millis(300), Duration::from_secs(5)).await;
+
+    let mut stream = send_slow_request(&addr).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown_tx.send(()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+    assert!(response.ends_with("done"));
+
+    handle.await.unwrap();
+    assert!(TcpStream::connect(&addr).await.is_err(), "no new connections after shutdown");
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_waiting_after_drain_timeout() {
+    let (addr, shutdown_tx, handle) = start_server(Duration::from_secs(30), Duration::from_millis(200)).await;
+
+    let _stream = send_slow_request(&addr).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown_tx.send(()).unwrap();
+
+    let result = tokio::time::timeout(Duration::from_secs(5), handle).await;
+    assert!(result.is_ok(), "serve should return once the drain timeout elapses");
+}
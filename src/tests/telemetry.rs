@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    routing::post,
+};
+use opentelemetry::trace::TracerProvider as _;
+use tracing_subscriber::layer::SubscriberExt;
+use super::helpers::{setup_test_db, create_test_app, test_request};
+use crate::telemetry::tracer_provider;
+
+type Received = Arc<Mutex<Vec<Bytes>>>;
+
+/// Stand-in OTLP collector that keeps every export request body it receives
+async fn start_collector() -> (String, Received) {
+    let received: Received = Arc::default();
+    let app = Router::new()
+        .route(
+            "/v1/traces",
+            post(|State(received): State<Received>, body: Bytes| async move {
+                received.lock().unwrap().push(body);
+                StatusCode::OK
+            }),
+        )
+        .with_state(received.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (endpoint, received)
+}
+
+#[tokio::test]
+async fn test_request_id_generated_and_propagated() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+
+    // A request id is generated when the client sends none
+    let (status, _, headers) = test_request(app.clone(), "GET", "/", None, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let generated = headers["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generated).is_ok());
+
+    // A client-supplied id is echoed back, including on error responses
+    let mut request_headers = HeaderMap::new();
+    request_headers.insert("x-request-id", HeaderValue::from_static("client-abc-123"));
+    let (status, _, headers) = test_request(app.clone(), "GET", "/me", None, Some(request_headers), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(headers["x-request-id"], "client-abc-123");
+
+    // Oversized ids are replaced rather than reflected
+    let mut request_headers = HeaderMap::new();
+    request_headers.insert("x-request-id", HeaderValue::from_str(&"a".repeat(200)).unwrap());
+    let (_, _, headers) = test_request(app, "GET", "/no-such-page", None, Some(request_headers), None).await;
+    assert!(uuid::Uuid::parse_str(headers["x-request-id"].to_str().unwrap()).is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_spans_exported_with_incoming_trace_context() {
+    let pool = setup_test_db().await;
+    let app = create_test_app(pool);
+    let (endpoint, received) = start_collector().await;
+
+    // Sample nothing locally: the span is only exported because the caller's
+    // traceparent says the trace is sampled
+    let provider = tracer_provider(&endpoint, 0.0, "test-service").unwrap();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        HeaderValue::from_str(&format!("00-{}-00f067aa0ba902b7-01", trace_id)).unwrap(),
+    );
+    {
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let (status, _, _) = test_request(app, "GET", "/", None, Some(headers), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Flushing blocks until the batch exporter has sent everything
+    let flush_provider = provider.clone();
+    tokio::task::spawn_blocking(move || flush_provider.force_flush()).await.unwrap();
+
+    let received = received.lock().unwrap();
+    assert!(!received.is_empty(), "collector should receive an export request");
+
+    // The protobuf payload carries the trace id as raw bytes
+    let trace_id_bytes = hex::decode(trace_id).unwrap();
+    let exported = received.iter().any(|body| {
+        body.windows(trace_id_bytes.len()).any(|window| window == trace_id_bytes.as_slice())
+            && body.windows(b"GET /".len()).any(|window| window == b"GET /")
+    });
+    assert!(exported, "exported span should continue the incoming trace");
+}